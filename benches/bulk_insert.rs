@@ -0,0 +1,61 @@
+//! Inserting a million tasks in one batch, and ticking a wheel that holds them.
+//!
+//! Run with `cargo bench --bench bulk_insert`.
+
+use std::hint::black_box;
+
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use minitimer::task::{Task, TaskBuilder};
+use minitimer::{task::SyncTaskRunner, timer::BlockingTimer};
+
+const TASK_COUNT: u64 = 1_000_000;
+
+struct NoopRunner;
+
+impl SyncTaskRunner for NoopRunner {
+    fn run(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+}
+
+/// Repeated tasks spread over the next two hours, so every level of the wheels is used.
+fn tasks() -> Vec<Task> {
+    (0..TASK_COUNT)
+        .map(|task_id| {
+            TaskBuilder::new(task_id)
+                .with_frequency_repeated_by_seconds(1 + task_id % 7200)
+                .spawn_sync(NoopRunner)
+                .unwrap()
+        })
+        .collect()
+}
+
+fn bulk_insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bulk_insert");
+    group.sample_size(10);
+    group.bench_function("add_tasks_1m", |b| {
+        b.iter_batched(
+            || (BlockingTimer::new(), tasks()),
+            |(timer, tasks)| {
+                timer.add_tasks(tasks);
+                timer
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.finish();
+}
+
+fn tick(c: &mut Criterion) {
+    let timer = BlockingTimer::new();
+    timer.add_tasks(tasks());
+    assert_eq!(timer.len(), TASK_COUNT as usize);
+
+    let mut group = c.benchmark_group("tick");
+    group.bench_function("tick_once_1m", |b| b.iter(|| timer.tick_once()));
+    group.finish();
+    black_box(timer);
+}
+
+criterion_group!(benches, bulk_insert, tick);
+criterion_main!(benches);
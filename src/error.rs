@@ -1,7 +1,134 @@
 use thiserror::Error;
 
+use crate::task::TaskId;
+
+/// Errors of scheduling and persisting tasks.
+///
+/// With the `serde` feature errors serialize as adjacently tagged JSON, e.g.
+/// `{"type":"TaskNotFound","data":7}`, so they can be passed on over the network. Variants
+/// carry plain data, the messages of underlying errors are kept as strings.
 #[derive(Error, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", content = "data")
+)]
 pub enum TaskError {
-    #[error("invalid frequency: {0}")]
-    InvalidFrequency(String),
+    /// `reason` and `value`, the value at fault if it is a number, are for matching on the
+    /// cause, `message` describes it.
+    #[error("invalid frequency: {message}")]
+    InvalidFrequency {
+        reason: InvalidFrequencyReason,
+        value: Option<u64>,
+        message: String,
+    },
+    #[error("no runner registered for task {0}")]
+    RunnerNotRegistered(TaskId),
+    #[error("schedule persistence failed: {0}")]
+    Persistence(String),
+    #[error("task {0} is already scheduled")]
+    DuplicateTask(TaskId),
+    #[error("delay of {requested}s exceeds the maximum of {max}s")]
+    DelayTooLong { requested: u64, max: u64 },
+    #[error("task {0} is not scheduled")]
+    TaskNotFound(TaskId),
+    #[error("the timer has been dropped")]
+    TimerDropped,
+    #[error("task {0} would close a dependency cycle")]
+    DependencyCycle(TaskId),
+    #[error("invalid wheel configuration: {0}")]
+    InvalidWheelConfig(String),
+    #[error("the maximum of {0} scheduled tasks is reached")]
+    CapacityExceeded(usize),
+    #[error("invalid placement: {0}")]
+    InvalidPlacement(String),
+    #[error("task {0} is past its deadline")]
+    TaskExpired(TaskId),
+    /// A runner panicked under `PanicStrategy::Isolate`, e.g. on a lock poisoned by an earlier
+    /// run, with the message of the panic.
+    #[error("runner panicked: {0}")]
+    RunnerFailed(String),
+}
+
+/// Why a frequency was rejected, see `TaskError::InvalidFrequency`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InvalidFrequencyReason {
+    /// An interval or delay shorter than a second.
+    ZeroInterval,
+    /// A count of zero, e.g. of a countdown.
+    ZeroCount,
+    /// The frequency doesn't fit into seconds.
+    Overflow,
+    /// A time that is not in the future.
+    PastTimestamp,
+    /// A calendar time that doesn't exist, e.g. hour 24.
+    InvalidTime,
+    /// No delays or times were given.
+    Empty,
+    /// No frequency was set.
+    Missing,
+    /// The operation doesn't apply to the schedule of the task, e.g. to a custom schedule.
+    Unsupported,
+}
+
+impl TaskError {
+    pub(crate) fn invalid_frequency(
+        reason: InvalidFrequencyReason,
+        value: Option<u64>,
+        message: impl Into<String>,
+    ) -> Self {
+        Self::InvalidFrequency {
+            reason,
+            value,
+            message: message.into(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_error_serde_round_trip() {
+        let error = TaskError::invalid_frequency(
+            InvalidFrequencyReason::PastTimestamp,
+            Some(5),
+            "timestamp 5 is not in the future",
+        );
+        assert_eq!(
+            error.to_string(),
+            "invalid frequency: timestamp 5 is not in the future"
+        );
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "type": "InvalidFrequency",
+                "data": {
+                    "reason": "PastTimestamp",
+                    "value": 5,
+                    "message": "timestamp 5 is not in the future"
+                }
+            })
+        );
+        let restored: TaskError = serde_json::from_value(json).unwrap();
+        assert!(matches!(
+            restored,
+            TaskError::InvalidFrequency {
+                reason: InvalidFrequencyReason::PastTimestamp,
+                value: Some(5),
+                ..
+            }
+        ));
+
+        let json = serde_json::to_string(&TaskError::TaskNotFound(TaskId::new(7))).unwrap();
+        assert_eq!(json, r#"{"type":"TaskNotFound","data":7}"#);
+        let restored: TaskError = serde_json::from_str(&json).unwrap();
+        assert!(matches!(restored, TaskError::TaskNotFound(task_id) if task_id == TaskId::new(7)));
+
+        let json = serde_json::to_string(&TaskError::TimerDropped).unwrap();
+        assert_eq!(json, r#"{"type":"TimerDropped"}"#);
+    }
 }
@@ -1,5 +1,8 @@
 pub mod error;
 pub mod mini_timer;
+pub mod prelude;
 pub mod task;
+#[cfg(feature = "test-util")]
+pub mod test_util;
 pub mod timer;
 pub mod utils;
@@ -0,0 +1,43 @@
+//! Re-exports of the commonly-used types.
+//!
+#![cfg_attr(
+    feature = "tokio",
+    doc = r#"
+```
+use minitimer::prelude::*;
+
+struct Greeter;
+
+#[async_trait::async_trait]
+impl TaskRunner for Greeter {
+    type Output = ();
+
+    async fn run(&self) -> Result<Self::Output, Box<dyn std::error::Error + Send + Sync>> {
+        println!("hello");
+        Ok(())
+    }
+}
+
+# fn main() -> Result<(), TaskError> {
+let task: Task = TaskBuilder::new(1)
+    .with_frequency_repeated_by_seconds(5)
+    .spwan_async(Greeter)?;
+let timer = Timer::new();
+timer.add_task(task)?;
+assert!(timer.contains(TaskId::new(1)));
+# Ok(())
+# }
+```
+"#
+)]
+
+#[cfg(feature = "tokio")]
+pub use crate::timer::Timer;
+pub use crate::{
+    error::{InvalidFrequencyReason, TaskError},
+    task::{
+        IntoTask, MissedPolicy, Task, TaskBuilder, TaskContext, TaskId, TaskRunner,
+        frequency::FrequencySeconds,
+    },
+    timer::TimerEvent,
+};
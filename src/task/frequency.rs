@@ -1,18 +1,347 @@
 use std::{
     iter::{Peekable, StepBy},
     ops::RangeFrom,
+    time::Duration,
 };
 
-use crate::utils::timestamp;
+use crate::{
+    error::{InvalidFrequencyReason, TaskError},
+    utils::timestamp,
+};
 
 pub(crate) type SecondsState = Peekable<StepBy<RangeFrom<u64>>>;
 const ONE_MINUTE: u64 = 60;
 
+/// Variants may be added by features, e.g. `Weekly` and `Monthly` need `chrono`, so matches need a
+/// wildcard arm.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "FrequencySecondsRepr", from = "FrequencySecondsRepr")
+)]
+#[non_exhaustive]
 pub enum FrequencySeconds {
     Once(u64),
     Repeated(u64),
     CountDown(u64, u64),
+    /// Every week on `weekday` at `hour:minute` UTC.
+    #[cfg(feature = "chrono")]
+    Weekly {
+        weekday: chrono::Weekday,
+        hour: u32,
+        minute: u32,
+    },
+    /// Every month on `day` at `hour:minute` UTC. In shorter months a `day` past the end of the
+    /// month falls on its last day, e.g. day 31 fires on February 28 or 29.
+    #[cfg(feature = "chrono")]
+    Monthly {
+        day: u32,
+        hour: u32,
+        minute: u32,
+    },
+}
+
+/// Tagged serde representation of `FrequencySeconds`, e.g. `{ "type": "Repeated", "seconds": 30 }`.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
+enum FrequencySecondsRepr {
+    Once {
+        seconds: u64,
+    },
+    Repeated {
+        seconds: u64,
+    },
+    CountDown {
+        count_down: u64,
+        seconds: u64,
+    },
+    #[cfg(feature = "chrono")]
+    Weekly {
+        weekday: chrono::Weekday,
+        hour: u32,
+        minute: u32,
+    },
+    #[cfg(feature = "chrono")]
+    Monthly {
+        day: u32,
+        hour: u32,
+        minute: u32,
+    },
+}
+
+#[cfg(feature = "serde")]
+impl From<FrequencySeconds> for FrequencySecondsRepr {
+    fn from(frequency: FrequencySeconds) -> Self {
+        match frequency {
+            FrequencySeconds::Once(seconds) => Self::Once { seconds },
+            FrequencySeconds::Repeated(seconds) => Self::Repeated { seconds },
+            FrequencySeconds::CountDown(count_down, seconds) => Self::CountDown {
+                count_down,
+                seconds,
+            },
+            #[cfg(feature = "chrono")]
+            FrequencySeconds::Weekly {
+                weekday,
+                hour,
+                minute,
+            } => Self::Weekly {
+                weekday,
+                hour,
+                minute,
+            },
+            #[cfg(feature = "chrono")]
+            FrequencySeconds::Monthly { day, hour, minute } => Self::Monthly { day, hour, minute },
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<FrequencySecondsRepr> for FrequencySeconds {
+    fn from(repr: FrequencySecondsRepr) -> Self {
+        match repr {
+            FrequencySecondsRepr::Once { seconds } => Self::Once(seconds),
+            FrequencySecondsRepr::Repeated { seconds } => Self::Repeated(seconds),
+            FrequencySecondsRepr::CountDown {
+                count_down,
+                seconds,
+            } => Self::CountDown(count_down, seconds),
+            #[cfg(feature = "chrono")]
+            FrequencySecondsRepr::Weekly {
+                weekday,
+                hour,
+                minute,
+            } => Self::Weekly {
+                weekday,
+                hour,
+                minute,
+            },
+            #[cfg(feature = "chrono")]
+            FrequencySecondsRepr::Monthly { day, hour, minute } => {
+                Self::Monthly { day, hour, minute }
+            }
+        }
+    }
+}
+
+impl FrequencySeconds {
+    /// The base interval between alarms, or the delay of a once frequency.
+    ///
+    /// Returns `Option` so that irregular frequencies without a base interval can return `None`.
+    pub fn interval(&self) -> Option<Duration> {
+        match *self {
+            Self::Once(seconds) | Self::Repeated(seconds) | Self::CountDown(_, seconds) => {
+                Some(Duration::from_secs(seconds))
+            }
+            #[cfg(feature = "chrono")]
+            Self::Weekly { .. } | Self::Monthly { .. } => None,
+        }
+    }
+
+    /// Check the fields of a frequency, e.g. that a calendar time exists.
+    pub(crate) fn validate(&self) -> Result<(), TaskError> {
+        match *self {
+            Self::Once(0) | Self::Repeated(0) | Self::CountDown(_, 0) => {
+                Err(TaskError::invalid_frequency(
+                    InvalidFrequencyReason::ZeroInterval,
+                    Some(0),
+                    format!("{self:?} must be greater than 0"),
+                ))
+            }
+            #[cfg(feature = "chrono")]
+            Self::Weekly { hour, minute, .. } if hour > 23 || minute > 59 => {
+                Err(TaskError::invalid_frequency(
+                    InvalidFrequencyReason::InvalidTime,
+                    None,
+                    format!("{self:?} is not a valid time of day"),
+                ))
+            }
+            #[cfg(feature = "chrono")]
+            Self::Monthly { day, hour, minute }
+                if !(1..=31).contains(&day) || hour > 23 || minute > 59 =>
+            {
+                Err(TaskError::invalid_frequency(
+                    InvalidFrequencyReason::InvalidTime,
+                    None,
+                    format!("{self:?} is not a valid day and time of day"),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The alarms of a calendar frequency, computed one at a time from the previous alarm.
+#[cfg(feature = "chrono")]
+#[derive(Clone)]
+struct CalendarSchedule {
+    frequency: FrequencySeconds,
+    next: Option<u64>,
+}
+
+#[cfg(feature = "chrono")]
+impl CalendarSchedule {
+    /// The first time matching the frequency strictly after `after`, in seconds.
+    fn next_after(frequency: FrequencySeconds, after: u64) -> Option<u64> {
+        use chrono::{DateTime, Datelike, Days, Months, NaiveDate};
+
+        let date = DateTime::from_timestamp(i64::try_from(after).ok()?, 0)?.date_naive();
+        let at = |date: NaiveDate, hour, minute| {
+            let timestamp = date.and_hms_opt(hour, minute, 0)?.and_utc().timestamp();
+            u64::try_from(timestamp)
+                .ok()
+                .filter(|&timestamp| timestamp > after)
+        };
+        match frequency {
+            FrequencySeconds::Weekly {
+                weekday,
+                hour,
+                minute,
+            } => (0..=7)
+                .filter_map(|days| date.checked_add_days(Days::new(days)))
+                .filter(|date| date.weekday() == weekday)
+                .find_map(|date| at(date, hour, minute)),
+            FrequencySeconds::Monthly { day, hour, minute } => {
+                let first = date.with_day(1)?;
+                (0..=1)
+                    .filter_map(|months| first.checked_add_months(Months::new(months)))
+                    .filter_map(|first| {
+                        // Clamp the day to the last day of shorter months
+                        let last = first.checked_add_months(Months::new(1))?.pred_opt()?;
+                        first.with_day(day.min(last.day()))
+                    })
+                    .find_map(|date| at(date, hour, minute))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl Schedule for CalendarSchedule {
+    fn next_alarm_timestamp(&mut self) -> Option<u64> {
+        let next = self.next?;
+        self.next = Self::next_after(self.frequency, next);
+        Some(next)
+    }
+
+    fn peek_alarm_timestamp(&mut self) -> Option<u64> {
+        self.next
+    }
+}
+
+/// The alarms of several weekly times, the earliest one next, see
+/// `TaskBuilder::with_frequency_weekly_schedule`.
+#[cfg(feature = "chrono")]
+#[derive(Clone)]
+struct WeeklySchedule {
+    /// `FrequencySeconds::Weekly` times.
+    entries: Vec<FrequencySeconds>,
+    next: Option<u64>,
+}
+
+#[cfg(feature = "chrono")]
+impl WeeklySchedule {
+    /// The first of the times strictly after `after`, in seconds.
+    fn next_after(entries: &[FrequencySeconds], after: u64) -> Option<u64> {
+        entries
+            .iter()
+            .filter_map(|&entry| CalendarSchedule::next_after(entry, after))
+            .min()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl Schedule for WeeklySchedule {
+    fn next_alarm_timestamp(&mut self) -> Option<u64> {
+        let next = self.next?;
+        self.next = Self::next_after(&self.entries, next);
+        Some(next)
+    }
+
+    fn peek_alarm_timestamp(&mut self) -> Option<u64> {
+        self.next
+    }
+}
+
+/// A time of day UTC to the second, checked when it is made, e.g. for
+/// `TaskBuilder::with_frequency_daily_at`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TimeOfDay {
+    hour: u8,
+    minute: u8,
+    second: u8,
+}
+
+impl TimeOfDay {
+    /// Fails with `InvalidTime` unless the hour is below 24 and the minute and second below 60.
+    pub fn new(hour: u8, minute: u8, second: u8) -> Result<Self, TaskError> {
+        if hour > 23 || minute > 59 || second > 59 {
+            return Err(TaskError::invalid_frequency(
+                InvalidFrequencyReason::InvalidTime,
+                None,
+                format!("{hour:02}:{minute:02}:{second:02} is not a valid time of day"),
+            ));
+        }
+        Ok(Self {
+            hour,
+            minute,
+            second,
+        })
+    }
+
+    pub fn hour(&self) -> u8 {
+        self.hour
+    }
+
+    pub fn minute(&self) -> u8 {
+        self.minute
+    }
+
+    pub fn second(&self) -> u8 {
+        self.second
+    }
+
+    /// The seconds since midnight.
+    pub fn seconds_from_midnight(&self) -> u64 {
+        u64::from(self.hour) * 3600 + u64::from(self.minute) * 60 + u64::from(self.second)
+    }
+}
+
+/// Repeated alarms snapped to the boundaries `offset` seconds past the multiples of `interval`
+/// since the epoch, see `TaskBuilder::with_aligned_repeat`. Only `next` may be off a boundary,
+/// the alarm after it is the next boundary.
+#[derive(Clone)]
+struct AlignedSchedule {
+    interval: u64,
+    offset: u64,
+    next: Option<u64>,
+}
+
+impl AlignedSchedule {
+    /// The first boundary strictly after `after`.
+    fn boundary_after(interval: u64, offset: u64, after: u64) -> Option<u64> {
+        match after.checked_sub(offset) {
+            Some(since) => (since / interval)
+                .checked_add(1)?
+                .checked_mul(interval)?
+                .checked_add(offset),
+            None => Some(offset),
+        }
+    }
+}
+
+impl Schedule for AlignedSchedule {
+    fn next_alarm_timestamp(&mut self) -> Option<u64> {
+        let next = self.next?;
+        self.next = Self::boundary_after(self.interval, self.offset, next);
+        Some(next)
+    }
+
+    fn peek_alarm_timestamp(&mut self) -> Option<u64> {
+        self.next
+    }
 }
 
 impl Default for FrequencySeconds {
@@ -21,52 +350,466 @@ impl Default for FrequencySeconds {
     }
 }
 
+/// A custom sequence of alarms, for schedules `FrequencySeconds` can't express.
+///
+/// Timestamps are in seconds. An alarm that is not in the future fires on the next tick.
+/// Schedules must be `Clone` as tasks are, any `Schedule + Clone` implements `ScheduleClone`.
+///
+/// Tasks with a custom schedule are not persisted by `Timer::save_schedule`.
+pub trait Schedule: ScheduleClone + Send + Sync + 'static {
+    /// Advance to the next alarm, `None` once the schedule is finished.
+    fn next_alarm_timestamp(&mut self) -> Option<u64>;
+
+    /// The next alarm without advancing.
+    fn peek_alarm_timestamp(&mut self) -> Option<u64>;
+}
+
+/// Clones a boxed `Schedule`, implemented for every `Schedule + Clone`.
+pub trait ScheduleClone {
+    fn clone_box(&self) -> Box<dyn Schedule>;
+}
+
+impl<T: Schedule + Clone> ScheduleClone for T {
+    fn clone_box(&self) -> Box<dyn Schedule> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Schedule> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// A schedule with an alarm put back in front of it, see `FrequencyState::rewind`.
+#[derive(Clone)]
+struct PendingAlarm {
+    alarm: Option<u64>,
+    rest: Box<dyn Schedule>,
+}
+
+impl Schedule for PendingAlarm {
+    fn next_alarm_timestamp(&mut self) -> Option<u64> {
+        self.alarm
+            .take()
+            .or_else(|| self.rest.next_alarm_timestamp())
+    }
+
+    fn peek_alarm_timestamp(&mut self) -> Option<u64> {
+        self.alarm.or_else(|| self.rest.peek_alarm_timestamp())
+    }
+}
+
+/// Where a schedule stands, see `Task::peek_state`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SchedulePeek {
+    /// The schedule is waiting for its next alarm, at this timestamp in seconds.
+    Next(u64),
+    /// The schedule is finished and won't fire again.
+    Exhausted,
+}
+
+/// What a task does about the alarms that passed while the timer couldn't fire it, e.g. after
+/// the clock jumped forward or the task was paused.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MissedPolicy {
+    /// Fire once for all the missed alarms and continue with the next alarm after now.
+    #[default]
+    Skip,
+    /// Fire once for each missed alarm, one per tick, before continuing with the schedule.
+    CatchUp,
+}
+
+/// What a once task does when its runner fails, see `TaskBuilder::with_once_failure_policy`.
+///
+/// Separate from the schedule, repeated tasks simply fire again at their next alarm.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum OnceFailurePolicy {
+    /// The task is gone after its run, whether it failed or not.
+    #[default]
+    Drop,
+    /// Schedule the task once more, the delay after the failure rounded up to whole seconds.
+    /// A failure of that run drops the task.
+    Rearm(Duration),
+}
+
+/// Which kind of sequence a `FrequencyStateSnapshot` was taken of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FrequencyStateKind {
+    /// Not counted from any time yet, the task isn't scheduled.
+    Unstarted,
+    /// Alarms every interval, without end.
+    Repeated,
+    /// Alarms every interval until the remaining count runs out; a once frequency counts down
+    /// from one.
+    CountDown,
+    /// The alarms of `TaskBuilder::with_frequency_delays`.
+    Delays,
+    /// A custom, calendar, weekly or aligned schedule, which only knows its next alarm.
+    Custom,
+}
+
+/// Where the sequence of alarms of a task stands, see `Task::frequency_snapshot`.
+///
+/// A `Repeated` or `CountDown` snapshot holds everything the sequence depends on, so
+/// `Task::restore_frequency` continues it exactly where it was, e.g. after a restart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrequencyStateSnapshot {
+    pub kind: FrequencyStateKind,
+    /// The seconds between alarms, `None` if they aren't evenly spaced.
+    pub interval: Option<u64>,
+    /// The timestamp of the next alarm in seconds, `None` if unstarted or finished.
+    pub next: Option<u64>,
+    /// The number of alarms left including `next`, `None` for schedules without end.
+    pub remaining: Option<u64>,
+}
+
 #[derive(Clone)]
 pub(crate) enum FrequencyState {
+    /// A frequency not counted from any time yet, see `start`.
+    Unstarted(FrequencySeconds),
+    /// An unstarted frequency whose first alarm is the given seconds after it is started.
+    Delayed(FrequencySeconds, u64),
     SecondsRepeated(SecondsState),
     SecondsCountDown(u64, SecondsState),
+    /// Delays in seconds not counted from any time yet, see `TaskBuilder::with_frequency_delays`.
+    UnstartedDelays(Vec<u64>),
+    /// The alarm `next` follows `delays[step]`, the delays are exhausted once `step` is past
+    /// the last one.
+    SecondsDelays {
+        delays: Vec<u64>,
+        step: usize,
+        next: u64,
+    },
+    /// Weekly times not counted from any time yet, the first alarm the first time after the
+    /// given seconds from the start. See `TaskBuilder::with_frequency_weekly_schedule`.
+    UnstartedWeekly(Vec<FrequencySeconds>, u64),
+    /// An aligned repeat not counted from any time yet: the first alarm is the first boundary
+    /// after `delay` seconds from the start, pushed back by `jitter` seconds off the boundary.
+    /// The boundaries are `offset` seconds past the multiples of `interval`. See
+    /// `TaskBuilder::with_aligned_repeat` and `TaskBuilder::with_frequency_daily_at`.
+    UnstartedAligned {
+        interval: u64,
+        offset: u64,
+        delay: u64,
+        jitter: u64,
+    },
+    Custom(Box<dyn Schedule>),
 }
 
 impl From<FrequencySeconds> for FrequencyState {
     fn from(frequency: FrequencySeconds) -> Self {
+        FrequencyState::starting_at(frequency, timestamp())
+    }
+}
+
+impl FrequencyState {
+    /// The state of a frequency counted from `now`: the first alarm is one interval later, or
+    /// the first matching time after `now` for a calendar frequency.
+    pub(crate) fn starting_at(frequency: FrequencySeconds, now: u64) -> Self {
+        match frequency.interval() {
+            Some(interval) => FrequencyState::resume(frequency, now + interval.as_secs()),
+            #[cfg(feature = "chrono")]
+            None => FrequencyState::Custom(Box::new(CalendarSchedule {
+                frequency,
+                next: CalendarSchedule::next_after(frequency, now),
+            })),
+            #[cfg(not(feature = "chrono"))]
+            None => unreachable!("every frequency has an interval"),
+        }
+    }
+
+    /// The state of weekly times counted from `now`, the first alarm the first time after it.
+    fn weekly_starting_at(entries: Vec<FrequencySeconds>, now: u64) -> Self {
+        #[cfg(feature = "chrono")]
+        {
+            let next = WeeklySchedule::next_after(&entries, now);
+            FrequencyState::Custom(Box::new(WeeklySchedule { entries, next }))
+        }
+        #[cfg(not(feature = "chrono"))]
+        {
+            let _ = (entries, now);
+            unreachable!("weekly times need the chrono feature")
+        }
+    }
+
+    /// Rebuild the state of a frequency whose next alarm is `next_alarm_timestamp`, so that a
+    /// restored schedule keeps its phase instead of restarting from now.
+    ///
+    /// For a countdown the count is the number of alarms left, including the next one.
+    pub(crate) fn resume(frequency: FrequencySeconds, next_alarm_timestamp: u64) -> Self {
         match frequency {
             FrequencySeconds::Once(seconds) => {
                 assert!(seconds > 0, "once frequency must be greater than 0");
-                let state: SecondsState = ((timestamp() + seconds)..)
+                let state: SecondsState = (next_alarm_timestamp..)
                     .step_by(seconds as usize)
                     .peekable();
-                FrequencyState::SecondsRepeated(state)
+                FrequencyState::SecondsCountDown(1, state)
             }
             FrequencySeconds::Repeated(seconds) => {
                 assert!(seconds > 0, "repeated frequency must be greater than 0");
-                let state: SecondsState = ((timestamp() + seconds)..)
+                let state: SecondsState = (next_alarm_timestamp..)
                     .step_by(seconds as usize)
                     .peekable();
                 FrequencyState::SecondsRepeated(state)
             }
             FrequencySeconds::CountDown(count_down, seconds) => {
                 assert!(seconds > 0, "countdown initial must be greater than 0");
-                let state: SecondsState = (timestamp() + seconds..)
-                    .step_by(count_down as usize)
+                let state: SecondsState = (next_alarm_timestamp..)
+                    .step_by(seconds as usize)
                     .peekable();
                 FrequencyState::SecondsCountDown(count_down, state)
             }
+            #[cfg(feature = "chrono")]
+            FrequencySeconds::Weekly { .. } | FrequencySeconds::Monthly { .. } => {
+                FrequencyState::Custom(Box::new(CalendarSchedule {
+                    frequency,
+                    next: Some(next_alarm_timestamp),
+                }))
+            }
+        }
+    }
+
+    /// Count an unstarted frequency from `now`, the time on the clock of the timer the task is
+    /// scheduled on. Started states are left as they are.
+    pub(crate) fn start(&mut self, now: u64) {
+        match *self {
+            Self::Unstarted(frequency) => *self = Self::starting_at(frequency, now),
+            Self::Delayed(frequency, delay) => {
+                *self = match frequency.interval() {
+                    Some(_) => Self::resume(frequency, now + delay),
+                    None => Self::starting_at(frequency, now + delay),
+                }
+            }
+            Self::UnstartedWeekly(ref mut entries, delay) => {
+                *self = Self::weekly_starting_at(std::mem::take(entries), now + delay);
+            }
+            Self::UnstartedAligned {
+                interval,
+                offset,
+                delay,
+                jitter,
+            } => {
+                let next = AlignedSchedule::boundary_after(interval, offset, now + delay)
+                    .and_then(|boundary| boundary.checked_add(jitter));
+                *self = Self::Custom(Box::new(AlignedSchedule {
+                    interval,
+                    offset,
+                    next,
+                }));
+            }
+            Self::UnstartedDelays(ref mut delays) => {
+                let delays = std::mem::take(delays);
+                let next = now + delays.first().copied().unwrap_or_default();
+                *self = Self::SecondsDelays {
+                    delays,
+                    step: 0,
+                    next,
+                };
+            }
+            _ => {}
+        }
+    }
+
+    /// Delay the first alarm of an unstarted frequency by `seconds`. Started states are left
+    /// as they are.
+    pub(crate) fn delay_start(&mut self, seconds: u64) {
+        match *self {
+            Self::Unstarted(frequency) => {
+                let interval = frequency
+                    .interval()
+                    .map_or(0, |interval| interval.as_secs());
+                *self = Self::Delayed(frequency, interval + seconds);
+            }
+            Self::Delayed(frequency, delay) => *self = Self::Delayed(frequency, delay + seconds),
+            Self::UnstartedDelays(ref mut delays) => {
+                if let Some(first) = delays.first_mut() {
+                    *first += seconds;
+                }
+            }
+            Self::UnstartedWeekly(_, ref mut delay) => *delay += seconds,
+            // Off the boundary, so only the first alarm is delayed
+            Self::UnstartedAligned { ref mut jitter, .. } => *jitter += seconds,
+            _ => {}
         }
     }
-}
 
-impl FrequencyState {
     pub(crate) fn peek_alarm_timestamp(&mut self) -> Option<u64> {
         match self {
-            Self::SecondsRepeated(state) => state.peek().map(|t| *t),
-            Self::SecondsCountDown(_, state) => state.peek().map(|t| *t),
+            // Outside of a timer the system clock is all there is
+            Self::Unstarted(_)
+            | Self::Delayed(..)
+            | Self::UnstartedDelays(_)
+            | Self::UnstartedWeekly(..)
+            | Self::UnstartedAligned { .. } => {
+                self.start(timestamp());
+                self.peek_alarm_timestamp()
+            }
+            Self::SecondsRepeated(state) => state.peek().copied(),
+            Self::SecondsDelays { delays, step, next } => (*step < delays.len()).then_some(*next),
+            Self::SecondsCountDown(0, _) => None,
+            Self::SecondsCountDown(_, state) => state.peek().copied(),
+            Self::Custom(schedule) => schedule.peek_alarm_timestamp(),
         }
     }
 
+    /// The next alarm without advancing, or that the schedule is finished.
+    pub(crate) fn peek_state(&mut self) -> SchedulePeek {
+        match self.peek_alarm_timestamp() {
+            Some(timestamp) => SchedulePeek::Next(timestamp),
+            None => SchedulePeek::Exhausted,
+        }
+    }
+
+    /// Advance to the next alarm. A countdown is exhausted once its count reaches zero.
     pub(crate) fn next_alarm_timestamp(&mut self) -> Option<u64> {
-        match self {
-            Self::SecondsRepeated(state) => state.next(),
+        let next = match self {
+            Self::Unstarted(_)
+            | Self::Delayed(..)
+            | Self::UnstartedDelays(_)
+            | Self::UnstartedWeekly(..)
+            | Self::UnstartedAligned { .. } => {
+                self.start(timestamp());
+                return self.next_alarm_timestamp();
+            }
+            Self::SecondsRepeated(state) => return state.next(),
+            Self::SecondsDelays { delays, step, next } => {
+                let alarm = (*step < delays.len()).then_some(*next)?;
+                *step += 1;
+                if let Some(delay) = delays.get(*step) {
+                    *next += delay;
+                }
+                return Some(alarm);
+            }
+            Self::SecondsCountDown(0, _) => return None,
             Self::SecondsCountDown(_, state) => state.next(),
+            Self::Custom(schedule) => return schedule.next_alarm_timestamp(),
+        };
+        self.down_count();
+        next
+    }
+
+    /// Undo the last `next_alarm_timestamp`, which returned `alarm`, so the sequence continues
+    /// from that alarm again. `frequency` is the one the state follows, `None` for a custom
+    /// schedule.
+    pub(crate) fn rewind(&mut self, frequency: Option<FrequencySeconds>, alarm: u64) {
+        let interval = frequency.and_then(|frequency| frequency.interval());
+        match (self, interval) {
+            (Self::SecondsRepeated(state), Some(interval)) => {
+                Self::step_back(state, interval.as_secs());
+            }
+            (Self::SecondsCountDown(count, state), Some(interval)) => {
+                Self::step_back(state, interval.as_secs());
+                *count += 1;
+            }
+            (Self::SecondsDelays { step, next, .. }, _) if *step > 0 => {
+                *step -= 1;
+                *next = alarm;
+            }
+            (Self::Custom(schedule), _) => {
+                *schedule = Box::new(PendingAlarm {
+                    alarm: Some(alarm),
+                    rest: schedule.clone(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    fn step_back(state: &mut SecondsState, seconds: u64) {
+        if let Some(&next) = state.peek() {
+            *state = (next.saturating_sub(seconds)..)
+                .step_by(seconds as usize)
+                .peekable();
+        }
+    }
+
+    /// The number of alarms left for a countdown or delays, `None` for other schedules.
+    pub(crate) fn remaining_count(&self) -> Option<u64> {
+        match self {
+            Self::Unstarted(FrequencySeconds::Once(_))
+            | Self::Delayed(FrequencySeconds::Once(_), _) => Some(1),
+            Self::Unstarted(FrequencySeconds::CountDown(count, _))
+            | Self::Delayed(FrequencySeconds::CountDown(count, _), _) => Some(*count),
+            Self::Unstarted(_)
+            | Self::Delayed(..)
+            | Self::SecondsRepeated(_)
+            | Self::UnstartedWeekly(..)
+            | Self::UnstartedAligned { .. }
+            | Self::Custom(_) => None,
+            Self::SecondsCountDown(count, _) => Some(*count),
+            Self::UnstartedDelays(delays) => Some(delays.len() as u64),
+            Self::SecondsDelays { delays, step, .. } => {
+                Some(delays.len().saturating_sub(*step) as u64)
+            }
+        }
+    }
+
+    /// Set the number of alarms left for a countdown, other schedules are left as they are.
+    pub(crate) fn set_remaining_count(&mut self, remaining: u64) {
+        if let Self::SecondsCountDown(count, _) = self {
+            *count = remaining;
+        }
+    }
+
+    /// Where the sequence stands, without advancing or starting it.
+    pub(crate) fn snapshot(&self) -> FrequencyStateSnapshot {
+        // The step of the sequence is the gap between the next two alarms
+        let interval = |state: &SecondsState| {
+            let mut state = state.clone();
+            let next = state.next()?;
+            state.next().map(|after| after - next)
+        };
+        let (kind, interval) = match self {
+            Self::Unstarted(frequency) | Self::Delayed(frequency, _) => (
+                FrequencyStateKind::Unstarted,
+                frequency.interval().map(|interval| interval.as_secs()),
+            ),
+            Self::UnstartedAligned { interval, .. } => {
+                (FrequencyStateKind::Unstarted, Some(*interval))
+            }
+            Self::UnstartedDelays(_) | Self::UnstartedWeekly(..) => {
+                (FrequencyStateKind::Unstarted, None)
+            }
+            Self::SecondsRepeated(state) => (FrequencyStateKind::Repeated, interval(state)),
+            Self::SecondsCountDown(_, state) => (FrequencyStateKind::CountDown, interval(state)),
+            Self::SecondsDelays { .. } => (FrequencyStateKind::Delays, None),
+            Self::Custom(_) => (FrequencyStateKind::Custom, None),
+        };
+        let next = match kind {
+            FrequencyStateKind::Unstarted => None,
+            _ => self.clone().peek_alarm_timestamp(),
+        };
+        FrequencyStateSnapshot {
+            kind,
+            interval,
+            next,
+            remaining: self.remaining_count(),
+        }
+    }
+
+    /// Rebuild the state a snapshot was taken of, `None` unless it is a `Repeated` or
+    /// `CountDown` snapshot with an interval.
+    pub(crate) fn from_snapshot(snapshot: &FrequencyStateSnapshot) -> Option<Self> {
+        let interval = snapshot.interval.filter(|&interval| interval > 0)?;
+        match (snapshot.kind, snapshot.next, snapshot.remaining) {
+            (FrequencyStateKind::Repeated, Some(next), _) => {
+                Some(Self::resume(FrequencySeconds::Repeated(interval), next))
+            }
+            (FrequencyStateKind::CountDown, Some(next), Some(remaining)) if remaining > 0 => Some(
+                Self::resume(FrequencySeconds::CountDown(remaining, interval), next),
+            ),
+            // A finished countdown stays finished
+            (FrequencyStateKind::CountDown, _, Some(0)) => Some(Self::SecondsCountDown(
+                0,
+                (snapshot.next.unwrap_or_default()..)
+                    .step_by(interval as usize)
+                    .peekable(),
+            )),
+            _ => None,
         }
     }
 
@@ -98,6 +841,10 @@ mod tests {
         // next_alarm_timestamp should advance the state
         let alarm3 = state.next_alarm_timestamp().unwrap();
         assert_eq!(alarm, alarm3);
+
+        // Once is exhausted after the first alarm
+        assert_eq!(state.peek_alarm_timestamp(), None);
+        assert_eq!(state.next_alarm_timestamp(), None);
     }
 
     #[test]
@@ -120,7 +867,7 @@ mod tests {
     #[test]
     fn test_frequency_state_from_countdown() {
         // Note: CountDown implementation creates a sequence starting from 'seconds'
-        // with step 'count_down', and the count is handled separately
+        // with step 'seconds', and the count is handled separately
         let freq = FrequencySeconds::CountDown(2, 5); // count_down=2, seconds=5
         let state = FrequencyState::from(freq);
 
@@ -131,6 +878,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_frequency_state_countdown_exhausted() {
+        let freq = FrequencySeconds::CountDown(2, 5);
+        let mut state = FrequencyState::from(freq);
+
+        let now = crate::utils::timestamp();
+        assert_eq!(state.next_alarm_timestamp(), Some(now + 5));
+        assert_eq!(state.next_alarm_timestamp(), Some(now + 10));
+        assert_eq!(state.next_alarm_timestamp(), None);
+        assert_eq!(state.peek_alarm_timestamp(), None);
+    }
+
     #[test]
     fn test_peek_alarm_timestamp() {
         let freq = FrequencySeconds::Repeated(10);
@@ -148,4 +907,313 @@ mod tests {
         let peek3 = state.peek_alarm_timestamp().unwrap();
         assert_ne!(peek1, peek3);
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_frequency_seconds_serde_round_trip() {
+        let frequencies = [
+            FrequencySeconds::Once(10),
+            FrequencySeconds::Repeated(30),
+            FrequencySeconds::CountDown(3, 5),
+        ];
+        for frequency in frequencies {
+            let json = serde_json::to_string(&frequency).unwrap();
+            let restored: FrequencySeconds = serde_json::from_str(&json).unwrap();
+            assert_eq!(restored, frequency);
+        }
+
+        let json = serde_json::to_value(FrequencySeconds::Repeated(30)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "type": "Repeated", "seconds": 30 })
+        );
+        let json = serde_json::to_value(FrequencySeconds::CountDown(3, 5)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "type": "CountDown", "count_down": 3, "seconds": 5 })
+        );
+    }
+
+    #[test]
+    fn test_frequency_state_resume_keeps_phase() {
+        let mut state = FrequencyState::resume(FrequencySeconds::Repeated(5), 1_000);
+        assert_eq!(state.next_alarm_timestamp(), Some(1_000));
+        assert_eq!(state.next_alarm_timestamp(), Some(1_005));
+
+        let mut state = FrequencyState::resume(FrequencySeconds::CountDown(2, 5), 1_000);
+        assert_eq!(state.next_alarm_timestamp(), Some(1_000));
+        assert_eq!(state.next_alarm_timestamp(), Some(1_005));
+        assert_eq!(state.next_alarm_timestamp(), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_frequency_state_resume_from_persisted_timestamp() {
+        let frequency = FrequencySeconds::Repeated(5);
+        let mut state = FrequencyState::from(frequency);
+        state.next_alarm_timestamp();
+        state.next_alarm_timestamp();
+
+        // Persist the frequency together with the next alarm, mid-sequence
+        let next_alarm = state.peek_alarm_timestamp().unwrap();
+        let json = serde_json::to_string(&(frequency, next_alarm)).unwrap();
+
+        let (frequency, next_alarm): (FrequencySeconds, u64) = serde_json::from_str(&json).unwrap();
+        let mut restored = FrequencyState::resume(frequency, next_alarm);
+        assert_eq!(
+            restored.next_alarm_timestamp(),
+            state.next_alarm_timestamp()
+        );
+        assert_eq!(
+            restored.next_alarm_timestamp(),
+            state.next_alarm_timestamp()
+        );
+    }
+
+    #[test]
+    fn test_frequency_seconds_interval() {
+        assert_eq!(
+            FrequencySeconds::Repeated(30).interval(),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            FrequencySeconds::CountDown(3, 5).interval(),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            FrequencySeconds::Once(10).interval(),
+            Some(Duration::from_secs(10))
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> u64 {
+        chrono::NaiveDate::from_ymd_opt(year, month, day)
+            .and_then(|date| date.and_hms_opt(hour, minute, 0))
+            .unwrap()
+            .and_utc()
+            .timestamp() as u64
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_weekly_frequency() {
+        let frequency = FrequencySeconds::Weekly {
+            weekday: chrono::Weekday::Mon,
+            hour: 8,
+            minute: 0,
+        };
+        assert_eq!(frequency.interval(), None);
+
+        // 2024-01-01 is a Monday, before and after the alarm time
+        let mut state = FrequencyState::starting_at(frequency, utc(2024, 1, 1, 7, 30));
+        assert_eq!(state.next_alarm_timestamp(), Some(utc(2024, 1, 1, 8, 0)));
+        assert_eq!(state.next_alarm_timestamp(), Some(utc(2024, 1, 8, 8, 0)));
+
+        let mut state = FrequencyState::starting_at(frequency, utc(2024, 1, 1, 8, 0));
+        assert_eq!(state.next_alarm_timestamp(), Some(utc(2024, 1, 8, 8, 0)));
+        let mut state = FrequencyState::starting_at(frequency, utc(2024, 1, 3, 12, 0));
+        assert_eq!(state.peek_alarm_timestamp(), Some(utc(2024, 1, 8, 8, 0)));
+
+        let invalid = FrequencySeconds::Weekly {
+            weekday: chrono::Weekday::Mon,
+            hour: 24,
+            minute: 0,
+        };
+        assert!(invalid.validate().is_err());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_weekly_schedule_picks_the_nearest_entry() {
+        let entries = vec![
+            FrequencySeconds::Weekly {
+                weekday: chrono::Weekday::Mon,
+                hour: 9,
+                minute: 0,
+            },
+            FrequencySeconds::Weekly {
+                weekday: chrono::Weekday::Fri,
+                hour: 17,
+                minute: 0,
+            },
+        ];
+        let start = |now| {
+            let mut state = FrequencyState::UnstartedWeekly(entries.clone(), 0);
+            state.start(now);
+            state
+        };
+
+        // 2024-01-01 is a Monday
+        let mut state = start(utc(2024, 1, 1, 8, 0));
+        let alarms: Vec<u64> = (0..3)
+            .filter_map(|_| state.next_alarm_timestamp())
+            .collect();
+        assert_eq!(
+            alarms,
+            vec![
+                utc(2024, 1, 1, 9, 0),
+                utc(2024, 1, 5, 17, 0),
+                utc(2024, 1, 8, 9, 0),
+            ]
+        );
+        assert_eq!(
+            start(utc(2024, 1, 1, 9, 0)).peek_alarm_timestamp(),
+            Some(utc(2024, 1, 5, 17, 0))
+        );
+        assert_eq!(
+            start(utc(2024, 1, 5, 18, 0)).peek_alarm_timestamp(),
+            Some(utc(2024, 1, 8, 9, 0))
+        );
+
+        // A start delay counts from now
+        let mut state = FrequencyState::UnstartedWeekly(entries.clone(), 0);
+        state.delay_start(60 * 60);
+        state.start(utc(2024, 1, 1, 8, 30));
+        assert_eq!(state.peek_alarm_timestamp(), Some(utc(2024, 1, 5, 17, 0)));
+    }
+
+    #[test]
+    fn test_aligned_repeat_snaps_back_after_delayed_start() {
+        let aligned = |delay| FrequencyState::UnstartedAligned {
+            interval: 60,
+            offset: 0,
+            delay,
+            jitter: 0,
+        };
+
+        let mut state = aligned(0);
+        state.start(1_000);
+        let alarms: Vec<u64> = (0..3)
+            .filter_map(|_| state.next_alarm_timestamp())
+            .collect();
+        assert_eq!(alarms, vec![1_020, 1_080, 1_140]);
+
+        // A start delay pushes the first alarm off the boundary, the second is back on it
+        let mut state = aligned(0);
+        state.delay_start(17);
+        state.start(1_000);
+        let alarms: Vec<u64> = (0..3)
+            .filter_map(|_| state.next_alarm_timestamp())
+            .collect();
+        assert_eq!(alarms, vec![1_037, 1_080, 1_140]);
+
+        // An initial delay picks a later boundary instead
+        let mut state = aligned(100);
+        state.start(1_000);
+        assert_eq!(state.peek_alarm_timestamp(), Some(1_140));
+        assert_eq!(state.remaining_count(), None);
+    }
+
+    #[test]
+    fn test_snapshot_of_started_states() {
+        let mut state = FrequencyState::Unstarted(FrequencySeconds::CountDown(3, 5));
+        let unstarted = state.snapshot();
+        assert_eq!(unstarted.kind, FrequencyStateKind::Unstarted);
+        assert_eq!((unstarted.interval, unstarted.next), (Some(5), None));
+
+        state.start(1_000);
+        state.next_alarm_timestamp();
+        let snapshot = state.snapshot();
+        assert_eq!(
+            snapshot,
+            FrequencyStateSnapshot {
+                kind: FrequencyStateKind::CountDown,
+                interval: Some(5),
+                next: Some(1_010),
+                remaining: Some(2),
+            }
+        );
+        // Taking a snapshot doesn't advance
+        assert_eq!(state.next_alarm_timestamp(), Some(1_010));
+
+        state.next_alarm_timestamp();
+        let finished = state.snapshot();
+        assert_eq!((finished.next, finished.remaining), (None, Some(0)));
+        let mut restored = FrequencyState::from_snapshot(&finished).unwrap();
+        assert_eq!(restored.next_alarm_timestamp(), None);
+
+        let delays = FrequencyState::SecondsDelays {
+            delays: vec![5, 10],
+            step: 1,
+            next: 1_015,
+        };
+        let snapshot = delays.snapshot();
+        assert_eq!(snapshot.kind, FrequencyStateKind::Delays);
+        assert_eq!(snapshot.next, Some(1_015));
+        assert!(FrequencyState::from_snapshot(&snapshot).is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_repeated_snapshot_serde_round_trip() {
+        let mut state = FrequencyState::starting_at(FrequencySeconds::Repeated(30), 1_000);
+        for _ in 0..3 {
+            state.next_alarm_timestamp();
+        }
+        let snapshot = state.snapshot();
+        assert_eq!(snapshot.kind, FrequencyStateKind::Repeated);
+        assert_eq!(
+            (snapshot.interval, snapshot.next, snapshot.remaining),
+            (Some(30), Some(1_120), None)
+        );
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let decoded: FrequencyStateSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, snapshot);
+
+        let mut restored = FrequencyState::from_snapshot(&decoded).unwrap();
+        assert_eq!(restored.snapshot(), snapshot);
+        for _ in 0..4 {
+            assert_eq!(
+                restored.next_alarm_timestamp(),
+                state.next_alarm_timestamp()
+            );
+        }
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_monthly_frequency_clamps_to_month_end() {
+        let frequency = FrequencySeconds::Monthly {
+            day: 31,
+            hour: 8,
+            minute: 0,
+        };
+        let mut state = FrequencyState::starting_at(frequency, utc(2024, 1, 31, 9, 0));
+        let alarms: Vec<u64> = (0..4)
+            .filter_map(|_| state.next_alarm_timestamp())
+            .collect();
+        assert_eq!(
+            alarms,
+            vec![
+                utc(2024, 2, 29, 8, 0),
+                utc(2024, 3, 31, 8, 0),
+                utc(2024, 4, 30, 8, 0),
+                utc(2024, 5, 31, 8, 0),
+            ]
+        );
+
+        // Not a leap year
+        let mut state = FrequencyState::starting_at(frequency, utc(2023, 2, 1, 0, 0));
+        assert_eq!(state.next_alarm_timestamp(), Some(utc(2023, 2, 28, 8, 0)));
+
+        // A restored schedule continues from its pending alarm
+        let mut restored = FrequencyState::resume(frequency, utc(2024, 12, 31, 8, 0));
+        assert_eq!(
+            restored.next_alarm_timestamp(),
+            Some(utc(2024, 12, 31, 8, 0))
+        );
+        assert_eq!(
+            restored.next_alarm_timestamp(),
+            Some(utc(2025, 1, 31, 8, 0))
+        );
+
+        let invalid = FrequencySeconds::Monthly {
+            day: 0,
+            hour: 8,
+            minute: 0,
+        };
+        assert!(invalid.validate().is_err());
+    }
 }
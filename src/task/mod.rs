@@ -1,10 +1,132 @@
 pub mod frequency;
+pub mod registry;
 pub mod runner;
+#[allow(clippy::module_inception)]
 pub mod task;
 
-pub type TaskId = u64;
+use std::{
+    fmt,
+    sync::atomic::{AtomicI64, Ordering},
+};
+
+/// The identifier of a task.
+///
+/// Task ids used to be plain `u64`s. To migrate, convert with `TaskId::from(id)` or `id.into()`
+/// where an id is expected, and `u64::from(task_id)` where the number is needed.
+/// `TaskBuilder::new` accepts either.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct TaskId(u64);
+
+impl TaskId {
+    pub const fn new(id: u64) -> Self {
+        Self(id)
+    }
+
+    pub const fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl From<u64> for TaskId {
+    fn from(id: u64) -> Self {
+        Self(id)
+    }
+}
+
+impl From<TaskId> for u64 {
+    fn from(task_id: TaskId) -> Self {
+        task_id.0
+    }
+}
+
+impl fmt::Display for TaskId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
 pub type RecordId = i64;
 
-pub use runner::TaskRunner;
+/// Hands out increasing record ids, starting from a configurable id.
+///
+/// Starting past the ids stored before a restart keeps new records from colliding with them.
+#[derive(Debug)]
+pub struct RecordIdGenerator {
+    start: RecordId,
+    next: AtomicI64,
+}
+
+impl RecordIdGenerator {
+    pub const fn new(start: RecordId) -> Self {
+        Self {
+            start,
+            next: AtomicI64::new(start),
+        }
+    }
+
+    /// Take the next id, the first call returns the start.
+    pub fn next(&self) -> RecordId {
+        self.next.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// The id taken last, `None` before the first.
+    pub fn last(&self) -> Option<RecordId> {
+        let next = self.next.load(Ordering::Relaxed);
+        (next > self.start).then(|| next - 1)
+    }
+}
+
+impl Default for RecordIdGenerator {
+    /// Record ids start at 1.
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+pub use frequency::{
+    FrequencyStateKind, FrequencyStateSnapshot, MissedPolicy, OnceFailurePolicy, Schedule,
+    SchedulePeek, TimeOfDay,
+};
+pub use registry::RunnerRegistry;
+pub use runner::{SyncTaskRunner, TaskRunner};
+
+pub use task::{IntoTask, Task, TaskBuilder, TaskContext};
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread};
+
+    use super::*;
+
+    #[test]
+    fn test_record_id_generator_from_custom_start() {
+        let generator = RecordIdGenerator::new(1_000);
+        assert_eq!(generator.last(), None);
+        assert_eq!(generator.next(), 1_000);
+        assert_eq!(generator.next(), 1_001);
+        assert_eq!(generator.last(), Some(1_001));
+        assert_eq!(RecordIdGenerator::default().next(), 1);
 
-pub use task::{Task, TaskBuilder, TaskContext};
+        let generator = Arc::new(generator);
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let generator = generator.clone();
+                thread::spawn(move || (0..100).map(|_| generator.next()).collect::<Vec<_>>())
+            })
+            .collect();
+        let mut ids = vec![];
+        for handle in handles {
+            let taken = handle.join().unwrap();
+            // Increasing within each thread, unique across threads
+            assert!(taken.windows(2).all(|pair| pair[0] < pair[1]));
+            ids.extend(taken);
+        }
+        ids.sort_unstable();
+        assert_eq!(ids, (1_002..1_402).collect::<Vec<_>>());
+    }
+}
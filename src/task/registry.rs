@@ -0,0 +1,58 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::task::{TaskId, TaskRunner};
+
+/// A factory producing the runner of a restored task.
+pub type RunnerFactory =
+    Arc<dyn Fn() -> Arc<dyn TaskRunner<Output = ()> + Send + Sync> + Send + Sync>;
+
+/// Resolves restored tasks to their runners.
+///
+/// Runners are trait objects and can't be persisted with the schedule, so they are rebuilt
+/// from the factories registered here when a schedule is loaded. A task built with
+/// `TaskBuilder::with_runner_name` is resolved by that name, any other task by its id.
+#[derive(Default, Clone)]
+pub struct RunnerRegistry {
+    by_name: HashMap<String, RunnerFactory>,
+    by_task_id: HashMap<TaskId, RunnerFactory>,
+}
+
+impl RunnerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the factory of the runner for a name.
+    pub fn register<F>(&mut self, name: impl Into<String>, factory: F) -> &mut Self
+    where
+        F: Fn() -> Arc<dyn TaskRunner<Output = ()> + Send + Sync> + Send + Sync + 'static,
+    {
+        self.by_name.insert(name.into(), Arc::new(factory));
+        self
+    }
+
+    /// Register the factory of the runner for a task id.
+    pub fn register_task<F>(&mut self, task_id: TaskId, factory: F) -> &mut Self
+    where
+        F: Fn() -> Arc<dyn TaskRunner<Output = ()> + Send + Sync> + Send + Sync + 'static,
+    {
+        self.by_task_id.insert(task_id, Arc::new(factory));
+        self
+    }
+
+    /// Build the runner registered under a name, `None` if no factory is registered for it.
+    pub fn resolve_name(
+        &self,
+        name: &str,
+    ) -> Option<Arc<dyn TaskRunner<Output = ()> + Send + Sync>> {
+        self.by_name.get(name).map(|factory| factory())
+    }
+
+    /// Build the runner of a task, `None` if no factory is registered for it.
+    pub fn resolve(
+        &self,
+        task_id: TaskId,
+    ) -> Option<Arc<dyn TaskRunner<Output = ()> + Send + Sync>> {
+        self.by_task_id.get(&task_id).map(|factory| factory())
+    }
+}
@@ -1,6 +1,96 @@
+use std::{
+    any::Any,
+    sync::{Arc, Mutex},
+};
+
+#[cfg(feature = "tokio-util")]
+use tokio_util::sync::CancellationToken;
+
+use crate::task::{RecordId, RecordIdGenerator, TaskContext, TaskId};
+
+#[cfg(feature = "tokio-util")]
+tokio::task_local! {
+    /// The token cancelled when the run being polled is aborted, set by the timer per run.
+    pub(crate) static RUN_CANCELLATION: CancellationToken;
+}
+
 #[async_trait::async_trait]
 pub trait TaskRunner: Send + Sync + 'static {
     type Output: Send + 'static;
 
     async fn run(&self) -> Result<Self::Output, Box<dyn std::error::Error + Send + Sync>>;
+
+    /// The record id of the latest run, for runners numbering their runs such as those built
+    /// by `TaskBuilder::spawn_fn`, see `TaskContext::record_id`.
+    fn last_record_id(&self) -> Option<RecordId> {
+        None
+    }
+}
+
+/// A runner for a `BlockingTimer`, executed on the timer thread.
+pub trait SyncTaskRunner: Send + Sync + 'static {
+    fn run(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+}
+
+/// Adapts a `SyncTaskRunner` to a `TaskRunner` whose future completes on the first poll.
+pub(crate) struct SyncRunnerAdapter<T>(pub(crate) T);
+
+#[async_trait::async_trait]
+impl<T: SyncTaskRunner> TaskRunner for SyncRunnerAdapter<T> {
+    type Output = ();
+
+    async fn run(&self) -> Result<Self::Output, Box<dyn std::error::Error + Send + Sync>> {
+        self.0.run()
+    }
+}
+
+/// Runs a closure returning a future, passing it the context of each run.
+pub(crate) struct FnRunner<F> {
+    task_id: TaskId,
+    record_ids: RecordIdGenerator,
+    state: Arc<Mutex<Box<dyn Any + Send>>>,
+    f: F,
+}
+
+impl<F> FnRunner<F> {
+    pub(crate) fn new(
+        task_id: TaskId,
+        first_record_id: RecordId,
+        state: Arc<Mutex<Box<dyn Any + Send>>>,
+        f: F,
+    ) -> Self {
+        Self {
+            task_id,
+            record_ids: RecordIdGenerator::new(first_record_id),
+            state,
+            f,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<F, Fut> TaskRunner for FnRunner<F>
+where
+    F: Fn(TaskContext) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    type Output = ();
+
+    async fn run(&self) -> Result<Self::Output, Box<dyn std::error::Error + Send + Sync>> {
+        let ctx = TaskContext {
+            task_id: self.task_id,
+            record_id: self.record_ids.next(),
+            state: self.state.clone(),
+            #[cfg(feature = "tokio-util")]
+            cancellation: RUN_CANCELLATION
+                .try_with(CancellationToken::clone)
+                .unwrap_or_default(),
+        };
+        (self.f)(ctx).await;
+        Ok(())
+    }
+
+    fn last_record_id(&self) -> Option<RecordId> {
+        self.record_ids.last()
+    }
 }
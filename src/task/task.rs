@@ -1,21 +1,40 @@
-use std::sync::Arc;
-
-use async_channel::Sender;
+use std::{
+    any::Any,
+    collections::HashMap,
+    fmt,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
 
 use crate::{
-    error::TaskError,
+    error::{InvalidFrequencyReason, TaskError},
     task::{
-        RecordId, TaskId, TaskRunner,
-        frequency::{FrequencySeconds, FrequencyState},
+        RecordId, SyncTaskRunner, TaskId, TaskRunner,
+        frequency::{
+            FrequencySeconds, FrequencyState, FrequencyStateKind, FrequencyStateSnapshot,
+            MissedPolicy, OnceFailurePolicy, Schedule, SchedulePeek, TimeOfDay,
+        },
+        runner::{FnRunner, SyncRunnerAdapter},
     },
-    timer::{TimerEvent, wheel::WheelCascadeGuide},
-    utils,
+    timer::wheel::WheelCascadeGuide,
+    utils::{self, TimeUnit},
 };
 
 #[derive(Clone)]
 pub struct Task {
     /// The unique identifier for the task.
     pub task_id: TaskId,
+    /// A human-readable name for logs and events.
+    pub(crate) name: Option<String>,
+    /// Key-value labels for operating on groups of related tasks.
+    pub(crate) tags: HashMap<String, String>,
+    /// Tasks with a higher priority are dispatched first when they arrive in the same slot.
+    pub(crate) priority: u8,
+    /// The order in which the task was scheduled, breaking ties between equal priorities.
+    pub(crate) seq: u64,
     /// The actual task runner that will be executed.
     pub(crate) runner: Arc<dyn TaskRunner<Output = ()> + Send + Sync>,
     /// The round number when the task is scheduled.
@@ -23,6 +42,47 @@ pub struct Task {
 
     /// The frequency state of the task.
     pub(crate) frequency: FrequencyState,
+    /// The frequency the task was built with, `None` for a custom schedule.
+    pub(crate) frequency_seconds: Option<FrequencySeconds>,
+    /// The timestamp of the alarm the task is currently scheduled for.
+    pub(crate) alarm_timestamp: u64,
+    /// The name the runner is registered under in a `RunnerRegistry`.
+    pub(crate) runner_name: Option<String>,
+    /// Tasks with the same key arriving in the same second run once.
+    pub(crate) dedup_key: Option<String>,
+    /// The tasks that must have completed once before this task first runs.
+    pub(crate) depends_on: Vec<TaskId>,
+    /// How late the task may fire before `TimerEvent::DeadlineMissed` is published.
+    pub(crate) max_lateness: Option<Duration>,
+    /// Whether the alarms missed while the task couldn't fire are skipped or caught up.
+    pub(crate) missed_policy: MissedPolicy,
+    /// Whether a once task is scheduled again when its runner fails.
+    pub(crate) once_failure: OnceFailurePolicy,
+    /// The id of the `TaskGroup` the task was scheduled with.
+    pub(crate) group_id: Option<u64>,
+    /// The alarm the task was scheduled for when that was already past.
+    pub(crate) missed_alarm: Option<u64>,
+    /// The most seconds the first alarm is delayed by at random when the task is added.
+    pub(crate) jitter: u64,
+    /// The most runs of the task a `Timer` runs at once, unbounded if `None`.
+    #[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+    pub(crate) max_in_flight: Option<usize>,
+    /// The failed runs in a row after which a `Timer` removes the task, never if `None`.
+    #[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+    pub(crate) max_failures: Option<u32>,
+    /// The deadline after which the task is removed instead of firing again.
+    pub(crate) auto_cancel: Option<AutoCancel>,
+    /// Where the task is first placed, instead of after its frequency.
+    pub(crate) placement: Option<WheelCascadeGuide>,
+}
+
+/// When a task is cancelled on its own, see `TaskBuilder::with_auto_cancel_at`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AutoCancel {
+    /// At the timestamp in seconds.
+    At(u64),
+    /// The seconds after the task is added, turned into `At` once it is.
+    After(u64),
 }
 
 impl Task {
@@ -35,77 +95,1287 @@ impl Task {
         self.frequency.next_alarm_timestamp()
     }
 
+    /// The next alarm of the task without advancing, or `SchedulePeek::Exhausted` once its
+    /// schedule is finished, e.g. after the last alarm of a countdown.
+    pub fn peek_state(&mut self) -> SchedulePeek {
+        self.frequency.peek_state()
+    }
+
+    /// Where the sequence of alarms of the task stands, e.g. to persist its phase or debug its
+    /// schedule. A task taken out of a timer has already advanced past its pending alarm.
+    pub fn frequency_snapshot(&self) -> FrequencyStateSnapshot {
+        self.frequency.snapshot()
+    }
+
+    /// Continue the sequence of alarms from a snapshot taken by `frequency_snapshot`, so the
+    /// task keeps its phase when added, rather than counting from then.
+    ///
+    /// Fails unless the snapshot is of a repeated or countdown sequence with the interval of
+    /// the frequency of the task; a once frequency counts down from one.
+    pub fn restore_frequency(
+        &mut self,
+        snapshot: &FrequencyStateSnapshot,
+    ) -> Result<(), TaskError> {
+        let compatible = match (snapshot.kind, self.frequency_seconds) {
+            (FrequencyStateKind::Repeated, Some(FrequencySeconds::Repeated(seconds)))
+            | (
+                FrequencyStateKind::CountDown,
+                Some(FrequencySeconds::Once(seconds) | FrequencySeconds::CountDown(_, seconds)),
+            ) => snapshot.interval == Some(seconds),
+            _ => false,
+        };
+        match FrequencyState::from_snapshot(snapshot).filter(|_| compatible) {
+            Some(frequency) => {
+                self.frequency = frequency;
+                Ok(())
+            }
+            None => Err(TaskError::invalid_frequency(
+                InvalidFrequencyReason::Unsupported,
+                snapshot.interval,
+                format!(
+                    "task {} can't continue from {snapshot:?} with frequency {:?}",
+                    self.task_id, self.frequency_seconds
+                ),
+            )),
+        }
+    }
+
+    /// The name of the task, if it was built with one.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The priority of the task, see `TaskBuilder::with_priority`.
+    pub fn priority(&self) -> u8 {
+        self.priority
+    }
+
+    /// The tags of the task.
+    pub fn tags(&self) -> &HashMap<String, String> {
+        &self.tags
+    }
+
+    /// The name the runner is registered under, if the task was built with one.
+    pub fn runner_name(&self) -> Option<&str> {
+        self.runner_name.as_deref()
+    }
+
+    /// The frequency the task follows, e.g. `FrequencySeconds::Repeated(30)`, as it was built
+    /// or last updated rather than its live state. `None` for a custom schedule, delays,
+    /// a weekly schedule or an aligned repeat.
+    pub fn frequency_kind(&self) -> Option<FrequencySeconds> {
+        self.frequency_seconds
+    }
+
+    /// The dedup key of the task, see `TaskBuilder::with_dedup_key`.
+    pub fn dedup_key(&self) -> Option<&str> {
+        self.dedup_key.as_deref()
+    }
+
+    /// The timestamp in seconds after which the task no longer fires, once it is known.
+    pub fn auto_cancel_at(&self) -> Option<u64> {
+        match self.auto_cancel? {
+            AutoCancel::At(timestamp) => Some(timestamp),
+            AutoCancel::After(_) => None,
+        }
+    }
+
+    /// The task to schedule again after this run failed, for a once task that re-arms on
+    /// failure. The copy drops on failure, so the task re-arms at most once.
+    #[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+    pub(crate) fn rearm_after_failure(&self) -> Option<Task> {
+        let OnceFailurePolicy::Rearm(delay) = self.once_failure else {
+            return None;
+        };
+        if !matches!(self.frequency_seconds, Some(FrequencySeconds::Once(_))) {
+            return None;
+        }
+        let seconds = delay.as_secs() + u64::from(delay.subsec_nanos() > 0);
+        let frequency = FrequencySeconds::Once(seconds.max(1));
+        let mut task = self.clone();
+        task.frequency = FrequencyState::Unstarted(frequency);
+        task.frequency_seconds = Some(frequency);
+        task.jitter = 0;
+        task.once_failure = OnceFailurePolicy::Drop;
+        Some(task)
+    }
+
+    #[cfg(test)]
     pub(crate) fn set_wheel_position(&mut self, wheel_position: WheelCascadeGuide) {
         self.cascade_guide = wheel_position;
     }
 }
 
-#[derive(Default, Clone, Copy)]
+impl fmt::Debug for Task {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Task")
+            .field("task_id", &self.task_id)
+            .field("name", &self.name)
+            .field("priority", &self.priority)
+            .field("depends_on", &self.depends_on)
+            .field("cascade_guide", &self.cascade_guide)
+            .field("frequency", &self.frequency_seconds)
+            .field("alarm_timestamp", &self.alarm_timestamp)
+            .field("runner", &"<runner>")
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Default, Clone)]
 pub struct TaskBuilder {
     task_id: TaskId,
-    frequency: FrequencySeconds,
+    name: Option<String>,
+    tags: HashMap<String, String>,
+    priority: u8,
+    /// `None` until a `with_frequency_*` method is called.
+    frequency: Option<FrequencySeconds>,
+    /// Replaces `frequency` when set by `with_schedule`.
+    schedule: Option<Box<dyn Schedule>>,
+    /// Replaces `frequency` when set by `with_frequency_delays`.
+    delays: Option<Vec<u64>>,
+    /// Replaces `frequency` when set by `with_frequency_weekly_schedule`.
+    weekly: Option<Vec<FrequencySeconds>>,
+    /// The interval and offset in seconds of `with_frequency_daily_at`, replaces `frequency`.
+    cycle: Option<(u64, u64)>,
+    /// The absolute alarm of `with_frequency_once_by_timestamp_seconds`.
+    once_at: Option<u64>,
+    /// The delay of the first alarm set by `with_initial_delay`.
+    initial_delay: Option<Duration>,
+    jitter: Option<Duration>,
+    /// Set by `with_aligned_repeat`.
+    aligned: bool,
+    max_in_flight: Option<usize>,
+    max_failures: Option<u32>,
+    auto_cancel: Option<AutoCancel>,
+    placement: Option<WheelCascadeGuide>,
+    runner_name: Option<String>,
+    dedup_key: Option<String>,
+    depends_on: Vec<TaskId>,
+    max_lateness: Option<Duration>,
+    missed_policy: MissedPolicy,
+    once_failure: OnceFailurePolicy,
+    first_record_id: Option<RecordId>,
+    /// Makes the initial task-local state of each task built, see `with_state`.
+    init_state: Option<Arc<dyn Fn() -> Box<dyn Any + Send> + Send + Sync>>,
 }
 
+/// The process-wide source of ids for `TaskBuilder::new_auto`.
+static NEXT_AUTO_TASK_ID: AtomicU64 = AtomicU64::new(1);
+
 impl TaskBuilder {
-    pub fn new(task_id: u64) -> Self {
+    pub fn new(task_id: impl Into<TaskId>) -> Self {
         Self {
-            task_id: task_id,
+            task_id: task_id.into(),
             ..Default::default()
         }
     }
 
-    pub fn with_frequency_once_by_seconds(&mut self, seconds: u64) -> &mut Self {
-        self.frequency = FrequencySeconds::Once(seconds);
+    /// Create a builder with a unique id from a process-wide counter, see `task_id`.
+    ///
+    /// Auto ids start at 1, don't mix them with ids chosen by hand on the same timer.
+    pub fn new_auto() -> Self {
+        Self::new(NEXT_AUTO_TASK_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// The id the task will be built with.
+    pub fn task_id(&self) -> TaskId {
+        self.task_id
+    }
+
+    /// Set a frequency computed at runtime, e.g. read from configuration.
+    pub fn with_frequency(mut self, frequency: FrequencySeconds) -> Self {
+        self.frequency = Some(frequency);
+        self.schedule = None;
+        self.delays = None;
+        self.weekly = None;
+        self.cycle = None;
+        self.once_at = None;
         self
     }
 
-    pub fn with_frequency_repeated_by_seconds(&mut self, seconds: u64) -> &mut Self {
-        self.frequency = FrequencySeconds::Repeated(seconds);
+    pub fn with_frequency_once_by_seconds(mut self, seconds: u64) -> Self {
+        self.frequency = Some(FrequencySeconds::Once(seconds));
+        self.schedule = None;
+        self.delays = None;
+        self.weekly = None;
+        self.cycle = None;
+        self.once_at = None;
         self
     }
 
-    pub fn with_frequency_count_down_by_seconds(
-        &mut self,
-        count_down: u64,
-        seconds: u64,
-    ) -> &mut Self {
-        self.frequency = FrequencySeconds::CountDown(count_down, seconds);
+    /// Fire once, `minutes` after the task is added. Fails if that overflows in seconds.
+    pub fn with_frequency_once_by_minutes(self, minutes: u64) -> Result<Self, TaskError> {
+        let seconds = utils::checked_seconds_from(TimeUnit::Minutes, minutes)?;
+        Ok(self.with_frequency_once_by_seconds(seconds))
+    }
+
+    /// Fire once, `hours` after the task is added. Fails if that overflows in seconds.
+    pub fn with_frequency_once_by_hours(self, hours: u64) -> Result<Self, TaskError> {
+        let seconds = utils::checked_seconds_from(TimeUnit::Hours, hours)?;
+        Ok(self.with_frequency_once_by_seconds(seconds))
+    }
+
+    /// Fire once, `days` after the task is added. Fails if that overflows in seconds.
+    pub fn with_frequency_once_by_days(self, days: u64) -> Result<Self, TaskError> {
+        let seconds = utils::checked_seconds_from(TimeUnit::Days, days)?;
+        Ok(self.with_frequency_once_by_seconds(seconds))
+    }
+
+    pub fn with_frequency_repeated_by_seconds(mut self, seconds: u64) -> Self {
+        self.frequency = Some(FrequencySeconds::Repeated(seconds));
+        self.schedule = None;
+        self.delays = None;
+        self.weekly = None;
+        self.cycle = None;
+        self.once_at = None;
+        self
+    }
+
+    pub fn with_frequency_count_down_by_seconds(mut self, count_down: u64, seconds: u64) -> Self {
+        self.frequency = Some(FrequencySeconds::CountDown(count_down, seconds));
+        self.schedule = None;
+        self.delays = None;
+        self.weekly = None;
+        self.cycle = None;
+        self.once_at = None;
         self
     }
 
+    /// Fire `count` times, every `interval` truncated to whole seconds.
+    ///
+    /// Fails if `count` is zero, the interval is under a second, or the last alarm would be
+    /// beyond what a timestamp in seconds can hold.
+    pub fn with_frequency_count_down_by_duration(
+        self,
+        count: u64,
+        interval: Duration,
+    ) -> Result<Self, TaskError> {
+        let seconds = interval.as_secs();
+        if count == 0 || seconds == 0 {
+            let reason = match seconds {
+                0 => InvalidFrequencyReason::ZeroInterval,
+                _ => InvalidFrequencyReason::ZeroCount,
+            };
+            return Err(TaskError::invalid_frequency(
+                reason,
+                Some(0),
+                format!(
+                    "countdown of {count} every {interval:?} needs a count and an interval of \
+                     at least one second"
+                ),
+            ));
+        }
+        if count.checked_mul(seconds).is_none() {
+            return Err(TaskError::invalid_frequency(
+                InvalidFrequencyReason::Overflow,
+                Some(count),
+                format!("countdown of {count} every {interval:?} overflows"),
+            ));
+        }
+        Ok(self.with_frequency_count_down_by_seconds(count, seconds))
+    }
+
+    /// Fire once at the absolute `timestamp` in seconds, on the tick at which the timer's clock
+    /// reaches it, however long after this call the task is built or added.
     pub fn with_frequency_once_by_timestamp_seconds(
-        &mut self,
+        mut self,
         timestamp: u64,
-    ) -> Result<&mut Self, TaskError> {
+    ) -> Result<Self, TaskError> {
         let now = utils::timestamp();
         let gap = timestamp.checked_sub(now).filter(|&gap| gap > 0).ok_or(
-            TaskError::InvalidFrequency(format!(
-                "Once timestamp({timestamp} need greater than current timestamp({now})"
-            )),
+            TaskError::invalid_frequency(
+                InvalidFrequencyReason::PastTimestamp,
+                Some(timestamp),
+                format!("Once timestamp({timestamp} need greater than current timestamp({now})"),
+            ),
         )?;
-        self.frequency = FrequencySeconds::Once(gap);
+        self.frequency = Some(FrequencySeconds::Once(gap));
+        self.schedule = None;
+        self.delays = None;
+        self.weekly = None;
+        self.cycle = None;
+        self.once_at = Some(timestamp);
+        Ok(self)
+    }
+
+    /// Fire once after each delay in seconds, in order, each counted from the previous alarm and
+    /// the first from when the task is added; e.g. `[5, 10, 20]` fires 5, 15 and 35 seconds
+    /// after. A countdown whose interval changes with every step, as for a backoff.
+    ///
+    /// Fails if there are no delays or a delay is zero.
+    pub fn with_frequency_delays(mut self, delays: Vec<u64>) -> Result<Self, TaskError> {
+        if delays.is_empty() || delays.contains(&0) {
+            let reason = match delays.is_empty() {
+                true => InvalidFrequencyReason::Empty,
+                false => InvalidFrequencyReason::ZeroInterval,
+            };
+            return Err(TaskError::invalid_frequency(
+                reason,
+                (!delays.is_empty()).then_some(0),
+                format!("delays {delays:?} need at least one delay, each greater than 0"),
+            ));
+        }
+        self.frequency = None;
+        self.schedule = None;
+        self.delays = Some(delays);
+        self.weekly = None;
+        self.cycle = None;
+        self.once_at = None;
         Ok(self)
     }
 
+    /// Fire every week at each of the `(weekday, hour, minute)` times UTC, e.g. Monday 09:00
+    /// and Friday 17:00; each alarm is the nearest of the times. The first alarm is the first
+    /// time after the task is added.
+    ///
+    /// Fails if there are no times or a time of day doesn't exist.
+    #[cfg(feature = "chrono")]
+    pub fn with_frequency_weekly_schedule(
+        mut self,
+        entries: Vec<(chrono::Weekday, u8, u8)>,
+    ) -> Result<Self, TaskError> {
+        if entries.is_empty() {
+            return Err(TaskError::invalid_frequency(
+                InvalidFrequencyReason::Empty,
+                None,
+                "a weekly schedule needs at least one time",
+            ));
+        }
+        let entries = entries
+            .into_iter()
+            .map(|(weekday, hour, minute)| {
+                let entry = FrequencySeconds::Weekly {
+                    weekday,
+                    hour: hour.into(),
+                    minute: minute.into(),
+                };
+                entry.validate().map(|()| entry)
+            })
+            .collect::<Result<_, _>>()?;
+        self.frequency = None;
+        self.schedule = None;
+        self.delays = None;
+        self.weekly = Some(entries);
+        self.cycle = None;
+        self.once_at = None;
+        Ok(self)
+    }
+
+    /// Fire every day at `time` UTC. The first alarm is the first such time after the task is
+    /// added.
+    pub fn with_frequency_daily_at(self, time: TimeOfDay) -> Self {
+        self.with_cycle(24 * 60 * 60, time.seconds_from_midnight())
+    }
+
+    /// Fire every week on `weekday` at `time` UTC, to the second. The first alarm is the first
+    /// such time after the task is added.
+    #[cfg(feature = "chrono")]
+    pub fn with_frequency_weekly_at(self, weekday: chrono::Weekday, time: TimeOfDay) -> Self {
+        // The epoch is on a Thursday
+        let days = (u64::from(weekday.num_days_from_monday()) + 4) % 7;
+        self.with_cycle(
+            7 * 24 * 60 * 60,
+            days * 24 * 60 * 60 + time.seconds_from_midnight(),
+        )
+    }
+
+    fn with_cycle(mut self, interval: u64, offset: u64) -> Self {
+        self.frequency = None;
+        self.schedule = None;
+        self.delays = None;
+        self.weekly = None;
+        self.cycle = Some((interval, offset));
+        self.once_at = None;
+        self
+    }
+
+    /// Use a custom sequence of alarms instead of a `with_frequency_*` method.
+    pub fn with_schedule(mut self, schedule: impl Schedule) -> Self {
+        self.frequency = None;
+        self.schedule = Some(Box::new(schedule));
+        self.delays = None;
+        self.weekly = None;
+        self.cycle = None;
+        self.once_at = None;
+        self
+    }
+
+    /// Fire first `delay` after the task is added, in whole seconds and at least one, and then
+    /// follow the frequency from there, e.g. wait an hour and then repeat every 5 minutes. For
+    /// a calendar frequency the first alarm is the first matching time after the delay.
+    ///
+    /// Has no effect on a custom schedule, delays or an absolute once timestamp.
+    pub fn with_initial_delay(mut self, delay: Duration) -> Self {
+        self.initial_delay = Some(delay);
+        self
+    }
+
+    /// Delay the first alarm by a random number of whole seconds up to `max`, drawn from the
+    /// `RandSource` of the timer when the task is added, to spread the load of tasks added
+    /// together. The following alarms keep the interval from the first, or go back to the
+    /// boundaries with `with_aligned_repeat`.
+    ///
+    /// Has no effect on a custom schedule or an absolute once timestamp.
+    pub fn with_jitter(mut self, max: Duration) -> Self {
+        self.jitter = Some(max);
+        self
+    }
+
+    /// Snap the alarms of a repeated frequency to the multiples of its interval since the
+    /// epoch, e.g. every 60 seconds fires at the start of each minute UTC and every hour on
+    /// the hour. The first alarm is the first boundary after the task is added, or after the
+    /// initial delay.
+    ///
+    /// With `with_jitter` only the first alarm is jittered off its boundary, the following ones
+    /// are back on the boundaries, so tasks added together spread their first run and then
+    /// fire in step. The task then reports no `frequency_kind`, like a custom schedule.
+    ///
+    /// Has no effect on other frequencies.
+    pub fn with_aligned_repeat(mut self) -> Self {
+        self.aligned = true;
+        self
+    }
+
+    /// Set a human-readable name, reported in events and tracking information.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Set the tags of the task, e.g. `tenant=42`, replacing any previously set.
+    pub fn with_tags(mut self, tags: HashMap<String, String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// Set the priority of the task, 0 by default.
+    ///
+    /// Tasks arriving in the same second are dispatched from the highest priority to the lowest,
+    /// tasks of equal priority in the order they were scheduled (FIFO).
+    pub fn with_priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Coalesce the task with the other tasks carrying the same key: of the tasks arriving in
+    /// the same second, only the first in dispatch order runs, the others skip that occurrence.
+    pub fn with_dedup_key(mut self, key: impl Into<String>) -> Self {
+        self.dedup_key = Some(key.into());
+        self
+    }
+
+    /// Run the task only after the task `task_id` has completed at least once, can be called
+    /// repeatedly to depend on several tasks. Until then, each arrival of the task is deferred
+    /// to the next second. Adding a task that closes a dependency cycle fails with
+    /// `TaskError::DependencyCycle`.
+    ///
+    /// A task waiting for a task that is neither scheduled nor running and never completed,
+    /// e.g. one that was removed or a once task whose run failed, is removed on its next
+    /// arrival, as it would wait forever.
+    pub fn with_depends_on(mut self, task_id: impl Into<TaskId>) -> Self {
+        self.depends_on.push(task_id.into());
+        self
+    }
+
+    /// Publish `TimerEvent::DeadlineMissed` whenever the task fires more than `lateness` after
+    /// its scheduled time, measured in whole seconds.
+    pub fn with_max_lateness(mut self, lateness: Duration) -> Self {
+        self.max_lateness = Some(lateness);
+        self
+    }
+
+    /// Run at most `limit` runs of this task at once, e.g. for a task that fans out and fires
+    /// faster than it finishes. Runs beyond the limit wait for one of the task to finish, in
+    /// the order they were dispatched, and don't hold a slot of
+    /// `TimerBuilder::with_max_concurrent_runs` meanwhile. A limit of zero counts as one.
+    pub fn with_max_in_flight(mut self, limit: usize) -> Self {
+        self.max_in_flight = Some(limit.max(1));
+        self
+    }
+
+    /// Remove the task from a `Timer` once `limit` runs in a row failed, e.g. a runner whose
+    /// state is broken for good. The failures of the runs count, errors and panics alike, a
+    /// successful run starts the count over. `TimerEvent::TaskDescheduled` reports the
+    /// removal. A limit of zero counts as one.
+    pub fn with_max_consecutive_failures(mut self, limit: u32) -> Self {
+        self.max_failures = Some(limit.max(1));
+        self
+    }
+
+    /// Remove the task once its next alarm would be after `timestamp` in seconds, e.g. for a
+    /// task polling on behalf of a request. An alarm at the timestamp still fires.
+    /// `TimerEvent::TaskExpired` reports the removal, right after the last run.
+    ///
+    /// Adding a task whose first alarm is already after the deadline fails with
+    /// `TaskError::TaskExpired`.
+    pub fn with_auto_cancel_at(mut self, timestamp: u64) -> Self {
+        self.auto_cancel = Some(AutoCancel::At(timestamp));
+        self
+    }
+
+    /// Like `with_auto_cancel_at`, with the deadline `after` the task is added, truncated to
+    /// whole seconds.
+    pub fn with_auto_cancel_after(mut self, after: Duration) -> Self {
+        self.auto_cancel = Some(AutoCancel::After(after.as_secs()));
+        self
+    }
+
+    /// Place the task into the slot the guide points at when it is added, e.g. to restore
+    /// state captured from the wheels or for a custom placement. The first alarm is where the
+    /// guide leads, counted from the hands of the wheels, later alarms follow the frequency
+    /// from there. Jitter and an initial delay don't apply.
+    ///
+    /// Adding the task fails with `TaskError::InvalidPlacement` if the guide doesn't fit the
+    /// dimensions of the wheels or isn't ahead of their hands, and for a custom schedule.
+    pub fn with_cascade_guide(mut self, guide: WheelCascadeGuide) -> Self {
+        self.placement = Some(guide);
+        self
+    }
+
+    /// Choose what happens to the alarms a repeated task misses, e.g. while the clock jumped
+    /// forward or the task was paused. `MissedPolicy::Skip` by default.
+    pub fn with_missed_policy(mut self, policy: MissedPolicy) -> Self {
+        self.missed_policy = policy;
+        self
+    }
+
+    /// Choose what happens when the runner of a once task fails, e.g. `Rearm` to give a
+    /// best-effort task a single retry. `OnceFailurePolicy::Drop` by default, other frequencies
+    /// ignore it.
+    pub fn with_once_failure_policy(mut self, policy: OnceFailurePolicy) -> Self {
+        self.once_failure = policy;
+        self
+    }
+
+    /// Number the runs of a task built by `spawn_fn` from `record_id` instead of 1, e.g. to
+    /// continue after the records kept before a restart.
+    pub fn with_first_record_id(mut self, record_id: RecordId) -> Self {
+        self.first_record_id = Some(record_id);
+        self
+    }
+
+    /// Set the initial task-local state of a task built by `spawn_fn`, see `TaskContext::state`.
+    ///
+    /// Every task built from the builder, e.g. by `staggered`, starts with its own clone.
+    pub fn with_state<S: Clone + Send + Sync + 'static>(mut self, state: S) -> Self {
+        self.init_state = Some(Arc::new(move || Box::new(state.clone())));
+        self
+    }
+
+    /// Set the name the runner is registered under, used to rebuild it when the task is restored.
+    pub fn with_runner_name(mut self, name: impl Into<String>) -> Self {
+        self.runner_name = Some(name.into());
+        self
+    }
+
+    /// Build a task running a runner instance shared with other tasks, e.g. one runner for
+    /// hundreds of tasks running the same logic. An `Arc<T>` of a concrete runner coerces.
+    ///
+    /// Every task runs the same instance, so state kept in the runner is shared between them.
+    pub fn spawn_async_shared(
+        self,
+        task_runner: Arc<dyn TaskRunner<Output = ()> + Send + Sync>,
+    ) -> Result<Task, TaskError> {
+        self.build(task_runner)
+    }
+
     pub fn spwan_async<T: TaskRunner<Output = ()> + Send + Sync>(
         self,
         task_runner: T,
     ) -> Result<Task, TaskError> {
-        let frequency = self.frequency.into();
+        self.build(Arc::new(task_runner))
+    }
+
+    /// Build a task running the closure, which is passed the context of each run.
+    ///
+    /// Each run gets the next record id, see `with_first_record_id`.
+    pub fn spawn_fn<F, Fut>(self, f: F) -> Result<Task, TaskError>
+    where
+        F: Fn(TaskContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let task_id = self.task_id;
+        let first_record_id = self.first_record_id.unwrap_or(1);
+        let state: Box<dyn Any + Send> = match &self.init_state {
+            Some(init_state) => init_state(),
+            None => Box::new(()),
+        };
+        let runner = FnRunner::new(task_id, first_record_id, Arc::new(Mutex::new(state)), f);
+        self.build(Arc::new(runner))
+    }
+
+    /// Build a task with a synchronous runner, to be scheduled on a `BlockingTimer`.
+    pub fn spawn_sync<T: SyncTaskRunner>(self, task_runner: T) -> Result<Task, TaskError> {
+        self.build(Arc::new(SyncRunnerAdapter(task_runner)))
+    }
+
+    /// Build `count` tasks repeating every `interval` seconds, with first alarms staggered by
+    /// `offset_step` seconds to spread them across slots: task `i` first fires after
+    /// `offset_step * (i + 1)` seconds. The tasks are ready for `Timer::add_tasks`.
+    ///
+    /// The builder is a template for every task: each gets an id from `new_auto`, and the
+    /// frequency of the template is replaced.
+    pub fn staggered<R, F>(
+        self,
+        count: usize,
+        interval: u64,
+        offset_step: u64,
+        mut make_runner: F,
+    ) -> Result<impl Iterator<Item = Task>, TaskError>
+    where
+        R: TaskRunner<Output = ()>,
+        F: FnMut(usize) -> R,
+    {
+        if interval == 0 {
+            return Err(TaskError::invalid_frequency(
+                InvalidFrequencyReason::ZeroInterval,
+                Some(0),
+                "staggered interval must be greater than 0",
+            ));
+        }
+        let start = utils::timestamp();
+        Ok((0..count).map(move |i| {
+            let mut builder = self.clone().with_frequency_repeated_by_seconds(interval);
+            builder.task_id = TaskBuilder::new_auto().task_id;
+            let mut task = builder
+                .spwan_async(make_runner(i))
+                .expect("the frequency is set");
+            let first_alarm = start + offset_step * (i as u64 + 1);
+            task.frequency =
+                FrequencyState::resume(FrequencySeconds::Repeated(interval), first_alarm);
+            task
+        }))
+    }
+
+    pub(crate) fn build(
+        self,
+        runner: Arc<dyn TaskRunner<Output = ()> + Send + Sync>,
+    ) -> Result<Task, TaskError> {
+        let aligned = self.aligned && matches!(self.frequency, Some(FrequencySeconds::Repeated(_)));
+        let frequency_state = match (self.schedule, self.delays, self.weekly, self.frequency) {
+            (Some(schedule), ..) => FrequencyState::Custom(schedule),
+            (None, Some(delays), ..) => FrequencyState::UnstartedDelays(delays),
+            (None, None, Some(entries), _) => FrequencyState::UnstartedWeekly(
+                entries,
+                self.initial_delay.map_or(0, |delay| delay.as_secs()),
+            ),
+            (None, None, None, Some(frequency)) => {
+                frequency.validate()?;
+                match (self.once_at, self.initial_delay, frequency) {
+                    // Keep the exact second rather than a gap counted from a later now
+                    (Some(timestamp), ..) => FrequencyState::resume(frequency, timestamp),
+                    (None, delay, FrequencySeconds::Repeated(interval)) if aligned => {
+                        FrequencyState::UnstartedAligned {
+                            interval,
+                            offset: 0,
+                            delay: delay.map_or(0, |delay| delay.as_secs()),
+                            jitter: 0,
+                        }
+                    }
+                    // Counted from the clock of the timer once the task is added
+                    (None, Some(delay), _) => {
+                        FrequencyState::Delayed(frequency, delay.as_secs().max(1))
+                    }
+                    (None, None, _) => FrequencyState::Unstarted(frequency),
+                }
+            }
+            (None, None, None, None) => match self.cycle {
+                Some((interval, offset)) => FrequencyState::UnstartedAligned {
+                    interval,
+                    offset,
+                    delay: self.initial_delay.map_or(0, |delay| delay.as_secs()),
+                    jitter: 0,
+                },
+                None => {
+                    return Err(TaskError::invalid_frequency(
+                        InvalidFrequencyReason::Missing,
+                        None,
+                        "no frequency specified",
+                    ));
+                }
+            },
+        };
         Ok(Task {
             task_id: self.task_id,
-            runner: Arc::new(task_runner),
+            name: self.name,
+            tags: self.tags,
+            priority: self.priority,
+            seq: 0,
+            runner,
             cascade_guide: WheelCascadeGuide::default(),
-            frequency,
+            frequency: frequency_state,
+            // The wheels would lose the alignment when counting the frequency again
+            frequency_seconds: self.frequency.filter(|_| !aligned),
+            alarm_timestamp: 0,
+            runner_name: self.runner_name,
+            dedup_key: self.dedup_key,
+            depends_on: self.depends_on,
+            max_lateness: self.max_lateness,
+            missed_policy: self.missed_policy,
+            once_failure: self.once_failure,
+            group_id: None,
+            missed_alarm: None,
+            jitter: self.jitter.map_or(0, |jitter| jitter.as_secs()),
+            max_in_flight: self.max_in_flight,
+            max_failures: self.max_failures,
+            auto_cancel: self.auto_cancel,
+            placement: self.placement,
         })
     }
 }
 
+/// Conversion into a task with an auto-assigned id, see `TaskBuilder::new_auto`.
+///
+/// Implemented for a `Task`, a `(FrequencySeconds, runner)` pair, and a `(Duration, closure)`
+/// pair running the closure every interval of at least one second.
+pub trait IntoTask {
+    fn into_task(self) -> Result<Task, TaskError>;
+}
+
+impl IntoTask for Task {
+    fn into_task(self) -> Result<Task, TaskError> {
+        Ok(self)
+    }
+}
+
+impl<R: TaskRunner<Output = ()>> IntoTask for (FrequencySeconds, R) {
+    fn into_task(self) -> Result<Task, TaskError> {
+        let (frequency, runner) = self;
+        TaskBuilder::new_auto()
+            .with_frequency(frequency)
+            .spwan_async(runner)
+    }
+}
+
+impl<F, Fut> IntoTask for (Duration, F)
+where
+    F: Fn(TaskContext) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    fn into_task(self) -> Result<Task, TaskError> {
+        let (interval, f) = self;
+        let seconds = interval.as_secs();
+        if seconds == 0 {
+            return Err(TaskError::invalid_frequency(
+                InvalidFrequencyReason::ZeroInterval,
+                Some(0),
+                format!("interval({interval:?}) must be at least one second"),
+            ));
+        }
+        TaskBuilder::new_auto()
+            .with_frequency_repeated_by_seconds(seconds)
+            .spawn_fn(f)
+    }
+}
+
 pub struct TaskContext {
     /// The id of Task.
     pub task_id: TaskId,
     /// The id of the task running instance.
     pub record_id: RecordId,
+    /// The task-local state, shared by every run of the task, see `TaskBuilder::with_state`.
+    /// It holds `()` unless the task was built with a state.
+    ///
+    /// Runs of the same task may overlap, the mutex serializes their access. It is a std
+    /// mutex: don't hold the guard across an `.await`, copy out what the run needs and write
+    /// it back instead. A run that panics while holding the guard poisons the state.
+    pub state: Arc<Mutex<Box<dyn Any + Send>>>,
+    /// Cancelled when the run is aborted, by `Timer::cancel_running` or `Timer::remove_task`.
+    /// The run's own future is dropped then, the token lets work it handed off, such as
+    /// spawned tasks, stop with it. It is never cancelled for runs outside a `Timer`.
+    #[cfg(feature = "tokio-util")]
+    pub cancellation: tokio_util::sync::CancellationToken,
+}
+
+impl TaskContext {
+    /// Lock the task-local state and pass it to `f` as an `S`, `None` if it is of another type.
+    ///
+    /// Panics if a run panicked while holding the state.
+    pub fn with_state<S: 'static, R>(&self, f: impl FnOnce(&mut S) -> R) -> Option<R> {
+        let mut state = self.state.lock().unwrap();
+        state.downcast_mut::<S>().map(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NoopRunner;
+
+    #[async_trait::async_trait]
+    impl TaskRunner for NoopRunner {
+        type Output = ();
 
-    pub(crate) timer_event_sender: Option<Sender<TimerEvent>>,
+        async fn run(&self) -> Result<Self::Output, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_builder_chains_by_value() {
+        let task = TaskBuilder::new(1)
+            .with_frequency_repeated_by_seconds(5)
+            .with_runner_name("noop")
+            .spwan_async(NoopRunner)
+            .unwrap();
+        assert_eq!(task.task_id, TaskId::new(1));
+        assert_eq!(task.frequency_seconds, Some(FrequencySeconds::Repeated(5)));
+        assert_eq!(task.runner_name(), Some("noop"));
+
+        let timestamp = utils::timestamp() + 30;
+        let task = TaskBuilder::new(2)
+            .with_frequency_once_by_timestamp_seconds(timestamp)
+            .unwrap()
+            .spwan_async(NoopRunner)
+            .unwrap();
+        assert!(matches!(
+            task.frequency_seconds,
+            Some(FrequencySeconds::Once(_))
+        ));
+    }
+
+    #[test]
+    fn test_frequency_kind() {
+        let task = TaskBuilder::new(4)
+            .with_frequency_repeated_by_seconds(30)
+            .spwan_async(NoopRunner)
+            .unwrap();
+        assert_eq!(task.frequency_kind(), Some(FrequencySeconds::Repeated(30)));
+
+        let task = TaskBuilder::new(5)
+            .with_frequency_delays(vec![1, 2])
+            .unwrap()
+            .spwan_async(NoopRunner)
+            .unwrap();
+        assert_eq!(task.frequency_kind(), None);
+    }
+
+    #[test]
+    fn test_builder_without_frequency() {
+        let result = TaskBuilder::new(3).spwan_async(NoopRunner);
+        assert!(matches!(
+            result,
+            Err(TaskError::InvalidFrequency {
+                reason: InvalidFrequencyReason::Missing,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_auto_task_ids_differ() {
+        let first = TaskBuilder::new_auto();
+        let second = TaskBuilder::new_auto();
+        assert_ne!(first.task_id(), second.task_id());
+        assert_eq!(
+            u64::from(second.task_id()).to_string(),
+            second.task_id().to_string()
+        );
+
+        let task_id = second.task_id();
+        let task = second
+            .with_frequency_once_by_seconds(1)
+            .spwan_async(NoopRunner)
+            .unwrap();
+        assert_eq!(task.task_id, task_id);
+    }
+
+    #[test]
+    fn test_debug_output() {
+        let task = TaskBuilder::new(42)
+            .with_frequency_repeated_by_seconds(5)
+            .spwan_async(NoopRunner)
+            .unwrap();
+        let debug = format!("{task:?}");
+        assert!(debug.contains("task_id: TaskId(42)"));
+        assert!(debug.contains("Repeated(5)"));
+        assert!(debug.contains("\"<runner>\""));
+    }
+
+    #[tokio::test]
+    async fn test_spawn_fn_numbers_runs_from_first_record_id() {
+        let records = Arc::new(std::sync::Mutex::new(vec![]));
+        let task = TaskBuilder::new(10)
+            .with_frequency_repeated_by_seconds(1)
+            .with_first_record_id(500)
+            .spawn_fn({
+                let records = records.clone();
+                move |ctx: TaskContext| {
+                    let records = records.clone();
+                    async move { records.lock().unwrap().push(ctx.record_id) }
+                }
+            })
+            .unwrap();
+        for _ in 0..3 {
+            task.runner.run().await.unwrap();
+        }
+        assert_eq!(*records.lock().unwrap(), vec![500, 501, 502]);
+    }
+
+    #[tokio::test]
+    async fn test_shared_runner_across_tasks() {
+        struct Counter(AtomicU64);
+
+        #[async_trait::async_trait]
+        impl TaskRunner for Counter {
+            type Output = ();
+
+            async fn run(&self) -> Result<Self::Output, Box<dyn std::error::Error + Send + Sync>> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        }
+
+        let counter = Arc::new(Counter(AtomicU64::new(0)));
+        let tasks: Vec<Task> = (1..=3)
+            .map(|task_id| {
+                TaskBuilder::new(task_id)
+                    .with_frequency_repeated_by_seconds(5)
+                    .spawn_async_shared(counter.clone())
+                    .unwrap()
+            })
+            .collect();
+        for task in &tasks {
+            task.runner.run().await.unwrap();
+        }
+        assert_eq!(counter.0.load(Ordering::SeqCst), 3);
+        // One instance, held by the three tasks and the test
+        assert_eq!(Arc::strong_count(&counter), 4);
+    }
+
+    #[tokio::test]
+    async fn test_task_local_state_across_runs() {
+        let seen = Arc::new(Mutex::new(vec![]));
+        let task = TaskBuilder::new(11)
+            .with_frequency_repeated_by_seconds(1)
+            .with_state(0_u32)
+            .spawn_fn({
+                let seen = seen.clone();
+                move |ctx: TaskContext| {
+                    let count = ctx.with_state(|count: &mut u32| {
+                        *count += 1;
+                        *count
+                    });
+                    seen.lock().unwrap().push(count);
+                    async {}
+                }
+            })
+            .unwrap();
+        for _ in 0..3 {
+            task.runner.run().await.unwrap();
+        }
+        assert_eq!(*seen.lock().unwrap(), vec![Some(1), Some(2), Some(3)]);
+    }
+
+    #[test]
+    fn test_delays_fire_at_cumulative_offsets() {
+        let mut task = TaskBuilder::new(12)
+            .with_frequency_delays(vec![5, 10, 20])
+            .unwrap()
+            .spwan_async(NoopRunner)
+            .unwrap();
+        assert_eq!(task.frequency_seconds, None);
+        task.frequency.start(1000);
+        assert_eq!(task.frequency.remaining_count(), Some(3));
+        let alarms: Vec<u64> = std::iter::from_fn(|| task.next_alarm_timestamp()).collect();
+        assert_eq!(alarms, vec![1005, 1015, 1035]);
+        assert_eq!(task.frequency.remaining_count(), Some(0));
+
+        for delays in [vec![], vec![5, 0]] {
+            assert!(matches!(
+                TaskBuilder::new(13).with_frequency_delays(delays),
+                Err(TaskError::InvalidFrequency { .. })
+            ));
+        }
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_weekly_schedule_builder() {
+        use chrono::Weekday;
+
+        let mut task = TaskBuilder::new(14)
+            .with_frequency_weekly_schedule(vec![(Weekday::Mon, 9, 0), (Weekday::Fri, 17, 0)])
+            .unwrap()
+            .spwan_async(NoopRunner)
+            .unwrap();
+        assert_eq!(task.frequency_kind(), None);
+        // Friday 2024-01-05 12:00 UTC
+        task.frequency.start(1_704_456_000);
+        assert_eq!(task.next_alarm_timestamp(), Some(1_704_456_000 + 5 * 3600));
+
+        for entries in [
+            vec![],
+            vec![(Weekday::Mon, 24, 0)],
+            vec![(Weekday::Tue, 9, 60)],
+        ] {
+            assert!(matches!(
+                TaskBuilder::new(15).with_frequency_weekly_schedule(entries),
+                Err(TaskError::InvalidFrequency { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_time_of_day_is_checked() {
+        for (hour, minute, second) in [(24, 0, 0), (25, 0, 0), (9, 60, 0), (9, 0, 60)] {
+            assert!(matches!(
+                TimeOfDay::new(hour, minute, second),
+                Err(TaskError::InvalidFrequency {
+                    reason: InvalidFrequencyReason::InvalidTime,
+                    ..
+                })
+            ));
+        }
+        let time = TimeOfDay::new(23, 59, 59).unwrap();
+        assert_eq!((time.hour(), time.minute(), time.second()), (23, 59, 59));
+        assert_eq!(TimeOfDay::new(0, 0, 0).unwrap().seconds_from_midnight(), 0);
+
+        // Monday 2024-01-01 08:00 UTC
+        let now = 1_704_067_200 + 8 * 3600;
+        let mut task = TaskBuilder::new(15)
+            .with_frequency_daily_at(TimeOfDay::new(9, 30, 15).unwrap())
+            .spwan_async(NoopRunner)
+            .unwrap();
+        assert_eq!(task.frequency_kind(), None);
+        task.frequency.start(now);
+        let first = now + 3600 + 30 * 60 + 15;
+        assert_eq!(task.next_alarm_timestamp(), Some(first));
+        assert_eq!(task.next_alarm_timestamp(), Some(first + 24 * 3600));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_weekly_at_time_of_day() {
+        use chrono::Weekday;
+
+        // Wednesday 2024-01-03 12:00 UTC
+        let now = 1_704_067_200 + 2 * 24 * 3600 + 12 * 3600;
+        let time = TimeOfDay::new(9, 0, 30).unwrap();
+        let mut task = TaskBuilder::new(16)
+            .with_frequency_weekly_at(Weekday::Mon, time)
+            .spwan_async(NoopRunner)
+            .unwrap();
+        task.frequency.start(now);
+        // Monday 2024-01-08 09:00:30
+        let first = 1_704_067_200 + 7 * 24 * 3600 + 9 * 3600 + 30;
+        assert_eq!(task.next_alarm_timestamp(), Some(first));
+        assert_eq!(task.next_alarm_timestamp(), Some(first + 7 * 24 * 3600));
+
+        let mut task = TaskBuilder::new(17)
+            .with_frequency_weekly_at(Weekday::Wed, time)
+            .spwan_async(NoopRunner)
+            .unwrap();
+        task.frequency.start(now);
+        assert_eq!(
+            task.next_alarm_timestamp(),
+            Some(now - 12 * 3600 + 7 * 24 * 3600 + 9 * 3600 + 30)
+        );
+    }
+
+    #[test]
+    fn test_into_task() {
+        let task = TaskBuilder::new(9)
+            .with_frequency_once_by_seconds(1)
+            .spwan_async(NoopRunner)
+            .unwrap()
+            .into_task()
+            .unwrap();
+        assert_eq!(task.task_id, TaskId::new(9));
+
+        let task = (FrequencySeconds::CountDown(3, 2), NoopRunner)
+            .into_task()
+            .unwrap();
+        assert_eq!(
+            task.frequency_seconds,
+            Some(FrequencySeconds::CountDown(3, 2))
+        );
+
+        let task = (Duration::from_secs(4), |_| async {}).into_task().unwrap();
+        assert_eq!(task.frequency_seconds, Some(FrequencySeconds::Repeated(4)));
+        assert_ne!(task.task_id, TaskId::new(9));
+
+        let result = (Duration::from_millis(10), |_| async {}).into_task();
+        assert!(matches!(result, Err(TaskError::InvalidFrequency { .. })));
+    }
+
+    #[test]
+    fn test_count_down_by_duration() {
+        let mut task = TaskBuilder::new(11)
+            .with_frequency_count_down_by_duration(3, Duration::from_secs(10))
+            .unwrap()
+            .spwan_async(NoopRunner)
+            .unwrap();
+        assert_eq!(
+            task.frequency_seconds,
+            Some(FrequencySeconds::CountDown(3, 10))
+        );
+        let alarms: Vec<u64> = std::iter::from_fn(|| task.next_alarm_timestamp()).collect();
+        assert_eq!(alarms.len(), 3);
+        assert_eq!(alarms[1] - alarms[0], 10);
+        assert_eq!(alarms[2] - alarms[1], 10);
+
+        for (count, interval) in [
+            (0, Duration::from_secs(10)),
+            (3, Duration::from_millis(500)),
+            (u64::MAX, Duration::from_secs(2)),
+        ] {
+            let result =
+                TaskBuilder::new(11).with_frequency_count_down_by_duration(count, interval);
+            assert!(matches!(result, Err(TaskError::InvalidFrequency { .. })));
+        }
+    }
+
+    #[test]
+    fn test_invalid_frequency_reasons() {
+        let result = TaskBuilder::new(12)
+            .with_frequency_repeated_by_seconds(0)
+            .spwan_async(NoopRunner);
+        assert!(matches!(
+            result,
+            Err(TaskError::InvalidFrequency {
+                reason: InvalidFrequencyReason::ZeroInterval,
+                value: Some(0),
+                ..
+            })
+        ));
+
+        let past = crate::utils::timestamp() - 10;
+        let result = TaskBuilder::new(12).with_frequency_once_by_timestamp_seconds(past);
+        assert!(matches!(
+            result,
+            Err(TaskError::InvalidFrequency {
+                reason: InvalidFrequencyReason::PastTimestamp,
+                value: Some(value),
+                ..
+            }) if value == past
+        ));
+    }
+
+    #[test]
+    fn test_once_by_larger_units() {
+        let task = TaskBuilder::new(13)
+            .with_frequency_once_by_hours(1)
+            .unwrap()
+            .spwan_async(NoopRunner)
+            .unwrap();
+        assert_eq!(task.frequency_seconds, Some(FrequencySeconds::Once(3600)));
+
+        let builder = TaskBuilder::new(13)
+            .with_frequency_once_by_minutes(3)
+            .unwrap();
+        assert_eq!(builder.frequency, Some(FrequencySeconds::Once(180)));
+        let builder = TaskBuilder::new(13).with_frequency_once_by_days(2).unwrap();
+        assert_eq!(builder.frequency, Some(FrequencySeconds::Once(172_800)));
+
+        let result = TaskBuilder::new(13).with_frequency_once_by_days(u64::MAX / 60);
+        assert!(matches!(result, Err(TaskError::InvalidFrequency { .. })));
+
+        for result in [
+            TaskBuilder::new(13).with_frequency_once_by_minutes(u64::MAX),
+            TaskBuilder::new(13).with_frequency_once_by_hours(u64::MAX),
+            TaskBuilder::new(13).with_frequency_once_by_days(u64::MAX),
+        ] {
+            assert!(matches!(
+                result,
+                Err(TaskError::InvalidFrequency {
+                    reason: InvalidFrequencyReason::Overflow,
+                    value: Some(u64::MAX),
+                    ..
+                })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_restore_frequency_keeps_phase() {
+        let mut original = TaskBuilder::new(16)
+            .with_frequency_repeated_by_seconds(30)
+            .spwan_async(NoopRunner)
+            .unwrap();
+        original.frequency.start(1_000);
+        original.next_alarm_timestamp();
+        let snapshot = original.frequency_snapshot();
+
+        let mut task = TaskBuilder::new(16)
+            .with_frequency_repeated_by_seconds(30)
+            .spwan_async(NoopRunner)
+            .unwrap();
+        task.restore_frequency(&snapshot).unwrap();
+        assert_eq!(task.next_alarm_timestamp(), Some(1_060));
+
+        // The interval must match the frequency of the task
+        let mut other = TaskBuilder::new(17)
+            .with_frequency_repeated_by_seconds(10)
+            .spwan_async(NoopRunner)
+            .unwrap();
+        assert!(matches!(
+            other.restore_frequency(&snapshot),
+            Err(TaskError::InvalidFrequency {
+                reason: InvalidFrequencyReason::Unsupported,
+                ..
+            })
+        ));
+        let mut once = TaskBuilder::new(18)
+            .with_frequency_once_by_seconds(30)
+            .spwan_async(NoopRunner)
+            .unwrap();
+        assert!(once.restore_frequency(&snapshot).is_err());
+    }
+
+    #[test]
+    fn test_initial_delay_then_interval() {
+        let mut task = TaskBuilder::new(14)
+            .with_frequency_repeated_by_seconds(300)
+            .with_initial_delay(Duration::from_secs(3600))
+            .spwan_async(NoopRunner)
+            .unwrap();
+        let now = 1_000_000;
+        task.frequency.start(now);
+        assert_eq!(task.next_alarm_timestamp(), Some(now + 3600));
+        assert_eq!(task.next_alarm_timestamp(), Some(now + 3600 + 300));
+
+        let mut task = TaskBuilder::new(14)
+            .with_initial_delay(Duration::from_secs(10))
+            .with_frequency_count_down_by_seconds(2, 60)
+            .spwan_async(NoopRunner)
+            .unwrap();
+        assert_eq!(task.frequency.remaining_count(), Some(2));
+        task.frequency.start(now);
+        let alarms: Vec<u64> = std::iter::from_fn(|| task.next_alarm_timestamp()).collect();
+        assert_eq!(alarms, [now + 10, now + 70]);
+    }
+
+    #[test]
+    fn test_peek_state_of_drained_count_down() {
+        let mut task = TaskBuilder::new(12)
+            .with_frequency_count_down_by_seconds(2, 5)
+            .spwan_async(NoopRunner)
+            .unwrap();
+        let SchedulePeek::Next(first) = task.peek_state() else {
+            panic!("a new countdown has alarms left");
+        };
+        assert_eq!(task.next_alarm_timestamp(), Some(first));
+        assert_eq!(task.peek_state(), SchedulePeek::Next(first + 5));
+        assert_eq!(task.next_alarm_timestamp(), Some(first + 5));
+        assert_eq!(task.peek_state(), SchedulePeek::Exhausted);
+        assert_eq!(task.next_alarm_timestamp(), None);
+        assert_eq!(task.peek_state(), SchedulePeek::Exhausted);
+    }
+
+    #[test]
+    fn test_builder_with_computed_frequency() {
+        let configured = ["once", "every"].map(|kind| match kind {
+            "once" => FrequencySeconds::Once(30),
+            _ => FrequencySeconds::Repeated(30),
+        });
+        for frequency in configured {
+            let task = TaskBuilder::new(10)
+                .with_frequency(frequency)
+                .spwan_async(NoopRunner)
+                .unwrap();
+            assert_eq!(task.frequency_seconds, Some(frequency));
+        }
+    }
 }
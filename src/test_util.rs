@@ -0,0 +1,63 @@
+//! Helpers for testing code that schedules tasks, enabled by the `test-util` feature.
+
+use crate::{
+    task::TaskId,
+    timer::{Timer, TimerEvent},
+};
+
+/// Drive the timer with `tick_once` up to `ticks` times, returning the tick on which the task
+/// fired, counting from 1.
+///
+/// Must be called within a tokio runtime, as `tick_once` spawns the runners. The firing is seen
+/// on an event receiver of its own, so with the `async-channel` backend another receiver
+/// draining events concurrently may take it first.
+///
+/// # Panics
+/// Panics if the task did not fire within `ticks` ticks.
+#[track_caller]
+pub fn assert_fires_within(timer: &Timer, task_id: TaskId, ticks: u64) -> u64 {
+    let mut events = timer.event_receiver();
+    for tick in 1..=ticks {
+        timer.tick_once();
+        while let Some(event) = events.try_recv() {
+            if matches!(event, TimerEvent::TaskFired { task_id: fired, .. } if fired == task_id) {
+                return tick;
+            }
+        }
+    }
+    panic!("task {task_id} did not fire within {ticks} ticks");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::{TaskBuilder, TaskContext};
+
+    #[tokio::test]
+    async fn test_assert_fires_within() {
+        let timer = Timer::new();
+        let task = TaskBuilder::new(1)
+            .with_frequency_once_by_seconds(3)
+            .spawn_fn(|_ctx: TaskContext| async {})
+            .unwrap();
+        timer.add_task(task).unwrap();
+        let ticks = timer
+            .task_location(TaskId::new(1))
+            .unwrap()
+            .ticks_until_fire;
+
+        assert_eq!(assert_fires_within(&timer, TaskId::new(1), 5), ticks);
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "did not fire within 2 ticks")]
+    async fn test_assert_fires_within_too_few_ticks() {
+        let timer = Timer::new();
+        let task = TaskBuilder::new(1)
+            .with_frequency_once_by_seconds(30)
+            .spawn_fn(|_ctx: TaskContext| async {})
+            .unwrap();
+        timer.add_task(task).unwrap();
+        assert_fires_within(&timer, TaskId::new(1), 2);
+    }
+}
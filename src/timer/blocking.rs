@@ -0,0 +1,601 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    error::TaskError,
+    task::{SyncTaskRunner, Task, TaskId, frequency::FrequencySeconds, runner::SyncRunnerAdapter},
+    timer::{
+        AuditError, EventReceiver, EventSender, OverduePolicy, TaskGroup, TaskLocation,
+        TaskTrackingInfo, TimerEvent, WheelPositions, WheelType,
+        event::EventPublisher,
+        wheel::{MulitWheel, RESYNC_THRESHOLD},
+    },
+    utils,
+};
+
+#[cfg(feature = "serde")]
+use std::path::Path;
+
+#[cfg(feature = "serde")]
+use crate::{
+    task::RunnerRegistry,
+    timer::schedule::{self, ScheduleSnapshot},
+};
+
+const DEFAULT_EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// A timer driven by `std::thread::sleep` on a dedicated thread, without a tokio runtime.
+///
+/// Runners are executed one after another on the timer thread, so they should be built with
+/// `TaskBuilder::spawn_sync`. An async runner is polled on the timer thread and must not depend
+/// on a runtime.
+#[derive(Clone)]
+pub struct BlockingTimer {
+    wheel: Arc<MulitWheel>,
+    events: Arc<EventPublisher>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl Default for BlockingTimer {
+    fn default() -> Self {
+        Self::build(None)
+    }
+}
+
+impl BlockingTimer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a timer retaining the last `capacity` events, see `recent_events`.
+    pub fn with_event_log_capacity(capacity: usize) -> Self {
+        Self::build(Some(capacity))
+    }
+
+    fn build(event_log_capacity: Option<usize>) -> Self {
+        let sender = EventSender::new(DEFAULT_EVENT_CHANNEL_CAPACITY);
+        Self {
+            wheel: Arc::new(MulitWheel::new()),
+            events: Arc::new(EventPublisher::new(sender, event_log_capacity)),
+            stopped: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Get a receiver of the events published by the timer.
+    pub fn event_receiver(&self) -> EventReceiver {
+        EventReceiver::new(self.events.sender())
+    }
+
+    /// Get the most recent events retained by the event log, oldest first.
+    pub fn recent_events(&self) -> Vec<TimerEvent> {
+        self.events.recent_events()
+    }
+
+    /// Schedule a task for its next alarm.
+    pub fn add_task(&self, task: Task) -> Result<(), TaskError> {
+        self.wheel.add_task(task)
+    }
+
+    /// Schedule a task to first fire `offset_secs` seconds after the next fire of the task
+    /// `reference`, e.g. to run a cleanup 30 seconds after a job. Later alarms follow from the
+    /// frequency of the task.
+    ///
+    /// Fails with `TaskError::TaskNotFound` if `reference` isn't scheduled, and for a task with
+    /// a custom schedule.
+    pub fn add_task_after(
+        &self,
+        reference: TaskId,
+        offset_secs: u64,
+        task: Task,
+    ) -> Result<(), TaskError> {
+        self.wheel.add_task_after(reference, offset_secs, task)
+    }
+
+    /// Check whether `add_tasks` would schedule each task, without scheduling any, e.g. before
+    /// committing a large batch. Returns the result `add_tasks` would give for each task.
+    pub fn validate(&self, tasks: &[Task]) -> Vec<Result<(), TaskError>> {
+        self.wheel.validate(tasks)
+    }
+
+    /// Schedule tasks in a batch, returning the result of each task in order.
+    ///
+    /// A task whose id is already scheduled fails with `TaskError::DuplicateTask`.
+    pub fn add_tasks(&self, tasks: Vec<Task>) -> Vec<Result<(), TaskError>> {
+        self.wheel.add_tasks(tasks)
+    }
+
+    /// Schedule tasks as a group, returning a handle operating on just these tasks. Either all
+    /// tasks are added or none, the first failure is returned.
+    pub fn add_group(&self, tasks: Vec<Task>) -> Result<TaskGroup, TaskError> {
+        let group_id = self.wheel.add_group(tasks)?;
+        Ok(TaskGroup::new(group_id, self.wheel.clone()))
+    }
+
+    /// Make room for at least `additional` more tasks, ahead of adding them one at a time.
+    /// `add_tasks` reserves for its batch by itself.
+    pub fn reserve(&self, additional: usize) {
+        self.wheel.reserve(additional)
+    }
+
+    /// Remove a scheduled task, returning it if it was present.
+    pub fn remove_task(&self, task_id: TaskId) -> Option<Task> {
+        self.wheel.remove_task(task_id)
+    }
+
+    /// Remove a scheduled task like `remove_task`, returning only its tracking information, for
+    /// cleanup paths that have no use for the task and its runner.
+    pub fn remove_task_info(&self, task_id: TaskId) -> Option<TaskTrackingInfo> {
+        self.wheel.remove_task_info(task_id)
+    }
+
+    /// The number of pending tasks. Tasks whose runner is in flight are counted once they are
+    /// scheduled for their next alarm.
+    pub fn len(&self) -> usize {
+        self.wheel.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.wheel.is_empty()
+    }
+
+    /// Whether a task with the id is scheduled.
+    pub fn contains(&self, task_id: TaskId) -> bool {
+        self.wheel.contains(task_id)
+    }
+
+    /// The timestamp in seconds of the next alarm of a task, `None` if it is not scheduled.
+    ///
+    /// The timestamp follows the wheels, which may lag a little behind the wall clock.
+    pub fn next_fire_time(&self, task_id: TaskId) -> Option<u64> {
+        self.wheel.next_fire_time(task_id)
+    }
+
+    /// Move the next alarm of a scheduled task to `timestamp`, later alarms follow its
+    /// frequency from there on. Timestamps that are not in the future are rejected.
+    pub fn set_next_fire(&self, task_id: TaskId, timestamp: u64) -> Result<(), TaskError> {
+        self.wheel.set_next_fire(task_id, timestamp)
+    }
+
+    /// Find the ids of the tasks tagged with `key=value`, in ascending order.
+    pub fn find_tasks_by_tag(&self, key: &str, value: &str) -> Vec<TaskId> {
+        self.wheel.find_tasks_by_tag(key, value)
+    }
+
+    /// Count the scheduled tasks per value of the tag `key`, e.g. the tasks of each tenant.
+    pub fn task_count_by_tag(&self, key: &str) -> HashMap<String, usize> {
+        self.wheel.task_count_by_tag(key)
+    }
+
+    /// The number of scheduled tasks in the second, minute and hour wheel.
+    pub fn level_distribution(&self) -> (usize, usize, usize) {
+        self.wheel.level_distribution()
+    }
+
+    /// A rough estimate of the memory the wheels take in bytes, see
+    /// `MulitWheel::estimated_memory_bytes`.
+    pub fn estimated_memory_bytes(&self) -> usize {
+        self.wheel.estimated_memory_bytes()
+    }
+
+    /// Check the wheels against the task tracker, see `MulitWheel::audit`.
+    pub fn audit(&self) -> Result<(), Vec<AuditError>> {
+        self.wheel.audit()
+    }
+
+    /// Reconcile the wheels with the task tracker, see `MulitWheel::gc_orphans`.
+    pub fn gc_orphans(&self) -> usize {
+        self.wheel.gc_orphans()
+    }
+
+    /// Remove every task tagged with `key=value`, returning how many were removed.
+    pub fn remove_tasks_by_tag(&self, key: &str, value: &str) -> usize {
+        self.wheel.remove_tasks_by_tag(key, value)
+    }
+
+    /// The metadata of a scheduled task: its name, tags, position and next alarm. `None` if it
+    /// is not scheduled.
+    pub fn task_info(&self, task_id: TaskId) -> Option<TaskTrackingInfo> {
+        self.wheel.get_task_tracking_info(task_id)
+    }
+
+    /// Locate a scheduled task: its wheel, slot and the ticks until it fires.
+    pub fn task_location(&self, task_id: TaskId) -> Option<TaskLocation> {
+        self.wheel.task_location(task_id)
+    }
+
+    /// Swap what a scheduled task runs, keeping its id and schedule. The next run uses the new
+    /// runner.
+    pub fn replace_runner<R: SyncTaskRunner>(
+        &self,
+        task_id: TaskId,
+        runner: R,
+    ) -> Result<(), TaskError> {
+        self.wheel
+            .replace_runner(task_id, Arc::new(SyncRunnerAdapter(runner)))
+    }
+
+    /// The number of alarms a countdown task has left, including the pending one.
+    pub fn remaining_count(&self, task_id: TaskId) -> Option<u64> {
+        self.wheel.remaining_count(task_id)
+    }
+
+    /// Set the number of alarms a countdown task has left, including the pending one, e.g. to
+    /// extend it without recreating the task.
+    pub fn set_remaining_count(&self, task_id: TaskId, count: u64) -> Result<(), TaskError> {
+        self.wheel.set_remaining_count(task_id, count)
+    }
+
+    /// The enabled tasks due within `within_secs` seconds, with their alarm timestamps,
+    /// soonest first.
+    pub fn upcoming(&self, within_secs: u64) -> Vec<(TaskId, u64)> {
+        self.wheel.upcoming(within_secs)
+    }
+
+    /// The ids of the tasks the next tick dispatches, in dispatch order, e.g. to see what is
+    /// about to fire without running it.
+    pub fn due_tasks_preview(&self) -> Vec<TaskId> {
+        self.wheel.due_tasks_preview()
+    }
+
+    /// The tick, counted from the creation of the timer, on which the task is next due.
+    pub fn fire_tick(&self, task_id: TaskId) -> Option<u64> {
+        self.wheel.fire_tick(task_id)
+    }
+
+    /// The positions of the hands of the wheels in tick units, for diagnostics.
+    pub fn wheel_positions(&self) -> WheelPositions {
+        self.wheel.positions()
+    }
+
+    /// Reposition the tasks that were overdue when scheduled, e.g. after `load_schedule` restored
+    /// an old snapshot. See `OverduePolicy`. Returns how many tasks were rescheduled.
+    pub fn reschedule_overdue(&self, policy: OverduePolicy) -> usize {
+        self.wheel.reschedule_overdue(policy)
+    }
+
+    /// Take every task whose alarm already passed wherever it waits, e.g. after `load_schedule`
+    /// restored an old snapshot, in the order they would fire. Running them is up to the
+    /// caller; they are already scheduled again for their next alarm.
+    /// See `reschedule_overdue` to let the timer fire them instead.
+    pub fn collect_overdue(&self) -> Vec<Task> {
+        self.wheel.hand_over(self.wheel.collect_overdue())
+    }
+
+    /// Write the metadata of the scheduled tasks to a JSON file, see `Timer::save_schedule`.
+    ///
+    /// Fails if a task has a custom schedule, see `Schedule`.
+    #[cfg(feature = "serde")]
+    pub fn save_schedule(&self, path: impl AsRef<Path>) -> Result<(), TaskError> {
+        ScheduleSnapshot::capture(&self.wheel)?.write(path)
+    }
+
+    /// Schedule the tasks saved by `save_schedule`, resolving their runners through the registry.
+    ///
+    /// Tasks whose alarm already passed while the schedule was stored fire on the next tick.
+    #[cfg(feature = "serde")]
+    pub fn load_schedule(
+        &self,
+        path: impl AsRef<Path>,
+        registry: &RunnerRegistry,
+    ) -> Result<(), TaskError> {
+        ScheduleSnapshot::read(path)?.restore(&self.wheel, registry)
+    }
+
+    /// Encode the metadata of the scheduled tasks in a compact binary form, see
+    /// `Timer::snapshot_bincode`.
+    #[cfg(feature = "bincode")]
+    pub fn snapshot_bincode(&self) -> Result<Vec<u8>, TaskError> {
+        self.wheel.snapshot_bincode()
+    }
+
+    /// Schedule the tasks encoded by `snapshot_bincode`, resolving their runners through the
+    /// registry.
+    #[cfg(feature = "bincode")]
+    pub fn restore_bincode(
+        &self,
+        bytes: &[u8],
+        registry: &RunnerRegistry,
+    ) -> Result<(), TaskError> {
+        self.wheel.restore_bincode(bytes, registry)
+    }
+
+    /// Schedule the tasks declared in a JSON document, building their runners through the
+    /// registry. See `Timer::load_tasks_from_json` for the format and the results.
+    #[cfg(feature = "serde")]
+    pub fn load_tasks_from_json(
+        &self,
+        json: &str,
+        registry: &RunnerRegistry,
+    ) -> Result<Vec<Result<TaskId, TaskError>>, TaskError> {
+        schedule::load_task_definitions(json, &self.wheel, registry)
+    }
+
+    /// Freeze the tasks on a wheel level, e.g. to hold everything more than a minute out.
+    /// See `resume_wheel`; tasks whose slot was passed while paused move on a revolution later.
+    pub fn pause_wheel(&self, wheel_type: WheelType) {
+        self.wheel.pause_wheel(wheel_type)
+    }
+
+    /// Let the tasks on a paused wheel level cascade and fire again.
+    pub fn resume_wheel(&self, wheel_type: WheelType) {
+        self.wheel.resume_wheel(wheel_type)
+    }
+
+    /// Stop firing the task while keeping its schedule, occurrences while disabled are skipped.
+    /// Returns `false` if the task is not scheduled.
+    pub fn disable_task(&self, task_id: TaskId) -> bool {
+        self.wheel.disable_task(task_id)
+    }
+
+    /// Resume firing a disabled task from its next alarm.
+    /// Returns `false` if the task is not scheduled.
+    pub fn enable_task(&self, task_id: TaskId) -> bool {
+        self.wheel.enable_task(task_id)
+    }
+
+    /// Swap the frequency of a scheduled task in place, its next alarm is counted from now.
+    /// The name, tags, priority and runner state of the task are kept.
+    pub fn update_frequency(
+        &self,
+        task_id: TaskId,
+        frequency: FrequencySeconds,
+    ) -> Result<(), TaskError> {
+        self.wheel.update_frequency(task_id, frequency)
+    }
+
+    /// Advance the wheels by one second and run the tasks that arrived on the calling thread.
+    pub fn tick_once(&self) {
+        self.wheel.tick();
+        for task in self.wheel.take_arrived_tasks() {
+            self.dispatch(task);
+        }
+    }
+
+    /// Tick once, or resync the wheels if they fell far behind the wall clock, e.g. after the
+    /// machine was suspended, so the tasks that became due meanwhile fire once.
+    fn advance(&self) {
+        let now = utils::timestamp();
+        if self.wheel.lag_behind(now) > RESYNC_THRESHOLD {
+            for task in self.wheel.resync(now) {
+                self.dispatch(task);
+            }
+        } else {
+            self.tick_once();
+        }
+    }
+
+    fn dispatch(&self, task: Task) {
+        let task_id = task.task_id;
+        let name = task.name.clone();
+        self.events.publish(TimerEvent::TaskFired {
+            task_id,
+            name: name.clone(),
+        });
+        if let Some(event) = TimerEvent::deadline_missed(&task, utils::timestamp()) {
+            self.events.publish(event);
+        }
+
+        // A failing run does not stop the schedule of the task
+        let failed = match utils::block_on(task.runner.run()) {
+            Ok(()) => {
+                self.wheel.record_completion(task_id);
+                self.events
+                    .publish(TimerEvent::TaskCompleted { task_id, name });
+                false
+            }
+            Err(e) => {
+                self.events.publish(TimerEvent::TaskFailed {
+                    task_id,
+                    name,
+                    error: e.to_string(),
+                });
+                true
+            }
+        };
+        if let Err(TaskError::TaskExpired(task_id)) = self.wheel.requeue(task) {
+            self.events.publish(TimerEvent::TaskExpired { task_id });
+        }
+        // After the requeue, so a task that fires again isn't taken for gone
+        if failed {
+            self.wheel.record_failure(task_id);
+        }
+    }
+
+    /// Drive the timer once per second on the calling thread until `shutdown` is called.
+    pub fn run(&self) {
+        let period = Duration::from_secs(1);
+        let mut next_tick = Instant::now() + period;
+        while !self.stopped.load(Ordering::Acquire) {
+            // Sleep until the deadline rather than for a fixed period, so slow runs don't drift
+            thread::sleep(next_tick.saturating_duration_since(Instant::now()));
+            if self.stopped.load(Ordering::Acquire) {
+                break;
+            }
+            self.advance();
+            next_tick += period;
+        }
+        self.events.publish(TimerEvent::StopTimer);
+    }
+
+    /// Run the timer on a dedicated thread.
+    pub fn start(&self) -> JoinHandle<()> {
+        let timer = self.clone();
+        thread::spawn(move || timer.run())
+    }
+
+    /// Stop the running loop after the current tick.
+    pub fn shutdown(&self) {
+        self.stopped.store(true, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+        mpsc,
+    };
+
+    use super::*;
+    use crate::task::{SyncTaskRunner, TaskBuilder};
+
+    struct NotifyingRunner {
+        count: Arc<AtomicU64>,
+        fired: Mutex<mpsc::Sender<()>>,
+    }
+
+    impl SyncTaskRunner for NotifyingRunner {
+        fn run(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            self.count.fetch_add(1, Ordering::SeqCst);
+            let _ = self.fired.lock().unwrap().send(());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_tick_once_runs_sync_task() {
+        let timer = BlockingTimer::new();
+        let count = Arc::new(AtomicU64::new(0));
+        let (sender, _receiver) = mpsc::channel();
+        let task = TaskBuilder::new(1)
+            .with_frequency_repeated_by_seconds(1)
+            .spawn_sync(NotifyingRunner {
+                count: count.clone(),
+                fired: Mutex::new(sender),
+            })
+            .unwrap();
+        timer.add_task(task).unwrap();
+
+        let mut receiver = timer.event_receiver();
+        assert_eq!(timer.due_tasks_preview(), vec![TaskId::new(1)]);
+        assert_eq!(
+            timer.task_info(TaskId::new(1)).unwrap().wheel_type,
+            WheelType::Second
+        );
+        timer.tick_once();
+        timer.tick_once();
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            receiver.try_recv(),
+            Some(TimerEvent::TaskFired {
+                task_id: TaskId::new(1),
+                name: None
+            })
+        );
+        assert_eq!(
+            receiver.try_recv(),
+            Some(TimerEvent::TaskCompleted {
+                task_id: TaskId::new(1),
+                name: None
+            })
+        );
+    }
+
+    #[test]
+    fn test_started_timer_fires_and_joins() {
+        let timer = BlockingTimer::with_event_log_capacity(1);
+        let count = Arc::new(AtomicU64::new(0));
+        let (sender, receiver) = mpsc::channel();
+        let task = TaskBuilder::new(2)
+            .with_frequency_once_by_seconds(1)
+            .spawn_sync(NotifyingRunner {
+                count: count.clone(),
+                fired: Mutex::new(sender),
+            })
+            .unwrap();
+        timer.add_task(task).unwrap();
+
+        let handle = timer.start();
+        receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        timer.shutdown();
+        handle.join().unwrap();
+
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert_eq!(timer.recent_events(), vec![TimerEvent::StopTimer]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_save_and_load_schedule() {
+        let path = std::env::temp_dir().join(format!(
+            "minitimer-blocking-schedule-{}.json",
+            std::process::id()
+        ));
+        let count = Arc::new(AtomicU64::new(0));
+        let (sender, _receiver) = mpsc::channel();
+        let sender = Arc::new(Mutex::new(sender));
+
+        let timer = BlockingTimer::new();
+        let task = TaskBuilder::new(3)
+            .with_frequency_once_by_seconds(2)
+            .spawn_sync(NotifyingRunner {
+                count: count.clone(),
+                fired: Mutex::new(sender.lock().unwrap().clone()),
+            })
+            .unwrap();
+        timer.add_task(task).unwrap();
+        timer.save_schedule(&path).unwrap();
+
+        let restored = BlockingTimer::new();
+        let mut registry = RunnerRegistry::new();
+        registry.register_task(TaskId::new(3), {
+            let count = count.clone();
+            move || {
+                Arc::new(SyncRunnerAdapter(NotifyingRunner {
+                    count: count.clone(),
+                    fired: Mutex::new(sender.lock().unwrap().clone()),
+                }))
+            }
+        });
+        restored.load_schedule(&path, &registry).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(restored.contains(TaskId::new(3)));
+
+        restored.tick_once();
+        restored.tick_once();
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert!(!restored.contains(TaskId::new(3)));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_load_tasks_from_json() {
+        let json = r#"{ "tasks": [
+            { "task_id": 1, "runner_name": "cleanup",
+              "frequency": { "type": "Repeated", "seconds": 1 } },
+            { "task_id": 2, "runner_name": "missing",
+              "frequency": { "type": "Once", "seconds": 10 } }
+        ] }"#;
+        let count = Arc::new(AtomicU64::new(0));
+        let (sender, _receiver) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        let mut registry = RunnerRegistry::new();
+        registry.register("cleanup", {
+            let count = count.clone();
+            move || {
+                Arc::new(SyncRunnerAdapter(NotifyingRunner {
+                    count: count.clone(),
+                    fired: Mutex::new(sender.lock().unwrap().clone()),
+                }))
+            }
+        });
+
+        let timer = BlockingTimer::new();
+        let results = timer.load_tasks_from_json(json, &registry).unwrap();
+        assert!(matches!(results[0], Ok(task_id) if task_id == TaskId::new(1)));
+        assert!(
+            matches!(results[1], Err(TaskError::RunnerNotRegistered(task_id)) if task_id == TaskId::new(2))
+        );
+
+        timer.tick_once();
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,241 @@
+//! The event channel backend.
+//!
+//! `async-channel` is used by default, a single channel is shared by all receivers so each
+//! event is delivered to only one of them. With the `tokio-channel` feature a
+//! `tokio::sync::broadcast` channel is used instead, every receiver gets every event sent
+//! after it subscribed and a lagging receiver skips the events it missed.
+
+use crate::timer::TimerEvent;
+
+#[cfg(not(any(feature = "async-channel", feature = "tokio-channel")))]
+compile_error!("either the `async-channel` or the `tokio-channel` feature must be enabled");
+
+#[cfg(not(feature = "tokio-channel"))]
+mod backend {
+    use crate::timer::TimerEvent;
+
+    #[derive(Clone)]
+    pub(crate) struct EventSender {
+        sender: async_channel::Sender<TimerEvent>,
+        receiver: async_channel::Receiver<TimerEvent>,
+    }
+
+    impl EventSender {
+        pub(crate) fn new(capacity: usize) -> Self {
+            let (sender, receiver) = async_channel::bounded(capacity);
+            Self { sender, receiver }
+        }
+
+        /// Events are dropped while the channel is full.
+        pub(crate) fn send(&self, event: TimerEvent) {
+            let _ = self.sender.try_send(event);
+        }
+
+        /// Send the event if the channel has room for it, otherwise hand it back.
+        pub(crate) fn try_deliver(&self, event: TimerEvent) -> Result<(), TimerEvent> {
+            self.sender.try_send(event).map_err(|e| e.into_inner())
+        }
+
+        /// Wait for room in the channel and send the event.
+        #[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+        pub(crate) async fn deliver(&self, event: TimerEvent) {
+            // The sender keeps a receiver, so the channel never closes
+            let _ = self.sender.send(event).await;
+        }
+
+        pub(crate) fn subscribe(&self) -> Receiver {
+            Receiver(self.receiver.clone())
+        }
+    }
+
+    #[derive(Clone)]
+    pub(crate) struct Receiver(async_channel::Receiver<TimerEvent>);
+
+    impl Receiver {
+        pub(crate) async fn recv(&mut self) -> Option<TimerEvent> {
+            self.0.recv().await.ok()
+        }
+
+        pub(crate) fn try_recv(&mut self) -> Option<TimerEvent> {
+            self.0.try_recv().ok()
+        }
+
+        pub(crate) fn is_empty(&self) -> bool {
+            self.0.is_empty()
+        }
+    }
+}
+
+#[cfg(feature = "tokio-channel")]
+mod backend {
+    use tokio::sync::broadcast::{
+        self,
+        error::{RecvError, TryRecvError},
+    };
+
+    use crate::timer::TimerEvent;
+
+    #[derive(Clone)]
+    pub(crate) struct EventSender {
+        sender: broadcast::Sender<TimerEvent>,
+        capacity: usize,
+    }
+
+    impl EventSender {
+        pub(crate) fn new(capacity: usize) -> Self {
+            let (sender, _) = broadcast::channel(capacity);
+            Self { sender, capacity }
+        }
+
+        /// Events are dropped while there is no receiver.
+        pub(crate) fn send(&self, event: TimerEvent) {
+            let _ = self.sender.send(event);
+        }
+
+        /// Send the event unless the slowest receiver would lose an event it hasn't received
+        /// yet, otherwise hand it back.
+        pub(crate) fn try_deliver(&self, event: TimerEvent) -> Result<(), TimerEvent> {
+            if self.sender.len() >= self.capacity {
+                return Err(event);
+            }
+            let _ = self.sender.send(event);
+            Ok(())
+        }
+
+        /// Wait until every receiver has room for the event and send it. Receiving doesn't
+        /// notify the sender, so the room is polled for.
+        pub(crate) async fn deliver(&self, mut event: TimerEvent) {
+            while let Err(rejected) = self.try_deliver(event) {
+                event = rejected;
+                tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+            }
+        }
+
+        pub(crate) fn subscribe(&self) -> Receiver {
+            Receiver(self.sender.subscribe())
+        }
+    }
+
+    pub(crate) struct Receiver(broadcast::Receiver<TimerEvent>);
+
+    impl Clone for Receiver {
+        fn clone(&self) -> Self {
+            Self(self.0.resubscribe())
+        }
+    }
+
+    impl Receiver {
+        pub(crate) async fn recv(&mut self) -> Option<TimerEvent> {
+            loop {
+                match self.0.recv().await {
+                    Ok(event) => return Some(event),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        }
+
+        pub(crate) fn try_recv(&mut self) -> Option<TimerEvent> {
+            loop {
+                match self.0.try_recv() {
+                    Ok(event) => return Some(event),
+                    Err(TryRecvError::Lagged(_)) => continue,
+                    Err(_) => return None,
+                }
+            }
+        }
+
+        pub(crate) fn is_empty(&self) -> bool {
+            self.0.is_empty()
+        }
+    }
+}
+
+pub(crate) use backend::EventSender;
+
+/// Receives the events published by a `Timer`.
+#[derive(Clone)]
+pub struct EventReceiver {
+    inner: backend::Receiver,
+}
+
+impl EventReceiver {
+    pub(crate) fn new(sender: &EventSender) -> Self {
+        Self {
+            inner: sender.subscribe(),
+        }
+    }
+
+    /// Wait for the next event, `None` once the channel is closed.
+    pub async fn recv(&mut self) -> Option<TimerEvent> {
+        self.inner.recv().await
+    }
+
+    /// Take the next event if one is buffered.
+    pub fn try_recv(&mut self) -> Option<TimerEvent> {
+        self.inner.try_recv()
+    }
+
+    /// Take every buffered event without waiting, e.g. to capture the final events of a timer
+    /// for an audit log once its run loop returned. The events stay buffered after the timer
+    /// is dropped, as long as a receiver is alive.
+    pub fn drain_events(&mut self) -> Vec<TimerEvent> {
+        std::iter::from_fn(|| self.try_recv()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::TaskId;
+
+    #[tokio::test]
+    async fn test_receiver_gets_sent_events() {
+        let sender = EventSender::new(4);
+        let mut receiver = EventReceiver::new(&sender);
+        assert!(receiver.is_empty());
+
+        sender.send(TimerEvent::TaskFired {
+            task_id: TaskId::new(1),
+            name: None,
+        });
+        sender.send(TimerEvent::StopTimer);
+        assert_eq!(
+            receiver.recv().await,
+            Some(TimerEvent::TaskFired {
+                task_id: TaskId::new(1),
+                name: None
+            })
+        );
+        assert_eq!(receiver.try_recv(), Some(TimerEvent::StopTimer));
+        assert_eq!(receiver.try_recv(), None);
+    }
+
+    #[cfg(feature = "tokio-channel")]
+    #[tokio::test]
+    async fn test_every_receiver_gets_every_event() {
+        let sender = EventSender::new(4);
+        let mut first = EventReceiver::new(&sender);
+        let mut second = first.clone();
+
+        sender.send(TimerEvent::StopTimer);
+        assert_eq!(first.recv().await, Some(TimerEvent::StopTimer));
+        assert_eq!(second.recv().await, Some(TimerEvent::StopTimer));
+    }
+
+    #[cfg(not(feature = "tokio-channel"))]
+    #[tokio::test]
+    async fn test_receivers_share_the_channel() {
+        let sender = EventSender::new(4);
+        let mut first = EventReceiver::new(&sender);
+        let second = first.clone();
+
+        sender.send(TimerEvent::StopTimer);
+        assert_eq!(first.recv().await, Some(TimerEvent::StopTimer));
+        assert!(second.is_empty());
+    }
+}
@@ -1,17 +1,119 @@
+#[cfg(feature = "stream")]
+use futures::Stream;
 use std::time::Duration;
+use tokio::sync::watch;
 use tokio::time::Instant;
 use tokio::time::{Interval, interval_at};
-pub(crate) struct Clock {
+
+/// The tick source driving the timer, one tick per period.
+///
+/// Ticks that are late are caught up with a burst, so the clock does not drift over time.
+/// That includes the ticks missed while the clock was paused.
+pub struct Clock {
     inner: Interval,
+    paused: watch::Sender<bool>,
 }
 
 impl Clock {
-    pub(crate) fn new() -> Self {
-        let inner = interval_at(Instant::now(), Duration::from_secs(1));
-        Self { inner }
+    /// The first tick completes one second from now.
+    pub fn new() -> Self {
+        Self::with_period(Duration::from_secs(1))
+    }
+
+    /// The first tick completes one `period` from now.
+    ///
+    /// # Panics
+    /// Panics if `period` is zero.
+    pub fn with_period(period: Duration) -> Self {
+        let inner = interval_at(Instant::now() + period, period);
+        let (paused, _) = watch::channel(false);
+        Self { inner, paused }
+    }
+
+    /// Wait for the next tick and get the instant it was scheduled for.
+    ///
+    /// Blocks for as long as the clock is paused, even if the tick is already due.
+    pub async fn tick(&mut self) -> Instant {
+        let mut paused = self.paused.subscribe();
+        loop {
+            // Never fails, the receiver and the sender live in the same clock
+            let _ = paused.wait_for(|paused| !paused).await;
+            tokio::select! {
+                biased;
+                _ = paused.wait_for(|paused| *paused) => continue,
+                instant = self.inner.tick() => return instant,
+            }
+        }
+    }
+
+    /// Stop ticking until `resume`, keeping the schedule of the ticks.
+    pub fn pause(&self) {
+        self.paused.send_replace(true);
+    }
+
+    /// Tick again after a `pause`. Ticks that fell due in between are caught up with a burst.
+    pub fn resume(&self) {
+        self.paused.send_replace(false);
+    }
+
+    /// Whether the clock is paused.
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    /// Turn the clock into a stream of its ticks, for composing with `StreamExt`.
+    #[cfg(feature = "stream")]
+    pub fn into_stream(self) -> impl Stream<Item = Instant> {
+        futures::stream::unfold(self, |mut clock| async move {
+            let instant = clock.tick().await;
+            Some((instant, clock))
+        })
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "stream")]
+    use futures::StreamExt;
+    use tokio::time::timeout;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pause_blocks_ticks_until_resumed() {
+        let period = Duration::from_millis(10);
+        let mut clock = Clock::with_period(period);
+        clock.pause();
+        assert!(clock.is_paused());
+        assert!(timeout(period * 5, clock.tick()).await.is_err());
+
+        clock.resume();
+        assert!(!clock.is_paused());
+        let start = Instant::now();
+        let instant = timeout(period * 5, clock.tick()).await.unwrap();
+        // The first tick fell due while paused, so it completes right away
+        assert!(instant < start);
     }
 
-    pub(crate) async fn tick(&mut self) {
-        self.inner.tick().await;
+    #[cfg(feature = "stream")]
+    #[tokio::test]
+    async fn test_into_stream_yields_ticks() {
+        let period = Duration::from_millis(10);
+        let start = Instant::now();
+        let ticks: Vec<Instant> = Clock::with_period(period)
+            .into_stream()
+            .take(3)
+            .collect()
+            .await;
+        assert_eq!(ticks.len(), 3);
+        assert!(ticks[0] >= start + period);
+        assert_eq!(ticks[1] - ticks[0], period);
+        assert_eq!(ticks[2] - ticks[1], period);
     }
 }
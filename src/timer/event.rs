@@ -1,4 +1,275 @@
-#[derive(Debug)]
+use std::{
+    collections::VecDeque,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use crate::{
+    task::{Task, TaskId},
+    timer::channel::EventSender,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TimerEvent {
     StopTimer,
+    /// The task arrived and its runner has been dispatched.
+    TaskFired {
+        task_id: TaskId,
+        name: Option<String>,
+    },
+    /// The runner of the task returned `Ok`.
+    TaskCompleted {
+        task_id: TaskId,
+        name: Option<String>,
+    },
+    /// The runner of the task returned an error, or panicked under `PanicStrategy::Isolate`.
+    TaskFailed {
+        task_id: TaskId,
+        name: Option<String>,
+        error: String,
+    },
+    /// A run of the task failed and its group was aborted, see `TaskGroup::abort_on_failure`.
+    /// `cancelled` holds the ids of the tasks removed, in ascending order.
+    GroupAborted {
+        group_id: u64,
+        task_id: TaskId,
+        cancelled: Vec<TaskId>,
+    },
+    /// The task failed `failures` runs in a row and was removed, see
+    /// `TaskBuilder::with_max_consecutive_failures`.
+    TaskDescheduled {
+        task_id: TaskId,
+        failures: u32,
+    },
+    /// The next alarm of the task was after its deadline and it was removed, see
+    /// `TaskBuilder::with_auto_cancel_at`.
+    TaskExpired {
+        task_id: TaskId,
+    },
+    /// The task fired later than its maximum lateness allows, timestamps are in seconds.
+    DeadlineMissed {
+        task_id: TaskId,
+        scheduled: u64,
+        fired: u64,
+        lateness: Duration,
+    },
+    /// No task is scheduled or running any more: the last one finished its schedule and its
+    /// run, or was removed through `Timer::remove_task` or `Timer::remove_tasks_by_tag`.
+    Idle,
+}
+
+impl TimerEvent {
+    /// The `DeadlineMissed` event for a task fired at `fired`, if it was too late.
+    pub(crate) fn deadline_missed(task: &Task, fired: u64) -> Option<Self> {
+        let max_lateness = task.max_lateness?;
+        let lateness = Duration::from_secs(fired.saturating_sub(task.alarm_timestamp));
+        (lateness > max_lateness).then_some(Self::DeadlineMissed {
+            task_id: task.task_id,
+            scheduled: task.alarm_timestamp,
+            fired,
+            lateness,
+        })
+    }
+}
+
+/// How hard a `Timer` tries to deliver its events, see `TimerBuilder::with_event_delivery`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum EventDelivery {
+    /// Events the channel can't take are dropped, the timer never waits for its receivers.
+    #[default]
+    BestEffort,
+    /// `TaskFired`, `TaskCompleted` and `TaskFailed` are never dropped: when the channel is
+    /// full they are held back, and every event after them too so the order is kept, until
+    /// the receivers catch up. Other events are still dropped when the channel is full.
+    ///
+    /// The run loop delivers the held back events before every tick, and a run delivers them
+    /// before it finishes. A slow receiver therefore delays the dispatch of due tasks and
+    /// keeps runs in flight longer, the timer runs at the pace of its slowest receiver. With
+    /// the `tokio-channel` feature every receiver gets every event; with `async-channel` each
+    /// event still goes to only one of the receivers.
+    Strict,
+}
+
+impl TimerEvent {
+    /// Whether the event is never dropped under `EventDelivery::Strict`.
+    fn is_critical(&self) -> bool {
+        matches!(
+            self,
+            Self::TaskFired { .. } | Self::TaskCompleted { .. } | Self::TaskFailed { .. }
+        )
+    }
+}
+
+/// Fixed-size ring buffer retaining the most recent events.
+pub(crate) struct EventLog {
+    capacity: usize,
+    events: VecDeque<TimerEvent>,
+}
+
+impl EventLog {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            events: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Record an event, overwriting the oldest one when the log is full.
+    pub(crate) fn push(&mut self, event: TimerEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    pub(crate) fn events(&self) -> Vec<TimerEvent> {
+        self.events.iter().cloned().collect()
+    }
+}
+
+/// Publishes timer events to the event channel and the optional event log.
+pub(crate) struct EventPublisher {
+    sender: EventSender,
+    log: Option<Mutex<EventLog>>,
+    delivery: EventDelivery,
+    /// The events held back under `EventDelivery::Strict`, oldest first.
+    backlog: Mutex<VecDeque<TimerEvent>>,
+    #[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+    flushing: AtomicBool,
+}
+
+impl EventPublisher {
+    pub(crate) fn new(sender: EventSender, log_capacity: Option<usize>) -> Self {
+        Self {
+            sender,
+            log: log_capacity.map(|capacity| Mutex::new(EventLog::new(capacity))),
+            delivery: EventDelivery::BestEffort,
+            backlog: Mutex::new(VecDeque::new()),
+            flushing: AtomicBool::new(false),
+        }
+    }
+
+    #[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+    pub(crate) fn with_delivery(mut self, delivery: EventDelivery) -> Self {
+        self.delivery = delivery;
+        self
+    }
+
+    /// Events are best-effort by default: when the channel can't take the event it is dropped
+    /// from the channel, but it is still recorded in the log. See `EventDelivery::Strict` for
+    /// the events held back instead.
+    pub(crate) fn publish(&self, event: TimerEvent) {
+        if let Some(log) = &self.log {
+            log.lock().unwrap().push(event.clone());
+        }
+        if self.delivery == EventDelivery::BestEffort {
+            return self.sender.send(event);
+        }
+        let mut backlog = self.backlog.lock().unwrap();
+        if !backlog.is_empty() {
+            backlog.push_back(event);
+        } else if let Err(event) = self.sender.try_deliver(event)
+            && event.is_critical()
+        {
+            backlog.push_back(event);
+        }
+    }
+
+    /// Deliver the held back events, waiting for the receivers to make room for them.
+    ///
+    /// Only one caller delivers at a time, the others return right away; the deliverer keeps
+    /// going until the backlog is empty, including the events held back meanwhile.
+    #[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+    pub(crate) async fn flush(&self) {
+        struct Flushing<'a>(&'a AtomicBool);
+
+        impl Drop for Flushing<'_> {
+            fn drop(&mut self) {
+                self.0.store(false, Ordering::SeqCst);
+            }
+        }
+
+        loop {
+            if self.flushing.swap(true, Ordering::SeqCst) {
+                return;
+            }
+            let flushing = Flushing(&self.flushing);
+            // An event leaves the backlog once delivered, so events published meanwhile
+            // queue up behind it
+            loop {
+                let Some(event) = self.backlog.lock().unwrap().front().cloned() else {
+                    break;
+                };
+                self.sender.deliver(event).await;
+                self.backlog.lock().unwrap().pop_front();
+            }
+            drop(flushing);
+            // Held back after the last check but before the flag was cleared
+            if self.backlog.lock().unwrap().is_empty() {
+                return;
+            }
+        }
+    }
+
+    pub(crate) fn sender(&self) -> &EventSender {
+        &self.sender
+    }
+
+    pub(crate) fn recent_events(&self) -> Vec<TimerEvent> {
+        match &self.log {
+            Some(log) => log.lock().unwrap().events(),
+            None => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timer::EventReceiver;
+
+    #[test]
+    fn test_event_log_keeps_most_recent() {
+        let mut log = EventLog::new(3);
+        for task_id in 0..5 {
+            log.push(TimerEvent::TaskFired {
+                task_id: task_id.into(),
+                name: None,
+            });
+        }
+
+        assert_eq!(
+            log.events(),
+            vec![
+                TimerEvent::TaskFired {
+                    task_id: TaskId::new(2),
+                    name: None
+                },
+                TimerEvent::TaskFired {
+                    task_id: TaskId::new(3),
+                    name: None
+                },
+                TimerEvent::TaskFired {
+                    task_id: TaskId::new(4),
+                    name: None
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_event_publisher_without_log() {
+        let publisher = EventPublisher::new(EventSender::new(4), None);
+        let mut receiver = EventReceiver::new(publisher.sender());
+        publisher.publish(TimerEvent::StopTimer);
+
+        assert!(publisher.recent_events().is_empty());
+        assert_eq!(receiver.try_recv().unwrap(), TimerEvent::StopTimer);
+    }
 }
@@ -0,0 +1,159 @@
+use std::{fmt, sync::Arc};
+
+use crate::{task::TaskId, timer::wheel::MulitWheel};
+
+/// A handle on the tasks scheduled together by `Timer::add_group`.
+///
+/// Membership is tracked by the id of the group, so the handle only ever operates on its own
+/// tasks. Tasks leave the group when they are removed or their schedule is finished; a removed
+/// task added back on its own rejoins it.
+pub struct TaskGroup {
+    group_id: u64,
+    wheel: Arc<MulitWheel>,
+    cancel_on_drop: bool,
+}
+
+impl TaskGroup {
+    pub(crate) fn new(group_id: u64, wheel: Arc<MulitWheel>) -> Self {
+        Self {
+            group_id,
+            wheel,
+            cancel_on_drop: false,
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.group_id
+    }
+
+    /// Cancel the tasks of the group when the handle is dropped.
+    pub fn cancel_on_drop(mut self) -> Self {
+        self.cancel_on_drop = true;
+        self
+    }
+
+    /// Cancel the remaining tasks of the group as soon as a run of one of them fails, e.g. for
+    /// the steps of a pipeline. `TimerEvent::GroupAborted` then lists the cancelled tasks.
+    ///
+    /// Runs already in flight keep going. The group is aborted once, tasks added back to it
+    /// later are not cancelled by another failure.
+    pub fn abort_on_failure(self) -> Self {
+        self.wheel.set_abort_on_failure(self.group_id);
+        self
+    }
+
+    /// The ids of the scheduled tasks of the group, in ascending order.
+    pub fn task_ids(&self) -> Vec<TaskId> {
+        self.wheel.group_task_ids(self.group_id)
+    }
+
+    /// The number of scheduled tasks of the group.
+    pub fn len(&self) -> usize {
+        self.task_ids().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove every task of the group, returning how many were removed.
+    pub fn cancel_all(&self) -> usize {
+        let group_id = Some(self.group_id);
+        self.wheel
+            .remove_tasks_where(|info| info.group_id == group_id)
+            .len()
+    }
+
+    /// Disable every task of the group, see `Timer::disable_task`. Returns how many were paused.
+    pub fn pause_all(&self) -> usize {
+        self.set_enabled(false)
+    }
+
+    /// Enable every task of the group again, returning how many were resumed.
+    pub fn resume_all(&self) -> usize {
+        self.set_enabled(true)
+    }
+
+    fn set_enabled(&self, enabled: bool) -> usize {
+        self.task_ids()
+            .into_iter()
+            .filter(|&task_id| match enabled {
+                true => self.wheel.enable_task(task_id),
+                false => self.wheel.disable_task(task_id),
+            })
+            .count()
+    }
+}
+
+impl fmt::Debug for TaskGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskGroup")
+            .field("group_id", &self.group_id)
+            .field("task_ids", &self.task_ids())
+            .field("cancel_on_drop", &self.cancel_on_drop)
+            .finish()
+    }
+}
+
+impl Drop for TaskGroup {
+    fn drop(&mut self) {
+        if self.cancel_on_drop {
+            self.cancel_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::{Task, TaskBuilder, TaskContext};
+
+    fn task(task_id: u64) -> Task {
+        TaskBuilder::new(task_id)
+            .with_frequency_repeated_by_seconds(5)
+            .spawn_fn(|_ctx: TaskContext| async {})
+            .unwrap()
+    }
+
+    #[test]
+    fn test_cancel_group() {
+        let wheel = Arc::new(MulitWheel::new());
+        wheel.add_task(task(1)).unwrap();
+        let group_id = wheel.add_group((2..=4).map(task).collect()).unwrap();
+        wheel.add_task(task(5)).unwrap();
+        let group = TaskGroup::new(group_id, wheel.clone());
+        assert_eq!(group.len(), 3);
+        assert_eq!(group.task_ids(), [2, 3, 4].map(TaskId::new));
+
+        assert_eq!(group.pause_all(), 3);
+        assert!(
+            !wheel
+                .get_task_tracking_info(TaskId::new(3))
+                .unwrap()
+                .enabled
+        );
+        assert!(
+            wheel
+                .get_task_tracking_info(TaskId::new(5))
+                .unwrap()
+                .enabled
+        );
+
+        assert_eq!(group.cancel_all(), 3);
+        assert!(group.is_empty());
+        assert_eq!(wheel.len(), 2);
+        assert!(wheel.contains(TaskId::new(1)) && wheel.contains(TaskId::new(5)));
+
+        // A failing task leaves none of its group behind
+        let result = wheel.add_group(vec![task(6), task(1)]);
+        assert!(matches!(
+            result,
+            Err(crate::error::TaskError::DuplicateTask(_))
+        ));
+        assert!(!wheel.contains(TaskId::new(6)));
+
+        let group_id = wheel.add_group(vec![task(7)]).unwrap();
+        drop(TaskGroup::new(group_id, wheel.clone()).cancel_on_drop());
+        assert!(!wheel.contains(TaskId::new(7)));
+    }
+}
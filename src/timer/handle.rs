@@ -0,0 +1,141 @@
+use std::{fmt, sync::Arc};
+
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{
+    error::TaskError,
+    task::{Task, TaskId, frequency::FrequencySeconds},
+    timer::{timer::InFlightRuns, wheel::MulitWheel},
+};
+
+/// A request to the run loop of a `Timer`, answered on the oneshot sender.
+pub(crate) enum TimerCommand {
+    Add(Box<Task>, oneshot::Sender<Result<(), TaskError>>),
+    Remove(TaskId, oneshot::Sender<Option<Task>>),
+    Reschedule(
+        TaskId,
+        FrequencySeconds,
+        oneshot::Sender<Result<(), TaskError>>,
+    ),
+}
+
+impl TimerCommand {
+    /// Apply the command to the wheel. The reply is dropped if the requester stopped waiting.
+    pub(crate) fn apply(self, wheel: &MulitWheel) {
+        match self {
+            Self::Add(task, reply) => {
+                let _ = reply.send(wheel.add_task(*task));
+            }
+            Self::Remove(task_id, reply) => {
+                let _ = reply.send(wheel.remove_task(task_id));
+            }
+            Self::Reschedule(task_id, frequency, reply) => {
+                let _ = reply.send(wheel.update_frequency(task_id, frequency));
+            }
+        }
+    }
+}
+
+/// A cloneable handle sending commands to the run loop of a `Timer`, e.g. from inside a runner.
+///
+/// Commands are applied between ticks. Commands sent while the loop is not running are queued
+/// until it runs, or applied when the loop is shutting down.
+#[derive(Clone)]
+pub struct TimerHandle {
+    commands: mpsc::UnboundedSender<TimerCommand>,
+}
+
+impl TimerHandle {
+    pub(crate) fn new(commands: mpsc::UnboundedSender<TimerCommand>) -> Self {
+        Self { commands }
+    }
+
+    /// Schedule a task for its next alarm.
+    pub async fn add_task(&self, task: Task) -> Result<(), TaskError> {
+        let (reply, response) = oneshot::channel();
+        self.send(TimerCommand::Add(Box::new(task), reply))?;
+        response.await.map_err(|_| TaskError::TimerDropped)?
+    }
+
+    /// Remove a scheduled task, returning it if it was present.
+    pub async fn remove_task(&self, task_id: TaskId) -> Result<Option<Task>, TaskError> {
+        let (reply, response) = oneshot::channel();
+        self.send(TimerCommand::Remove(task_id, reply))?;
+        response.await.map_err(|_| TaskError::TimerDropped)
+    }
+
+    /// Replace the frequency of a scheduled task, its next alarm is counted from now.
+    ///
+    /// This is [`Timer::update_frequency`](crate::timer::Timer::update_frequency) routed
+    /// through the run loop.
+    pub async fn reschedule(
+        &self,
+        task_id: TaskId,
+        frequency: FrequencySeconds,
+    ) -> Result<(), TaskError> {
+        let (reply, response) = oneshot::channel();
+        self.send(TimerCommand::Reschedule(task_id, frequency, reply))?;
+        response.await.map_err(|_| TaskError::TimerDropped)?
+    }
+
+    fn send(&self, command: TimerCommand) -> Result<(), TaskError> {
+        self.commands
+            .send(command)
+            .map_err(|_| TaskError::TimerDropped)
+    }
+}
+
+/// A handle on a single task scheduled by `Timer::schedule`.
+///
+/// Unlike `TimerHandle`, it operates on the timer directly rather than through the run loop,
+/// so it works whether the loop is running or not.
+#[derive(Clone)]
+pub struct TaskHandle {
+    task_id: TaskId,
+    wheel: Arc<MulitWheel>,
+    in_flight: Arc<InFlightRuns>,
+}
+
+impl TaskHandle {
+    pub(crate) fn new(
+        task_id: TaskId,
+        wheel: Arc<MulitWheel>,
+        in_flight: Arc<InFlightRuns>,
+    ) -> Self {
+        Self {
+            task_id,
+            wheel,
+            in_flight,
+        }
+    }
+
+    pub fn id(&self) -> TaskId {
+        self.task_id
+    }
+
+    /// Whether the task is still scheduled, i.e. neither cancelled nor finished.
+    pub fn is_scheduled(&self) -> bool {
+        self.wheel.contains(self.task_id)
+    }
+
+    /// Remove the task, returning it if it was still scheduled. Runs of the task in flight are
+    /// aborted as well, like `Timer::remove_task`.
+    pub fn cancel(&self) -> Option<Task> {
+        self.in_flight.remove_task(self.task_id)
+    }
+
+    /// Replace the frequency of the task, its next alarm is counted from now. See
+    /// `Timer::update_frequency`.
+    pub fn reschedule(&self, frequency: FrequencySeconds) -> Result<(), TaskError> {
+        self.wheel.update_frequency(self.task_id, frequency)
+    }
+}
+
+impl fmt::Debug for TaskHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskHandle")
+            .field("task_id", &self.task_id)
+            .field("scheduled", &self.is_scheduled())
+            .finish()
+    }
+}
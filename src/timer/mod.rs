@@ -1,9 +1,33 @@
+pub mod blocking;
+mod channel;
+#[cfg(feature = "tokio")]
 mod clock;
 pub mod event;
+mod group;
+#[cfg(feature = "tokio")]
+mod handle;
+#[cfg(feature = "serde")]
+pub mod schedule;
 pub(crate) mod slot;
+#[cfg(feature = "tokio")]
+#[allow(clippy::module_inception)]
 pub mod timer;
 pub(crate) mod wheel;
 
-pub(crate) use clock::Clock;
-pub use event::TimerEvent;
-pub use timer::Timer;
+pub use blocking::BlockingTimer;
+pub use channel::EventReceiver;
+pub(crate) use channel::EventSender;
+#[cfg(feature = "tokio")]
+pub use clock::Clock;
+pub use event::{EventDelivery, TimerEvent};
+pub use group::TaskGroup;
+#[cfg(feature = "tokio")]
+pub use handle::{TaskHandle, TimerHandle};
+#[cfg(feature = "tokio")]
+pub use timer::{
+    CompletedTask, PanicStrategy, TaskOutcome, TickCallback, Timer, TimerBuilder, TimerStats,
+};
+pub use wheel::{
+    AuditError, MulitWheelBuilder, OverduePolicy, TaskLocation, TaskTrackingInfo, TieBreak,
+    WheelCascadeGuide, WheelPositions, WheelType,
+};
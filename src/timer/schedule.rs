@@ -0,0 +1,610 @@
+use std::{collections::HashMap, path::Path, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::TaskError,
+    task::{
+        MissedPolicy, RunnerRegistry, Task, TaskBuilder, TaskId,
+        frequency::{FrequencySeconds, FrequencyState},
+    },
+    timer::wheel::MulitWheel,
+};
+
+/// The serializable metadata of a scheduled task.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskRecord {
+    pub task_id: TaskId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tags: HashMap<String, String>,
+    #[serde(default)]
+    pub priority: u8,
+    /// The remaining frequency, counting the pending alarm for countdowns.
+    pub frequency: FrequencySeconds,
+    /// The timestamp of the pending alarm.
+    pub alarm_timestamp: u64,
+    /// The registry name of the runner, the task id is used when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runner_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dedup_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<TaskId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_lateness: Option<Duration>,
+    #[serde(default)]
+    pub missed_policy: MissedPolicy,
+    /// The timestamp after which the task no longer fires.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_cancel_at: Option<u64>,
+}
+
+impl TryFrom<&Task> for TaskRecord {
+    type Error = TaskError;
+
+    /// Fails for a task with a custom schedule, which can't be recorded.
+    fn try_from(task: &Task) -> Result<Self, Self::Error> {
+        let frequency = task.frequency_seconds.ok_or_else(|| {
+            TaskError::Persistence(format!("task {} has a custom schedule", task.task_id))
+        })?;
+        let frequency = match frequency {
+            FrequencySeconds::CountDown(_, seconds) => FrequencySeconds::CountDown(
+                task.frequency.remaining_count().unwrap_or_default() + 1,
+                seconds,
+            ),
+            frequency => frequency,
+        };
+        Ok(Self {
+            task_id: task.task_id,
+            name: task.name.clone(),
+            tags: task.tags.clone(),
+            priority: task.priority,
+            frequency,
+            alarm_timestamp: task.alarm_timestamp,
+            runner_name: task.runner_name.clone(),
+            dedup_key: task.dedup_key.clone(),
+            depends_on: task.depends_on.clone(),
+            max_lateness: task.max_lateness,
+            missed_policy: task.missed_policy,
+            auto_cancel_at: task.auto_cancel_at(),
+        })
+    }
+}
+
+/// A persisted schedule, the runners are resolved by a `RunnerRegistry` on restore.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduleSnapshot {
+    pub tasks: Vec<TaskRecord>,
+}
+
+impl ScheduleSnapshot {
+    pub(crate) fn capture(wheel: &MulitWheel) -> Result<Self, TaskError> {
+        let mut tasks = vec![];
+        wheel.for_each_task(|task| tasks.push(TaskRecord::try_from(task)));
+        let mut tasks = tasks.into_iter().collect::<Result<Vec<_>, _>>()?;
+        // Completions aren't persisted, a restored task waits only for what is still pending
+        for record in tasks.iter_mut() {
+            record
+                .depends_on
+                .retain(|&task_id| !wheel.has_completed(task_id));
+        }
+        tasks.sort_by_key(|record| record.task_id);
+        Ok(Self { tasks })
+    }
+
+    /// Write the snapshot to a JSON file.
+    pub(crate) fn write(&self, path: impl AsRef<Path>) -> Result<(), TaskError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| TaskError::Persistence(e.to_string()))?;
+        std::fs::write(path, json).map_err(|e| TaskError::Persistence(e.to_string()))
+    }
+
+    /// Read a snapshot written by `write`.
+    pub(crate) fn read(path: impl AsRef<Path>) -> Result<Self, TaskError> {
+        let json =
+            std::fs::read_to_string(path).map_err(|e| TaskError::Persistence(e.to_string()))?;
+        serde_json::from_str(&json).map_err(|e| TaskError::Persistence(e.to_string()))
+    }
+
+    /// Encode the snapshot with bincode.
+    #[cfg(feature = "bincode")]
+    pub(crate) fn to_bytes(&self) -> Result<Vec<u8>, TaskError> {
+        let records = self
+            .tasks
+            .iter()
+            .cloned()
+            .map(BinaryRecord::from)
+            .collect::<Vec<_>>();
+        bincode::serialize(&records).map_err(|e| TaskError::Persistence(e.to_string()))
+    }
+
+    /// Decode a snapshot encoded by `to_bytes`.
+    #[cfg(feature = "bincode")]
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<Self, TaskError> {
+        let records: Vec<BinaryRecord> =
+            bincode::deserialize(bytes).map_err(|e| TaskError::Persistence(e.to_string()))?;
+        Ok(Self {
+            tasks: records.into_iter().map(TaskRecord::from).collect(),
+        })
+    }
+
+    /// Add the recorded tasks to the wheel. Alarms that are already past fire on the next tick.
+    pub(crate) fn restore(
+        &self,
+        wheel: &MulitWheel,
+        registry: &RunnerRegistry,
+    ) -> Result<(), TaskError> {
+        for record in self.tasks.iter() {
+            let runner = match &record.runner_name {
+                Some(name) => registry.resolve_name(name),
+                None => registry.resolve(record.task_id),
+            }
+            .ok_or(TaskError::RunnerNotRegistered(record.task_id))?;
+
+            let mut builder = TaskBuilder::new(record.task_id)
+                .with_frequency(record.frequency)
+                .with_priority(record.priority);
+            if !record.tags.is_empty() {
+                builder = builder.with_tags(record.tags.clone());
+            }
+            if let Some(name) = &record.name {
+                builder = builder.with_name(name.clone());
+            }
+            if let Some(name) = &record.runner_name {
+                builder = builder.with_runner_name(name.clone());
+            }
+            if let Some(key) = &record.dedup_key {
+                builder = builder.with_dedup_key(key.clone());
+            }
+            for &task_id in &record.depends_on {
+                builder = builder.with_depends_on(task_id);
+            }
+            if let Some(lateness) = record.max_lateness {
+                builder = builder.with_max_lateness(lateness);
+            }
+            if let Some(timestamp) = record.auto_cancel_at {
+                builder = builder.with_auto_cancel_at(timestamp);
+            }
+            let mut task = builder
+                .with_missed_policy(record.missed_policy)
+                .build(runner)?;
+
+            // Continue the persisted sequence rather than restarting it from now
+            task.frequency = FrequencyState::resume(record.frequency, record.alarm_timestamp);
+            wheel.add_task(task)?;
+        }
+        Ok(())
+    }
+}
+
+/// The bincode form of a `TaskRecord`. bincode isn't self-describing, so every field is
+/// written and the frequency is externally tagged.
+#[cfg(feature = "bincode")]
+#[derive(Serialize, Deserialize)]
+struct BinaryRecord {
+    task_id: TaskId,
+    name: Option<String>,
+    tags: HashMap<String, String>,
+    priority: u8,
+    frequency: BinaryFrequency,
+    alarm_timestamp: u64,
+    runner_name: Option<String>,
+    dedup_key: Option<String>,
+    depends_on: Vec<TaskId>,
+    max_lateness: Option<Duration>,
+    missed_policy: MissedPolicy,
+    auto_cancel_at: Option<u64>,
+}
+
+#[cfg(feature = "bincode")]
+#[derive(Serialize, Deserialize)]
+enum BinaryFrequency {
+    Once(u64),
+    Repeated(u64),
+    CountDown(u64, u64),
+    #[cfg(feature = "chrono")]
+    Weekly(chrono::Weekday, u32, u32),
+    #[cfg(feature = "chrono")]
+    Monthly(u32, u32, u32),
+}
+
+#[cfg(feature = "bincode")]
+impl From<TaskRecord> for BinaryRecord {
+    fn from(record: TaskRecord) -> Self {
+        let frequency = match record.frequency {
+            FrequencySeconds::Once(seconds) => BinaryFrequency::Once(seconds),
+            FrequencySeconds::Repeated(seconds) => BinaryFrequency::Repeated(seconds),
+            FrequencySeconds::CountDown(count_down, seconds) => {
+                BinaryFrequency::CountDown(count_down, seconds)
+            }
+            #[cfg(feature = "chrono")]
+            FrequencySeconds::Weekly {
+                weekday,
+                hour,
+                minute,
+            } => BinaryFrequency::Weekly(weekday, hour, minute),
+            #[cfg(feature = "chrono")]
+            FrequencySeconds::Monthly { day, hour, minute } => {
+                BinaryFrequency::Monthly(day, hour, minute)
+            }
+        };
+        Self {
+            task_id: record.task_id,
+            name: record.name,
+            tags: record.tags,
+            priority: record.priority,
+            frequency,
+            alarm_timestamp: record.alarm_timestamp,
+            runner_name: record.runner_name,
+            dedup_key: record.dedup_key,
+            depends_on: record.depends_on,
+            max_lateness: record.max_lateness,
+            missed_policy: record.missed_policy,
+            auto_cancel_at: record.auto_cancel_at,
+        }
+    }
+}
+
+#[cfg(feature = "bincode")]
+impl From<BinaryRecord> for TaskRecord {
+    fn from(record: BinaryRecord) -> Self {
+        let frequency = match record.frequency {
+            BinaryFrequency::Once(seconds) => FrequencySeconds::Once(seconds),
+            BinaryFrequency::Repeated(seconds) => FrequencySeconds::Repeated(seconds),
+            BinaryFrequency::CountDown(count_down, seconds) => {
+                FrequencySeconds::CountDown(count_down, seconds)
+            }
+            #[cfg(feature = "chrono")]
+            BinaryFrequency::Weekly(weekday, hour, minute) => FrequencySeconds::Weekly {
+                weekday,
+                hour,
+                minute,
+            },
+            #[cfg(feature = "chrono")]
+            BinaryFrequency::Monthly(day, hour, minute) => {
+                FrequencySeconds::Monthly { day, hour, minute }
+            }
+        };
+        Self {
+            task_id: record.task_id,
+            name: record.name,
+            tags: record.tags,
+            priority: record.priority,
+            frequency,
+            alarm_timestamp: record.alarm_timestamp,
+            runner_name: record.runner_name,
+            dedup_key: record.dedup_key,
+            depends_on: record.depends_on,
+            max_lateness: record.max_lateness,
+            missed_policy: record.missed_policy,
+            auto_cancel_at: record.auto_cancel_at,
+        }
+    }
+}
+
+/// A task declared in a configuration document, see `Timer::load_tasks_from_json`.
+///
+/// The runner is built by the factory registered under `runner_name`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskDefinition {
+    pub task_id: TaskId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub frequency: FrequencySeconds,
+    pub runner_name: String,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tags: HashMap<String, String>,
+}
+
+impl TaskDefinition {
+    fn build(self, registry: &RunnerRegistry) -> Result<Task, TaskError> {
+        let runner = registry
+            .resolve_name(&self.runner_name)
+            .ok_or(TaskError::RunnerNotRegistered(self.task_id))?;
+        let mut builder = TaskBuilder::new(self.task_id)
+            .with_frequency(self.frequency)
+            .with_runner_name(self.runner_name)
+            .with_tags(self.tags);
+        if let Some(name) = self.name {
+            builder = builder.with_name(name);
+        }
+        builder.build(runner)
+    }
+}
+
+/// A configuration document of the form `{ "tasks": [TaskDefinition, ...] }`. The entries are
+/// kept as JSON so that a malformed entry fails on its own.
+#[derive(Deserialize)]
+struct TaskDocument {
+    tasks: Vec<serde_json::Value>,
+}
+
+/// Schedule the tasks of a configuration document, returning the result of each entry in
+/// document order. Fails as a whole only if the document itself can't be read.
+pub(crate) fn load_task_definitions(
+    json: &str,
+    wheel: &MulitWheel,
+    registry: &RunnerRegistry,
+) -> Result<Vec<Result<TaskId, TaskError>>, TaskError> {
+    let document: TaskDocument =
+        serde_json::from_str(json).map_err(|e| TaskError::Persistence(e.to_string()))?;
+    Ok(document
+        .tasks
+        .into_iter()
+        .map(|entry| {
+            let definition: TaskDefinition =
+                serde_json::from_value(entry).map_err(|e| TaskError::Persistence(e.to_string()))?;
+            let task_id = definition.task_id;
+            wheel.add_task(definition.build(registry)?)?;
+            Ok(task_id)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    };
+
+    use super::*;
+    use crate::{task::TaskRunner, timer::OverduePolicy};
+
+    struct CountingRunner {
+        count: Arc<AtomicU64>,
+    }
+
+    #[async_trait::async_trait]
+    impl TaskRunner for CountingRunner {
+        type Output = ();
+
+        async fn run(&self) -> Result<Self::Output, Box<dyn std::error::Error + Send + Sync>> {
+            self.count.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    fn registry_with(name: &str, count: &Arc<AtomicU64>, registry: &mut RunnerRegistry) {
+        let count = count.clone();
+        registry.register(name, move || {
+            Arc::new(CountingRunner {
+                count: count.clone(),
+            })
+        });
+    }
+
+    #[tokio::test]
+    async fn test_restore_resolves_runners_by_name() {
+        let wheel = MulitWheel::new();
+        let unused = Arc::new(AtomicU64::new(0));
+        for (task_id, name) in [(1, "cleanup"), (2, "report"), (3, "report")] {
+            let task = TaskBuilder::new(task_id)
+                .with_frequency_repeated_by_seconds(5)
+                .with_runner_name(name)
+                .spwan_async(CountingRunner {
+                    count: unused.clone(),
+                })
+                .unwrap();
+            wheel.add_task(task).unwrap();
+        }
+        let json = serde_json::to_string(&ScheduleSnapshot::capture(&wheel).unwrap()).unwrap();
+
+        let cleanup = Arc::new(AtomicU64::new(0));
+        let report = Arc::new(AtomicU64::new(0));
+        let mut registry = RunnerRegistry::new();
+        registry_with("cleanup", &cleanup, &mut registry);
+        registry_with("report", &report, &mut registry);
+
+        let restored = MulitWheel::new();
+        let snapshot: ScheduleSnapshot = serde_json::from_str(&json).unwrap();
+        snapshot.restore(&restored, &registry).unwrap();
+
+        for task_id in 1..=3 {
+            let task = restored.remove_task(task_id.into()).unwrap();
+            task.runner.run().await.unwrap();
+        }
+        assert_eq!(cleanup.load(Ordering::SeqCst), 1);
+        assert_eq!(report.load(Ordering::SeqCst), 2);
+        assert_eq!(unused.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_restore_unknown_runner_name() {
+        let snapshot = ScheduleSnapshot {
+            tasks: vec![TaskRecord {
+                task_id: TaskId::new(4),
+                name: None,
+                tags: HashMap::new(),
+                priority: 0,
+                frequency: FrequencySeconds::Repeated(5),
+                alarm_timestamp: 0,
+                runner_name: Some("missing".to_string()),
+                dedup_key: None,
+                depends_on: vec![],
+                max_lateness: None,
+                missed_policy: MissedPolicy::Skip,
+                auto_cancel_at: None,
+            }],
+        };
+
+        let result = snapshot.restore(&MulitWheel::new(), &RunnerRegistry::new());
+        assert!(
+            matches!(result, Err(TaskError::RunnerNotRegistered(task_id)) if task_id == TaskId::new(4))
+        );
+    }
+
+    #[test]
+    fn test_restore_keeps_repeated_phase() {
+        let alarm_timestamp = crate::utils::timestamp() + 3;
+        let snapshot = ScheduleSnapshot {
+            tasks: vec![TaskRecord {
+                task_id: TaskId::new(5),
+                name: None,
+                tags: HashMap::new(),
+                priority: 0,
+                frequency: FrequencySeconds::Repeated(10),
+                alarm_timestamp,
+                runner_name: None,
+                dedup_key: None,
+                depends_on: vec![],
+                max_lateness: None,
+                missed_policy: MissedPolicy::Skip,
+                auto_cancel_at: None,
+            }],
+        };
+        let count = Arc::new(AtomicU64::new(0));
+        let mut registry = RunnerRegistry::new();
+        registry.register_task(TaskId::new(5), move || {
+            Arc::new(CountingRunner {
+                count: count.clone(),
+            })
+        });
+
+        let wheel = MulitWheel::new();
+        snapshot.restore(&wheel, &registry).unwrap();
+
+        let mut task = wheel.remove_task(TaskId::new(5)).unwrap();
+        assert_eq!(task.alarm_timestamp, alarm_timestamp);
+        assert_eq!(task.next_alarm_timestamp(), Some(alarm_timestamp));
+        assert_eq!(task.next_alarm_timestamp(), Some(alarm_timestamp + 10));
+    }
+
+    #[test]
+    fn test_reschedule_overdue_tasks() {
+        fn overdue_snapshot(now: u64) -> ScheduleSnapshot {
+            let record = |task_id: u64, frequency, alarm_timestamp| TaskRecord {
+                task_id: TaskId::new(task_id),
+                name: None,
+                tags: HashMap::new(),
+                priority: 0,
+                frequency,
+                alarm_timestamp,
+                runner_name: Some("noop".to_string()),
+                dedup_key: None,
+                depends_on: vec![],
+                max_lateness: None,
+                missed_policy: MissedPolicy::Skip,
+                auto_cancel_at: None,
+            };
+            ScheduleSnapshot {
+                tasks: vec![
+                    record(1, FrequencySeconds::Repeated(10), now - 25),
+                    record(2, FrequencySeconds::Once(5), now - 60),
+                    record(3, FrequencySeconds::CountDown(3, 10), now - 15),
+                ],
+            }
+        }
+        let mut registry = RunnerRegistry::new();
+        registry_with("noop", &Arc::new(AtomicU64::new(0)), &mut registry);
+
+        let wheel = MulitWheel::new();
+        let now = wheel.current_timestamp();
+        overdue_snapshot(now).restore(&wheel, &registry).unwrap();
+        let missed = |task_id: u64| {
+            wheel
+                .get_task_tracking_info(TaskId::new(task_id))
+                .unwrap()
+                .missed_alarm
+        };
+        assert_eq!(missed(1), Some(now - 25));
+        assert_eq!(missed(3), Some(now - 15));
+
+        assert_eq!(wheel.reschedule_overdue(OverduePolicy::FireOnce), 3);
+        assert_eq!(wheel.reschedule_overdue(OverduePolicy::FireOnce), 0);
+        let mut tasks = vec![];
+        wheel.for_each_task(|task| tasks.push(task.clone()));
+        tasks.sort_by_key(|task| task.task_id);
+        for mut task in tasks {
+            assert_eq!(task.alarm_timestamp, now + 1);
+            assert_eq!(task.missed_alarm, None);
+            // The missed alarms are fired once, the sequence continues after now
+            let next = (task.task_id != TaskId::new(2)).then_some(now + 5);
+            assert_eq!(task.next_alarm_timestamp(), next);
+        }
+
+        let wheel = MulitWheel::new();
+        let now = wheel.current_timestamp();
+        overdue_snapshot(now).restore(&wheel, &registry).unwrap();
+        assert_eq!(wheel.reschedule_overdue(OverduePolicy::SkipToNext), 3);
+        // The once task has no alarm after now left
+        assert!(!wheel.contains(TaskId::new(2)));
+        let task = wheel.remove_task(TaskId::new(1)).unwrap();
+        assert_eq!(task.alarm_timestamp, now + 5);
+        let mut task = wheel.remove_task(TaskId::new(3)).unwrap();
+        assert_eq!(task.next_alarm_timestamp(), Some(now + 5));
+        assert_eq!(task.next_alarm_timestamp(), None);
+    }
+
+    #[cfg(feature = "bincode")]
+    fn large_wheel(unused: &Arc<AtomicU64>) -> MulitWheel {
+        let wheel = MulitWheel::new();
+        for task_id in 1..=2000u64 {
+            let mut builder = TaskBuilder::new(task_id)
+                .with_frequency_repeated_by_seconds(task_id % 7200 + 1)
+                .with_priority((task_id % 4) as u8)
+                .with_runner_name(if task_id % 2 == 0 { "even" } else { "odd" });
+            if task_id % 3 == 0 {
+                builder = builder
+                    .with_name(format!("task-{task_id}"))
+                    .with_tags(HashMap::from([(
+                        "tenant".to_string(),
+                        (task_id % 10).to_string(),
+                    )]))
+                    .with_depends_on(task_id - 1);
+            }
+            let task = builder
+                .spwan_async(CountingRunner {
+                    count: unused.clone(),
+                })
+                .unwrap();
+            wheel.add_task(task).unwrap();
+        }
+        wheel
+    }
+
+    #[cfg(feature = "bincode")]
+    #[test]
+    fn test_bincode_round_trip() {
+        let unused = Arc::new(AtomicU64::new(0));
+        let wheel = large_wheel(&unused);
+        let snapshot = ScheduleSnapshot::capture(&wheel).unwrap();
+        let locations = (1..=2000u64)
+            .map(|task_id| wheel.task_location(TaskId::new(task_id)))
+            .collect::<Vec<_>>();
+        let bytes = wheel.snapshot_bincode().unwrap();
+
+        // Restore into the same wheel, so the hands stand where they stood when encoding
+        for task_id in 1..=2000u64 {
+            wheel.remove_task(TaskId::new(task_id));
+        }
+        let mut registry = RunnerRegistry::new();
+        registry_with("even", &unused, &mut registry);
+        registry_with("odd", &unused, &mut registry);
+        wheel.restore_bincode(&bytes, &registry).unwrap();
+
+        assert_eq!(ScheduleSnapshot::capture(&wheel).unwrap(), snapshot);
+        for (task_id, location) in (1..=2000u64).zip(locations) {
+            assert_eq!(wheel.task_location(TaskId::new(task_id)), location);
+        }
+
+        let result = MulitWheel::new().restore_bincode(&bytes[..bytes.len() / 2], &registry);
+        assert!(matches!(result, Err(TaskError::Persistence(_))));
+    }
+
+    #[cfg(feature = "bincode")]
+    #[test]
+    fn test_bincode_is_smaller_than_json() {
+        let wheel = large_wheel(&Arc::new(AtomicU64::new(0)));
+        let json = serde_json::to_vec(&ScheduleSnapshot::capture(&wheel).unwrap()).unwrap();
+        let bytes = wheel.snapshot_bincode().unwrap();
+        assert!(
+            bytes.len() < json.len(),
+            "{} >= {}",
+            bytes.len(),
+            json.len()
+        );
+    }
+}
@@ -1,7 +1,13 @@
-use std::{collections::HashMap, mem::swap};
+use std::{cmp::Reverse, collections::HashMap};
 
-use crate::task::{TaskId, task::Task};
+use crate::{
+    task::{TaskId, task::Task},
+    timer::TieBreak,
+};
 
+/// The tasks waiting for a second of a wheel. They are kept unordered and sorted as they
+/// arrive, so tasks added, rescheduled or cascaded into the slot in any order are dispatched by
+/// priority and tie break.
 pub(crate) struct Slot {
     pub task_map: HashMap<TaskId, Task>,
 }
@@ -17,35 +23,32 @@ impl Slot {
         self.task_map.insert(task.task_id, task)
     }
 
-    pub(crate) fn update_task(&mut self, mut task: Task) -> Option<Task> {
-        match self.task_map.get_mut(&task.task_id) {
-            Some(t) => {
-                swap(t, &mut task);
-                Some(task)
-            }
-
-            None => self.task_map.insert(task.task_id, task),
-        }
-    }
-
     pub(crate) fn remove_task(&mut self, task_id: TaskId) -> Option<Task> {
         self.task_map.remove(&task_id)
     }
 
     // Check and reduce cylinder_line锛?    // Returns a Vec. containing all task ids to be executed.(cylinder_line == 0)
-    pub(crate) fn arrival_time_tasks(&mut self) -> Vec<TaskId> {
-        let mut task_id_vec = vec![];
+    // The ids are ordered by descending priority, then by the tie break.
+    pub(crate) fn arrival_time_tasks(&self, tie_break: TieBreak) -> Vec<TaskId> {
+        let mut arrived = vec![];
 
-        for (_, task) in self.task_map.iter_mut() {
+        for task in self.task_map.values() {
             if task.is_arrived() {
-                task_id_vec.push(task.task_id);
+                arrived.push((Reverse(task.priority), tie_break.key(task), task.task_id));
             }
         }
 
-        task_id_vec
+        arrived.sort_unstable();
+        arrived.into_iter().map(|(_, _, task_id)| task_id).collect()
     }
 
-    pub(crate) fn shrink(&mut self) {
-        self.task_map.shrink_to(128);
+    /// Give back the capacity left over from a burst of tasks, once the slot holds less than a
+    /// quarter of it. At least `capacity_floor` is retained so steady traffic doesn't reallocate.
+    pub(crate) fn shrink(&mut self, capacity_floor: usize) {
+        let capacity = self.task_map.capacity();
+        if capacity > capacity_floor && self.task_map.len() * 4 < capacity {
+            self.task_map
+                .shrink_to(capacity_floor.max(self.task_map.len() * 2));
+        }
     }
 }
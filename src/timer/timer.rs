@@ -1,8 +1,4137 @@
-use async_channel::Sender;
+#[cfg(feature = "serde")]
+use std::path::Path;
+use std::{
+    any::Any,
+    collections::{HashMap, VecDeque},
+    fmt,
+    panic::AssertUnwindSafe,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    },
+    task::Poll,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use crate::timer::{Clock, TimerEvent};
+use dashmap::{DashMap, mapref::entry::Entry};
+
+#[cfg(feature = "stream")]
+use futures::Stream;
+use tokio::{
+    runtime::Handle,
+    sync::{Notify, mpsc, oneshot},
+    task::{AbortHandle, JoinHandle},
+    time::Instant,
+};
+#[cfg(feature = "tokio-util")]
+use tokio_util::sync::CancellationToken;
+
+#[cfg(feature = "tokio-util")]
+use crate::task::runner::RUN_CANCELLATION;
+use crate::{
+    error::{InvalidFrequencyReason, TaskError},
+    task::{
+        IntoTask, RecordId, SchedulePeek, Task, TaskBuilder, TaskContext, TaskId, TaskRunner,
+        frequency::FrequencySeconds,
+    },
+    timer::{
+        AuditError, Clock, EventReceiver, EventSender, OverduePolicy, TaskGroup, TaskHandle,
+        TaskLocation, TaskTrackingInfo, TieBreak, TimerEvent, TimerHandle, WheelPositions,
+        WheelType,
+        event::{EventDelivery, EventPublisher},
+        handle::TimerCommand,
+        wheel::{MulitWheel, MulitWheelBuilder, RESYNC_THRESHOLD},
+    },
+    utils::{rand::RandSource, timestamp},
+};
+#[cfg(feature = "serde")]
+use crate::{
+    task::RunnerRegistry,
+    timer::schedule::{self, ScheduleSnapshot, TaskRecord},
+};
+
+const DEFAULT_EVENT_CHANNEL_CAPACITY: usize = 1024;
+/// A tick callback running longer than this is reported, it holds up the dispatch.
+const SLOW_TICK_CALLBACK: Duration = Duration::from_millis(50);
+
+/// A callback invoked with the tick count on every tick, see `Timer::on_tick`.
+pub type TickCallback = Arc<dyn Fn(u64) + Send + Sync>;
 
 pub struct Timer {
-    clock: Clock,
-    event_sender: Sender<TimerEvent>,
+    wheel: Arc<MulitWheel>,
+    // The wall clock in seconds, replaced in tests
+    now: fn() -> u64,
+    // Shifts the wall clock, for simulating other times of day
+    time_offset: i64,
+    events: Arc<EventPublisher>,
+    shutdown: Notify,
+    // Wakes the run loop idling on an empty wheel
+    task_added: Notify,
+    // Set while the run loop idles, the wheels stop moving meanwhile
+    idle: AtomicBool,
+    // Set by `shutdown_drain` for the loop to fire the overdue tasks before it stops
+    drain_on_shutdown: AtomicBool,
+    commands: mpsc::UnboundedSender<TimerCommand>,
+    // Taken by the run loop while it is running
+    command_receiver: Mutex<Option<mpsc::UnboundedReceiver<TimerCommand>>>,
+    fair_dispatch: Option<FairDispatch>,
+    // The most tasks dispatched at once, see `TimerBuilder::with_dispatch_batch_size`
+    dispatch_batch_size: Option<usize>,
+    // Limits the runners running at once
+    run_slots: Option<Arc<RunSlots>>,
+    // Limits the runs of each task with a maximum in flight, with the limit it was made for
+    task_slots: DashMap<TaskId, Arc<RunSlots>>,
+    // The failed runs in a row of each task with a maximum of failures
+    failures: Arc<DashMap<TaskId, u32>>,
+    // The due tasks kept for `drain_due` when the timer doesn't run them itself
+    due: Option<Mutex<Vec<Task>>>,
+    in_flight: Arc<InFlightRuns>,
+    // When the run loop first started, for the uptime
+    started_at: Mutex<Option<Instant>>,
+    total_fired: AtomicU64,
+    total_failed: Arc<AtomicU64>,
+    // The heartbeat of the run loop, see `is_healthy`
+    created_at: Instant,
+    last_tick: AtomicU64,
+    panic_strategy: PanicStrategy,
+    raised_panic: Arc<RaisedPanic>,
+    // The tasks whose schedule finished, if retained
+    completed: Option<Arc<CompletedTasks>>,
+    // The frequency of the builders of `task_builder`
+    default_frequency: Option<FrequencySeconds>,
+    // The exact instants of the tasks of `at_instant` under precise first fire
+    fire_at: Option<DashMap<TaskId, Instant>>,
+    on_tick: Mutex<Option<TickCallback>>,
+}
+
+/// `last_tick` before the run loop first ticked.
+const NEVER_TICKED: u64 = u64::MAX;
+/// `last_tick` while the run loop is parked on an empty wheel, it doesn't tick meanwhile.
+const PARKED: u64 = u64::MAX - 1;
+
+/// Makes staleness count from the stop for a run loop that stopped while parked.
+struct Unpark<'a>(&'a Timer);
+
+impl Drop for Unpark<'_> {
+    fn drop(&mut self) {
+        let timer = self.0;
+        let _ = timer.last_tick.compare_exchange(
+            PARKED,
+            timer.created_at.elapsed().as_millis() as u64,
+            Ordering::SeqCst,
+            Ordering::SeqCst,
+        );
+    }
+}
+
+/// What a `Timer` does when a runner panics, see `TimerBuilder::with_panic_strategy`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum PanicStrategy {
+    /// Fail fast: the panic propagates out of the run loop, so `run` panics with it and the
+    /// timer stops. With `panic = "abort"` the process ends right away.
+    Abort,
+    /// Report the panic as a `TimerEvent::TaskFailed` and keep going, the schedule of the task
+    /// continues.
+    #[default]
+    Isolate,
+}
+
+/// The first runner panic under `PanicStrategy::Abort`, for the run loop to propagate.
+#[derive(Default)]
+struct RaisedPanic {
+    payload: Mutex<Option<Box<dyn Any + Send>>>,
+    raised: Notify,
+}
+
+impl RaisedPanic {
+    fn raise(&self, payload: Box<dyn Any + Send>) {
+        self.payload.lock().unwrap().get_or_insert(payload);
+        self.raised.notify_one();
+    }
+
+    /// Continue unwinding with the raised panic, if there is one.
+    fn propagate(&self) {
+        if let Some(payload) = self.payload.lock().unwrap().take() {
+            std::panic::resume_unwind(payload);
+        }
+    }
+}
+
+/// The message of a panic payload, as the default panic hook prints it.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message,
+        None => payload
+            .downcast_ref::<String>()
+            .map_or("Box<dyn Any>", String::as_str),
+    }
+}
+
+/// A summary of the activity of a `Timer`, see `Timer::stats`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerStats {
+    /// The time since the run loop first started, zero if it never ran.
+    pub uptime: Duration,
+    /// The runs dispatched, whatever their outcome.
+    pub total_fired: u64,
+    /// The runs whose runner returned an error.
+    pub total_failed: u64,
+    /// The number of pending tasks, as `Timer::len`.
+    pub pending: usize,
+    /// The times the minute wheel cascaded into the second wheel, one per minute ticked.
+    pub minute_cascades: u64,
+    /// The times the hour wheel cascaded into the minute wheel, one per hour ticked.
+    pub hour_cascades: u64,
+}
+
+/// A task whose schedule finished, retained by `TimerBuilder::with_completed_retention`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletedTask {
+    pub task_id: TaskId,
+    pub name: Option<String>,
+    /// The record id of the last run, for runners numbering their runs.
+    pub last_record_id: Option<RecordId>,
+    pub outcome: TaskOutcome,
+}
+
+/// How the last run of a `CompletedTask` went.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskOutcome {
+    Completed,
+    /// The runner returned an error or panicked, with its message.
+    Failed(String),
+}
+
+/// The most recently completed tasks, dropping the oldest beyond the capacity.
+struct CompletedTasks {
+    capacity: usize,
+    tasks: Mutex<VecDeque<CompletedTask>>,
+}
+
+impl CompletedTasks {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tasks: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    fn push(&self, task: CompletedTask) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut tasks = self.tasks.lock().unwrap();
+        if tasks.len() == self.capacity {
+            tasks.pop_front();
+        }
+        tasks.push_back(task);
+    }
+}
+
+/// Limits the runs at once. Slots are handed out in the order they were requested, which
+/// is the dispatch order, not the order the waiting runs happen to be polled in.
+struct RunSlots {
+    limit: usize,
+    state: Mutex<RunSlotsState>,
+}
+
+#[derive(Default)]
+struct RunSlotsState {
+    taken: usize,
+    waiting: VecDeque<oneshot::Sender<()>>,
+}
+
+/// A slot of `RunSlots`, given back when dropped.
+struct RunSlot(Arc<RunSlots>);
+
+/// A request for a slot, queued as it is made.
+enum SlotRequest {
+    Granted(RunSlot),
+    Queued(SlotWaiter),
+}
+
+/// A queued request. A slot granted to it that is never taken up, e.g. when the run is
+/// aborted meanwhile, is passed on when it is dropped.
+struct SlotWaiter {
+    granted: oneshot::Receiver<()>,
+    slots: Arc<RunSlots>,
+}
+
+impl RunSlots {
+    fn new(limit: usize) -> Arc<Self> {
+        Arc::new(Self {
+            limit,
+            state: Mutex::new(RunSlotsState::default()),
+        })
+    }
+
+    fn request(self: &Arc<Self>) -> SlotRequest {
+        let mut state = self.state.lock().unwrap();
+        if state.taken < self.limit {
+            state.taken += 1;
+            return SlotRequest::Granted(RunSlot(self.clone()));
+        }
+        let (grant, granted) = oneshot::channel();
+        state.waiting.push_back(grant);
+        SlotRequest::Queued(SlotWaiter {
+            granted,
+            slots: self.clone(),
+        })
+    }
+
+    fn release(&self) {
+        let mut state = self.state.lock().unwrap();
+        // Pass the slot on to the first request still waiting
+        while let Some(grant) = state.waiting.pop_front() {
+            if grant.send(()).is_ok() {
+                return;
+            }
+        }
+        state.taken -= 1;
+    }
+}
+
+impl Drop for RunSlot {
+    fn drop(&mut self) {
+        self.0.release();
+    }
+}
+
+impl SlotRequest {
+    async fn wait(self) -> RunSlot {
+        match self {
+            SlotRequest::Granted(slot) => slot,
+            SlotRequest::Queued(mut waiter) => {
+                // The grant can't be dropped unsent, the waiter keeps the slots alive
+                let _ = (&mut waiter.granted).await;
+                RunSlot(waiter.slots.clone())
+            }
+        }
+    }
+}
+
+impl Drop for SlotWaiter {
+    fn drop(&mut self) {
+        // Closed first, so no slot is granted between the check and the drop
+        self.granted.close();
+        if self.granted.try_recv().is_ok() {
+            self.slots.release();
+        }
+    }
+}
+
+/// The runs of the tasks in flight, for aborting them and telling when the timer is idle.
+pub(crate) struct InFlightRuns {
+    next_run_id: AtomicU64,
+    runs: DashMap<TaskId, Vec<RunHandle>>,
+    count: AtomicUsize,
+    wheel: Arc<MulitWheel>,
+    events: Arc<EventPublisher>,
+    // Wakes `run_until_idle`
+    became_idle: Notify,
+    // The arrived tasks left for later batches under dispatch batching, they count as in flight
+    deferred: Mutex<VecDeque<Task>>,
+    /// The runtime the runs are spawned onto, the current one if `None`.
+    runtime: Option<Handle>,
+}
+
+impl InFlightRuns {
+    fn new(wheel: Arc<MulitWheel>, events: Arc<EventPublisher>, runtime: Option<Handle>) -> Self {
+        Self {
+            next_run_id: AtomicU64::new(0),
+            runs: DashMap::new(),
+            count: AtomicUsize::new(0),
+            wheel,
+            events,
+            became_idle: Notify::new(),
+            deferred: Mutex::new(VecDeque::new()),
+            runtime,
+        }
+    }
+
+    /// Spawn a run of a task, which stays registered until it finishes or is aborted. The run
+    /// starts once the returned sender is used, dropping it cancels the run.
+    fn spawn(
+        self: &Arc<Self>,
+        task_id: TaskId,
+        run: impl Future<Output = ()> + Send + 'static,
+    ) -> (JoinHandle<()>, oneshot::Sender<()>) {
+        let run_id = self.next_run_id.fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::SeqCst);
+        let deregister = Deregister {
+            in_flight: self.clone(),
+            task_id,
+            run_id,
+        };
+        // The run waits until it is registered, so that it can't deregister before
+        let (start, wait) = oneshot::channel();
+        #[cfg(feature = "tokio-util")]
+        let cancellation = CancellationToken::new();
+        #[cfg(feature = "tokio-util")]
+        let run = RUN_CANCELLATION.scope(cancellation.clone(), run);
+        let run = async move {
+            let _deregister = deregister;
+            if wait.await.is_ok() {
+                run.await
+            }
+        };
+        let handle = match &self.runtime {
+            Some(runtime) => runtime.spawn(run),
+            None => tokio::spawn(run),
+        };
+        self.runs.entry(task_id).or_default().push(RunHandle {
+            run_id,
+            abort: handle.abort_handle(),
+            #[cfg(feature = "tokio-util")]
+            cancellation,
+        });
+        (handle, start)
+    }
+
+    fn is_idle(&self) -> bool {
+        self.count.load(Ordering::SeqCst) == 0 && self.wheel.is_empty() && !self.has_deferred()
+    }
+
+    fn has_deferred(&self) -> bool {
+        !self.deferred.lock().unwrap().is_empty()
+    }
+
+    /// Publish `TimerEvent::Idle` if no task is scheduled or running.
+    fn publish_if_idle(&self) {
+        if self.is_idle() {
+            self.events.publish(TimerEvent::Idle);
+            self.became_idle.notify_waiters();
+        }
+    }
+
+    /// Abort the runs of a task in flight, returning how many there were.
+    fn abort(&self, task_id: TaskId) -> usize {
+        let Some((_, runs)) = self.runs.remove(&task_id) else {
+            return 0;
+        };
+        for run in runs.iter() {
+            #[cfg(feature = "tokio-util")]
+            run.cancellation.cancel();
+            run.abort.abort();
+        }
+        runs.len()
+    }
+
+    /// Remove a scheduled task along with its runs in flight, returning it if it was present.
+    pub(crate) fn remove_task(&self, task_id: TaskId) -> Option<Task> {
+        self.abort(task_id);
+        // A deferred task is back in the wheel only once dispatched
+        let deferred = self.take_deferred(task_id);
+        let task = self.wheel.remove_task(task_id).or(deferred);
+        if task.is_some() {
+            self.publish_if_idle();
+        }
+        task
+    }
+
+    /// Like `remove_task`, returning only the tracking information of a task in the wheels.
+    pub(crate) fn remove_task_info(&self, task_id: TaskId) -> Option<TaskTrackingInfo> {
+        self.abort(task_id);
+        let deferred = self.take_deferred(task_id);
+        let info = self.wheel.remove_task_info(task_id);
+        if info.is_some() || deferred.is_some() {
+            self.publish_if_idle();
+        }
+        info
+    }
+
+    fn take_deferred(&self, task_id: TaskId) -> Option<Task> {
+        let mut deferred = self.deferred.lock().unwrap();
+        let index = deferred.iter().position(|task| task.task_id == task_id)?;
+        deferred.remove(index)
+    }
+}
+
+/// A run in flight, see `InFlightRuns::abort`.
+struct RunHandle {
+    run_id: u64,
+    abort: AbortHandle,
+    /// Handed to the runner as `TaskContext::cancellation`.
+    #[cfg(feature = "tokio-util")]
+    cancellation: CancellationToken,
+}
+
+/// Deregisters a run when it finishes or its future is dropped on abort.
+struct Deregister {
+    in_flight: Arc<InFlightRuns>,
+    task_id: TaskId,
+    run_id: u64,
+}
+
+impl Drop for Deregister {
+    fn drop(&mut self) {
+        if let Entry::Occupied(mut runs) = self.in_flight.runs.entry(self.task_id) {
+            runs.get_mut().retain(|run| run.run_id != self.run_id);
+            if runs.get().is_empty() {
+                runs.remove();
+            }
+        }
+        if self.in_flight.count.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.in_flight.publish_if_idle();
+        }
+    }
+}
+
+/// Interleaves the tasks arriving together across the groups formed by the values of a tag.
+#[derive(Clone)]
+struct FairDispatch {
+    tag_key: String,
+    weights: HashMap<String, usize>,
+}
+
+impl FairDispatch {
+    /// Order the tasks round-robin across their groups, taking up to the weight of a group from
+    /// it per round, 1 by default. Groups take turns in the order they first appear, tasks keep
+    /// their order within a group, and untagged tasks form a group of their own.
+    fn order(&self, tasks: Vec<Task>) -> Vec<Task> {
+        let mut groups: Vec<(Option<String>, VecDeque<Task>)> = vec![];
+        for task in tasks {
+            let group = task.tags.get(&self.tag_key).cloned();
+            match groups.iter_mut().find(|(g, _)| *g == group) {
+                Some((_, queue)) => queue.push_back(task),
+                None => groups.push((group, VecDeque::from([task]))),
+            }
+        }
+
+        let mut ordered = Vec::with_capacity(groups.iter().map(|(_, queue)| queue.len()).sum());
+        while !groups.is_empty() {
+            for (group, queue) in groups.iter_mut() {
+                let weight = group
+                    .as_ref()
+                    .and_then(|group| self.weights.get(group))
+                    .copied()
+                    .unwrap_or(1)
+                    .max(1);
+                ordered.extend(queue.drain(..weight.min(queue.len())));
+            }
+            groups.retain(|(_, queue)| !queue.is_empty());
+        }
+        ordered
+    }
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        TimerBuilder::default().build()
+    }
+}
+
+impl fmt::Debug for Timer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The run loop holds the command receiver while it is running
+        let state = match self.command_receiver.try_lock() {
+            Ok(receiver) if receiver.is_none() => "running",
+            Ok(_) => "idle",
+            Err(_) => "busy",
+        };
+        f.debug_struct("Timer")
+            .field("state", &state)
+            .field("wheel_positions", &self.wheel.get_wheel_positions())
+            .field("current_timestamp", &self.wheel.current_timestamp())
+            .field("pending_tasks", &self.wheel.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Schedule a task for its next alarm.
+    pub fn add_task(&self, task: Task) -> Result<(), TaskError> {
+        self.adding(|wheel| wheel.add_task(task))
+    }
+
+    /// A builder for a task of this timer, with the default frequency of the timer if it has
+    /// one, see `TimerBuilder::with_default_frequency`. Any `with_frequency_*` call overrides it.
+    pub fn task_builder(&self, task_id: impl Into<TaskId>) -> TaskBuilder {
+        let builder = TaskBuilder::new(task_id);
+        match self.default_frequency {
+            Some(frequency) => builder.with_frequency(frequency),
+            None => builder,
+        }
+    }
+
+    /// Schedule anything convertible into a task, returning its id.
+    ///
+    /// ```
+    /// # use minitimer::prelude::*;
+    /// # use std::time::Duration;
+    /// let timer = Timer::new();
+    /// let task_id = timer.add((Duration::from_secs(5), |_ctx| async {})).unwrap();
+    /// assert!(timer.contains(task_id));
+    /// ```
+    pub fn add(&self, task: impl IntoTask) -> Result<TaskId, TaskError> {
+        let task = task.into_task()?;
+        let task_id = task.task_id;
+        self.add_task(task)?;
+        Ok(task_id)
+    }
+
+    /// Schedule anything convertible into a task, returning a handle to cancel or reschedule
+    /// it later without keeping track of its id.
+    ///
+    /// ```
+    /// # use minitimer::prelude::*;
+    /// # use std::time::Duration;
+    /// let timer = Timer::new();
+    /// let handle = timer.schedule((Duration::from_secs(5), |_ctx| async {})).unwrap();
+    /// assert!(handle.is_scheduled());
+    /// handle.cancel();
+    /// assert!(timer.is_empty());
+    /// ```
+    pub fn schedule(&self, task: impl IntoTask) -> Result<TaskHandle, TaskError> {
+        let task_id = self.add(task)?;
+        Ok(TaskHandle::new(
+            task_id,
+            self.wheel.clone(),
+            self.in_flight.clone(),
+        ))
+    }
+
+    /// Run the closure every `interval`, returning the id assigned to the task.
+    ///
+    /// The interval is truncated to whole seconds and must be at least one second.
+    pub fn every<F, Fut>(&self, interval: Duration, f: F) -> Result<TaskId, TaskError>
+    where
+        F: Fn(TaskContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.add((interval, f))
+    }
+
+    /// Run the closure once at the timestamp in seconds, returning the id assigned to the task.
+    pub fn at<F, Fut>(&self, timestamp: u64, f: F) -> Result<TaskId, TaskError>
+    where
+        F: Fn(TaskContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.add(
+            TaskBuilder::new_auto()
+                .with_frequency_once_by_timestamp_seconds(timestamp)?
+                .spawn_fn(f)?,
+        )
+    }
+
+    /// Run the runner once at `time`, returning the id assigned to the task.
+    ///
+    /// A time within a second is rounded up, so the task never fires early. Times that are not
+    /// in the future are rejected.
+    pub fn at_time<R: TaskRunner<Output = ()>>(
+        &self,
+        time: SystemTime,
+        runner: R,
+    ) -> Result<TaskId, TaskError> {
+        let since_epoch = time.duration_since(UNIX_EPOCH).map_err(|_| {
+            TaskError::invalid_frequency(
+                InvalidFrequencyReason::PastTimestamp,
+                None,
+                format!("{time:?} is before the unix epoch"),
+            )
+        })?;
+        let timestamp = since_epoch.as_secs() + u64::from(since_epoch.subsec_nanos() > 0);
+        self.add(
+            TaskBuilder::new_auto()
+                .with_frequency_once_by_timestamp_seconds(timestamp)?
+                .spwan_async(runner)?,
+        )
+    }
+
+    /// Run the runner once at `instant`, returning the id assigned to the task.
+    ///
+    /// The instant is turned into a delay from now, counted in ticks of the wheels like
+    /// `TaskBuilder::with_frequency_once_by_seconds`, so no epoch seconds are involved. A delay
+    /// within a second is rounded up, so the task never fires early. Instants that are not in
+    /// the future are rejected.
+    ///
+    /// Under `TimerBuilder::with_precise_first_fire` the task arrives on the tick before the
+    /// instant instead, and its run waits for the instant itself.
+    pub fn at_instant<R: TaskRunner<Output = ()>>(
+        &self,
+        instant: Instant,
+        runner: R,
+    ) -> Result<TaskId, TaskError> {
+        let delay = instant.saturating_duration_since(Instant::now());
+        if delay.is_zero() {
+            return Err(TaskError::invalid_frequency(
+                InvalidFrequencyReason::PastTimestamp,
+                None,
+                format!("{instant:?} is not in the future"),
+            ));
+        }
+        let seconds = match self.fire_at {
+            Some(_) => delay.as_secs().max(1),
+            None => delay.as_secs() + u64::from(delay.subsec_nanos() > 0),
+        };
+        let task_id = self.add(
+            TaskBuilder::new_auto()
+                .with_frequency_once_by_seconds(seconds)
+                .spwan_async(runner)?,
+        )?;
+        if let Some(fire_at) = &self.fire_at {
+            fire_at.insert(task_id, instant);
+        }
+        Ok(task_id)
+    }
+
+    /// Schedule a task to first fire `offset_secs` seconds after the next fire of the task
+    /// `reference`, e.g. to run a cleanup 30 seconds after a job. Later alarms follow from the
+    /// frequency of the task.
+    ///
+    /// Fails with `TaskError::TaskNotFound` if `reference` isn't scheduled, and for a task with
+    /// a custom schedule.
+    pub fn add_task_after(
+        &self,
+        reference: TaskId,
+        offset_secs: u64,
+        task: Task,
+    ) -> Result<(), TaskError> {
+        self.adding(|wheel| wheel.add_task_after(reference, offset_secs, task))
+    }
+
+    /// Check whether `add_tasks` would schedule each task, without scheduling any, e.g. before
+    /// committing a large batch. Returns the result `add_tasks` would give for each task.
+    pub fn validate(&self, tasks: &[Task]) -> Vec<Result<(), TaskError>> {
+        self.wheel.validate(tasks)
+    }
+
+    /// Schedule tasks in a batch, returning the result of each task in order.
+    ///
+    /// A task whose id is already scheduled fails with `TaskError::DuplicateTask`.
+    pub fn add_tasks(&self, tasks: Vec<Task>) -> Vec<Result<(), TaskError>> {
+        self.adding(|wheel| wheel.add_tasks(tasks))
+    }
+
+    /// Schedule tasks as a group, returning a handle operating on just these tasks. Either all
+    /// tasks are added or none, the first failure is returned.
+    pub fn add_group(&self, tasks: Vec<Task>) -> Result<TaskGroup, TaskError> {
+        let group_id = self.adding(|wheel| wheel.add_group(tasks))?;
+        Ok(TaskGroup::new(group_id, self.wheel.clone()))
+    }
+
+    /// Make room for at least `additional` more tasks, ahead of adding them one at a time.
+    /// `add_tasks` reserves for its batch by itself.
+    pub fn reserve(&self, additional: usize) {
+        self.wheel.reserve(additional)
+    }
+
+    /// Remove a scheduled task, returning it if it was present. Runs of the task in flight are
+    /// aborted as well, see `cancel_running`.
+    pub fn remove_task(&self, task_id: TaskId) -> Option<Task> {
+        self.in_flight.remove_task(task_id)
+    }
+
+    /// Remove a scheduled task like `remove_task`, returning only its tracking information, for
+    /// cleanup paths that have no use for the task and its runner.
+    pub fn remove_task_info(&self, task_id: TaskId) -> Option<TaskTrackingInfo> {
+        self.in_flight.remove_task_info(task_id)
+    }
+
+    /// The uptime, run counts and pending tasks of the timer, for health checks.
+    pub fn stats(&self) -> TimerStats {
+        TimerStats {
+            uptime: self
+                .started_at
+                .lock()
+                .unwrap()
+                .map_or(Duration::ZERO, |started_at| started_at.elapsed()),
+            total_fired: self.total_fired.load(Ordering::Relaxed),
+            total_failed: self.total_failed.load(Ordering::Relaxed),
+            pending: self.wheel.len(),
+            minute_cascades: self.wheel.minute_cascades(),
+            hour_cascades: self.wheel.hour_cascades(),
+        }
+    }
+
+    /// Zero the counters of `stats`, returning the stats counted until now, e.g. to report the
+    /// runs per interval rather than since the start.
+    ///
+    /// Each counter is swapped on its own, so every increment lands in exactly one interval,
+    /// but a run that fired before the reset and fails after it counts as a failure of the next.
+    /// The uptime and the pending tasks are not counters and are left as they are.
+    pub fn reset_metrics(&self) -> TimerStats {
+        let mut stats = self.stats();
+        stats.total_fired = self.total_fired.swap(0, Ordering::Relaxed);
+        stats.total_failed = self.total_failed.swap(0, Ordering::Relaxed);
+        (stats.minute_cascades, stats.hour_cascades) = self.wheel.take_cascades();
+        stats
+    }
+
+    /// Whether the run loop ticked within `max_staleness`, for supervisors to restart a timer
+    /// whose loop is stuck or gone. A loop parked on an empty wheel counts as healthy, a timer
+    /// that never ran as not.
+    pub fn is_healthy(&self, max_staleness: Duration) -> bool {
+        match self.last_tick.load(Ordering::SeqCst) {
+            NEVER_TICKED => false,
+            PARKED => true,
+            last_tick => {
+                let elapsed = self.created_at.elapsed().as_millis() as u64;
+                elapsed.saturating_sub(last_tick) <= max_staleness.as_millis() as u64
+            }
+        }
+    }
+
+    /// Record that the run loop is alive now.
+    fn beat(&self) {
+        let elapsed = self.created_at.elapsed().as_millis() as u64;
+        self.last_tick
+            .store(elapsed.min(PARKED - 1), Ordering::SeqCst);
+    }
+
+    /// Abort the runs of a task in flight without removing the task, returning how many were
+    /// aborted. Neither `TaskCompleted` nor `TaskFailed` is published for them.
+    ///
+    /// An aborted run's future is dropped at its next `.await`, so runners must be
+    /// cancellation-aware: state that must not be left half-done belongs in a guard whose
+    /// `Drop` restores it, not in code after an `.await`. Work a run handed off, e.g. to a
+    /// spawned task, can stop with it through `TaskContext::cancellation`.
+    pub fn cancel_running(&self, task_id: TaskId) -> usize {
+        self.in_flight.abort(task_id)
+    }
+
+    /// The number of pending tasks. Tasks whose runner is in flight are counted once they are
+    /// scheduled for their next alarm.
+    pub fn len(&self) -> usize {
+        self.wheel.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.wheel.is_empty()
+    }
+
+    /// Whether a task with the id is scheduled.
+    pub fn contains(&self, task_id: TaskId) -> bool {
+        self.wheel.contains(task_id)
+    }
+
+    /// The timestamp in seconds of the next alarm of a task, `None` if it is not scheduled.
+    ///
+    /// The timestamp follows the wheels, which may lag a little behind the wall clock.
+    pub fn next_fire_time(&self, task_id: TaskId) -> Option<u64> {
+        self.wheel.next_fire_time(task_id)
+    }
+
+    /// The metadata of a scheduled task: its name, tags, position and next alarm. `None` if it
+    /// is not scheduled.
+    pub fn task_info(&self, task_id: TaskId) -> Option<TaskTrackingInfo> {
+        self.wheel.get_task_tracking_info(task_id)
+    }
+
+    /// Locate a scheduled task: its wheel, slot and the ticks until it fires.
+    pub fn task_location(&self, task_id: TaskId) -> Option<TaskLocation> {
+        self.wheel.task_location(task_id)
+    }
+
+    /// Swap what a scheduled task runs, keeping its id and schedule. A run in flight finishes
+    /// with the previous runner, the next run uses the new one.
+    pub fn replace_runner<R: TaskRunner<Output = ()>>(
+        &self,
+        task_id: TaskId,
+        runner: R,
+    ) -> Result<(), TaskError> {
+        self.wheel.replace_runner(task_id, Arc::new(runner))
+    }
+
+    /// The number of alarms a countdown task has left, including the pending one.
+    pub fn remaining_count(&self, task_id: TaskId) -> Option<u64> {
+        self.wheel.remaining_count(task_id)
+    }
+
+    /// Set the number of alarms a countdown task has left, including the pending one, e.g. to
+    /// extend it without recreating the task.
+    pub fn set_remaining_count(&self, task_id: TaskId, count: u64) -> Result<(), TaskError> {
+        self.wheel.set_remaining_count(task_id, count)
+    }
+
+    /// The enabled tasks due within `within_secs` seconds, with their alarm timestamps,
+    /// soonest first.
+    pub fn upcoming(&self, within_secs: u64) -> Vec<(TaskId, u64)> {
+        self.wheel.upcoming(within_secs)
+    }
+
+    /// The ids of the tasks the next tick dispatches, in dispatch order, e.g. to see what is
+    /// about to fire without running it.
+    pub fn due_tasks_preview(&self) -> Vec<TaskId> {
+        self.wheel.due_tasks_preview()
+    }
+
+    /// The tick, counted from the creation of the timer, on which the task is next due.
+    pub fn fire_tick(&self, task_id: TaskId) -> Option<u64> {
+        self.wheel.fire_tick(task_id)
+    }
+
+    /// The positions of the hands of the wheels in tick units, for diagnostics.
+    pub fn wheel_positions(&self) -> WheelPositions {
+        self.wheel.positions()
+    }
+
+    /// Reposition the tasks that were overdue when scheduled, e.g. after `load_schedule` restored
+    /// an old snapshot. See `OverduePolicy`. Returns how many tasks were rescheduled.
+    pub fn reschedule_overdue(&self, policy: OverduePolicy) -> usize {
+        self.wheel.reschedule_overdue(policy)
+    }
+
+    /// Take every task whose alarm already passed wherever it waits, e.g. after `load_schedule`
+    /// restored an old snapshot, in the order they would fire. Running them is up to the
+    /// caller; they are already scheduled again for their next alarm, as with `drain_due`.
+    /// See `reschedule_overdue` to let the timer fire them instead.
+    pub fn collect_overdue(&self) -> Vec<Task> {
+        self.wheel.hand_over(self.wheel.collect_overdue())
+    }
+
+    /// Freeze the tasks on a wheel level, e.g. to hold everything more than a minute out.
+    /// See `resume_wheel`; tasks whose slot was passed while paused move on a revolution later.
+    pub fn pause_wheel(&self, wheel_type: WheelType) {
+        self.wheel.pause_wheel(wheel_type)
+    }
+
+    /// Let the tasks on a paused wheel level cascade and fire again.
+    pub fn resume_wheel(&self, wheel_type: WheelType) {
+        self.wheel.resume_wheel(wheel_type)
+    }
+
+    /// Stop firing the task while keeping its schedule, occurrences while disabled are skipped.
+    /// Returns `false` if the task is not scheduled.
+    pub fn disable_task(&self, task_id: TaskId) -> bool {
+        self.wheel.disable_task(task_id)
+    }
+
+    /// Resume firing a disabled task from its next alarm.
+    /// Returns `false` if the task is not scheduled.
+    pub fn enable_task(&self, task_id: TaskId) -> bool {
+        self.wheel.enable_task(task_id)
+    }
+
+    /// Swap the frequency of a scheduled task in place, its next alarm is counted from now.
+    /// The name, tags, priority and runner state of the task are kept.
+    pub fn update_frequency(
+        &self,
+        task_id: TaskId,
+        frequency: FrequencySeconds,
+    ) -> Result<(), TaskError> {
+        self.wheel.update_frequency(task_id, frequency)
+    }
+
+    /// Move the next alarm of a scheduled task to `timestamp`, later alarms follow its
+    /// frequency from there on. Timestamps that are not in the future are rejected.
+    pub fn set_next_fire(&self, task_id: TaskId, timestamp: u64) -> Result<(), TaskError> {
+        self.wheel.set_next_fire(task_id, timestamp)
+    }
+
+    /// Find the ids of the tasks tagged with `key=value`, in ascending order.
+    pub fn find_tasks_by_tag(&self, key: &str, value: &str) -> Vec<TaskId> {
+        self.wheel.find_tasks_by_tag(key, value)
+    }
+
+    /// Count the scheduled tasks per value of the tag `key`, e.g. the tasks of each tenant.
+    pub fn task_count_by_tag(&self, key: &str) -> HashMap<String, usize> {
+        self.wheel.task_count_by_tag(key)
+    }
+
+    /// The number of scheduled tasks in the second, minute and hour wheel.
+    pub fn level_distribution(&self) -> (usize, usize, usize) {
+        self.wheel.level_distribution()
+    }
+
+    /// A rough estimate of the memory the wheels take in bytes, see
+    /// `MulitWheel::estimated_memory_bytes`.
+    pub fn estimated_memory_bytes(&self) -> usize {
+        self.wheel.estimated_memory_bytes()
+    }
+
+    /// Check the wheels against the task tracker, see `MulitWheel::audit`.
+    pub fn audit(&self) -> Result<(), Vec<AuditError>> {
+        self.wheel.audit()
+    }
+
+    /// Reconcile the wheels with the task tracker, see `MulitWheel::gc_orphans`.
+    pub fn gc_orphans(&self) -> usize {
+        self.wheel.gc_orphans()
+    }
+
+    /// Remove every task tagged with `key=value`, returning how many were removed.
+    pub fn remove_tasks_by_tag(&self, key: &str, value: &str) -> usize {
+        let removed = self.wheel.remove_tasks_by_tag(key, value);
+        if removed > 0 {
+            self.in_flight.publish_if_idle();
+        }
+        removed
+    }
+
+    /// Get a handle sending add, remove and reschedule commands to the run loop.
+    pub fn handle(&self) -> TimerHandle {
+        TimerHandle::new(self.commands.clone())
+    }
+
+    /// Get a receiver of the events published by the timer.
+    ///
+    /// See the channel backend for how events are shared between receivers.
+    pub fn event_receiver(&self) -> EventReceiver {
+        EventReceiver::new(self.events.sender())
+    }
+
+    /// Get the events published by the timer as a stream, for composing with `StreamExt`.
+    ///
+    /// The stream receives events like a receiver from `event_receiver`.
+    #[cfg(feature = "stream")]
+    pub fn event_stream(&self) -> impl Stream<Item = TimerEvent> + use<> {
+        futures::stream::unfold(self.event_receiver(), |mut receiver| async move {
+            receiver.recv().await.map(|event| (event, receiver))
+        })
+    }
+
+    /// Get the most recent events retained by the event log, oldest first.
+    ///
+    /// Returns an empty list if the timer was built without an event log.
+    pub fn recent_events(&self) -> Vec<TimerEvent> {
+        self.events.recent_events()
+    }
+
+    /// Get the tasks whose schedule finished, oldest first, with the outcome of their last run.
+    ///
+    /// Returns an empty list if the timer was built without completed retention.
+    pub fn completed_tasks(&self) -> Vec<CompletedTask> {
+        match &self.completed {
+            Some(completed) => completed.tasks.lock().unwrap().iter().cloned().collect(),
+            None => vec![],
+        }
+    }
+
+    /// Write the metadata of the scheduled tasks to a JSON file.
+    ///
+    /// Fails if a task has a custom schedule, see `Schedule`.
+    #[cfg(feature = "serde")]
+    pub fn save_schedule(&self, path: impl AsRef<Path>) -> Result<(), TaskError> {
+        ScheduleSnapshot::capture(&self.wheel)?.write(path)
+    }
+
+    /// Write the schedule for `resume_from` to pick up in the next process, typically once the
+    /// run loop returned after `shutdown`. Every task keeps its phase: a repeated task continues
+    /// on the same seconds and a countdown with the alarms it has left.
+    ///
+    /// Unlike `save_schedule`, the tasks that arrived but are still deferred by dispatch
+    /// batching are kept too, they fire on the first tick after resuming. Fails if a task has
+    /// a custom schedule, see `Schedule`.
+    #[cfg(feature = "serde")]
+    pub fn persist(&self, path: impl AsRef<Path>) -> Result<(), TaskError> {
+        let mut snapshot = ScheduleSnapshot::capture(&self.wheel)?;
+        for task in self.in_flight.deferred.lock().unwrap().iter() {
+            snapshot.tasks.push(TaskRecord::try_from(task)?);
+        }
+        snapshot.tasks.sort_by_key(|record| record.task_id);
+        snapshot.write(path)
+    }
+
+    /// Make a timer with the default configuration scheduling the tasks written by `persist`,
+    /// resolving their runners through the registry. Tasks that became overdue while the
+    /// process was down fire once on the first tick, see `TimerBuilder::resume_from` for
+    /// another policy.
+    #[cfg(feature = "serde")]
+    pub fn resume_from(
+        path: impl AsRef<Path>,
+        registry: &RunnerRegistry,
+    ) -> Result<Timer, TaskError> {
+        TimerBuilder::new().resume_from(path, registry, OverduePolicy::FireOnce)
+    }
+
+    /// Schedule the tasks saved by `save_schedule`, resolving their runners through the registry.
+    ///
+    /// Tasks whose alarm already passed while the schedule was stored fire on the next tick.
+    #[cfg(feature = "serde")]
+    pub fn load_schedule(
+        &self,
+        path: impl AsRef<Path>,
+        registry: &RunnerRegistry,
+    ) -> Result<(), TaskError> {
+        let snapshot = ScheduleSnapshot::read(path)?;
+        self.adding(|wheel| snapshot.restore(wheel, registry))
+    }
+
+    /// Encode the metadata of the scheduled tasks like `save_schedule`, but with bincode, a
+    /// compact binary form for schedules of many tasks.
+    ///
+    /// Fails if a task has a custom schedule, see `Schedule`.
+    #[cfg(feature = "bincode")]
+    pub fn snapshot_bincode(&self) -> Result<Vec<u8>, TaskError> {
+        self.wheel.snapshot_bincode()
+    }
+
+    /// Schedule the tasks encoded by `snapshot_bincode`, resolving their runners through the
+    /// registry. Tasks whose alarm already passed fire on the next tick.
+    #[cfg(feature = "bincode")]
+    pub fn restore_bincode(
+        &self,
+        bytes: &[u8],
+        registry: &RunnerRegistry,
+    ) -> Result<(), TaskError> {
+        self.adding(|wheel| wheel.restore_bincode(bytes, registry))
+    }
+
+    /// Schedule the tasks declared in a JSON document, building their runners through the
+    /// registry, e.g.
+    ///
+    /// ```json
+    /// { "tasks": [{ "task_id": 1, "name": "cleanup", "runner_name": "cleanup",
+    ///   "frequency": { "type": "Repeated", "seconds": 60 }, "tags": { "team": "ops" } }] }
+    /// ```
+    ///
+    /// Returns the result of each entry in document order, an entry that fails, e.g. for an
+    /// unknown runner name or an invalid frequency, doesn't stop the others from loading.
+    /// Fails as a whole only if the document is not of this form.
+    #[cfg(feature = "serde")]
+    pub fn load_tasks_from_json(
+        &self,
+        json: &str,
+        registry: &RunnerRegistry,
+    ) -> Result<Vec<Result<TaskId, TaskError>>, TaskError> {
+        self.adding(|wheel| schedule::load_task_definitions(json, wheel, registry))
+    }
+
+    /// Take the tasks that became due since the last call, in the order they arrived, when the
+    /// timer was built without auto execution. Running them is up to the caller; they are
+    /// already scheduled again for their next alarm. Always empty with auto execution.
+    ///
+    /// The timer doesn't see how the runs end, so a drained task doesn't count as completed
+    /// for the tasks depending on it until the caller reports it with `mark_completed`.
+    pub fn drain_due(&self) -> Vec<Task> {
+        match &self.due {
+            Some(due) => std::mem::take(&mut *due.lock().unwrap()),
+            None => vec![],
+        }
+    }
+
+    /// Count a completed run of a task the caller ran itself, e.g. one taken with `drain_due`,
+    /// so the tasks depending on it through `TaskBuilder::with_depends_on` can fire.
+    pub fn mark_completed(&self, task_id: TaskId) {
+        self.wheel.record_completion(task_id);
+    }
+
+    /// Advance the wheels by one tick, a second unless the wheels were built with a finer
+    /// resolution, and dispatch the tasks that arrived.
+    ///
+    /// Must be called within a tokio runtime, as runners are spawned onto it.
+    pub fn tick_once(&self) {
+        self.wheel.tick();
+        self.notify_tick();
+        self.dispatch_all(self.wheel.take_arrived_tasks());
+    }
+
+    /// Call `callback` with the tick count on every tick of the run loop, before the tasks that
+    /// arrived are dispatched, e.g. to flush a buffer every second without scheduling a task.
+    /// A resync after the wheels fell behind counts as one tick. Replaces any previous callback.
+    ///
+    /// The callback runs on the run loop and holds up the dispatch, so it must be cheap and
+    /// must not block; one running longer than 50 milliseconds is logged as a warning.
+    pub fn on_tick(&self, callback: TickCallback) {
+        *self.on_tick.lock().unwrap() = Some(callback);
+    }
+
+    fn notify_tick(&self) {
+        // Called outside the lock, so the callback may replace itself
+        let Some(callback) = self.on_tick.lock().unwrap().clone() else {
+            return;
+        };
+        let ticks = self.wheel.ticks();
+        let started = Instant::now();
+        callback(ticks);
+        let elapsed = started.elapsed();
+        if elapsed > SLOW_TICK_CALLBACK {
+            tracing::warn!(ticks, ?elapsed, "tick callback is holding up the timer");
+        }
+    }
+
+    /// Advance the wheels by `seconds`, one tick at a time, and dispatch the tasks that arrive
+    /// on each tick as `tick_once` does, so a repeated task fires once per alarm passed.
+    ///
+    /// Must be called within a tokio runtime, as runners are spawned onto it.
+    pub fn fast_forward(&self, seconds: u64) {
+        // Tick by tick, so a task is back in the wheels for its next alarm before the next tick
+        for _ in 0..seconds * self.wheel.ticks_per_second() {
+            self.tick_once();
+        }
+    }
+
+    /// Take every task in one slot without moving the hands, see `MulitWheel::fire_slot`.
+    /// Running them is up to the caller.
+    #[cfg(feature = "test-util")]
+    pub fn fire_slot(&self, wheel_type: WheelType, slot_num: u64) -> Vec<Task> {
+        self.wheel.fire_slot(wheel_type, slot_num)
+    }
+
+    /// The wall clock in seconds, shifted by the time offset.
+    fn now(&self) -> u64 {
+        (self.now)().saturating_add_signed(self.time_offset)
+    }
+
+    /// Tick once, or resync the wheels if they fell far behind the wall clock, e.g. after the
+    /// machine was suspended, so the tasks that became due meanwhile fire once.
+    fn advance(&self) {
+        let now = self.now();
+        if self.wheel.lag_behind(now) > RESYNC_THRESHOLD {
+            let arrived = self.wheel.resync(now);
+            self.notify_tick();
+            self.dispatch_all(arrived);
+        } else {
+            self.tick_once();
+        }
+    }
+
+    /// Drive the timer once per tick until `shutdown` is called.
+    pub async fn run(&self) {
+        self.run_until(std::future::pending()).await
+    }
+
+    /// Drive the timer once per tick until it becomes idle, see `TimerEvent::Idle`, e.g. for
+    /// a batch of tasks to run to completion. Returns right away if nothing is scheduled or
+    /// running.
+    pub async fn run_until_idle(&self) {
+        if self.in_flight.is_idle() {
+            return;
+        }
+        self.run_until(async {
+            loop {
+                // Listen before checking, so becoming idle in between isn't missed
+                let became_idle = self.in_flight.became_idle.notified();
+                tokio::pin!(became_idle);
+                became_idle.as_mut().enable();
+                if self.in_flight.is_idle() {
+                    return;
+                }
+                became_idle.await;
+            }
+        })
+        .await
+    }
+
+    /// Drive the timer once per tick until `shutdown` is called or the token is cancelled.
+    #[cfg(feature = "tokio-util")]
+    pub async fn run_with_cancellation(&self, token: CancellationToken) {
+        self.run_until(token.cancelled_owned()).await
+    }
+
+    /// Every branch is cancellation safe and `select!` polls them in random order, so a busy
+    /// command channel doesn't starve the clock. A tick that started always completes, so its
+    /// runners are dispatched before the loop stops.
+    async fn run_until(&self, stop: impl Future<Output = ()>) {
+        // Also when the loop's future is dropped while parked, e.g. its task was aborted
+        let _unpark = Unpark(self);
+        let mut clock = Clock::with_period(self.wheel.resolution());
+        self.started_at
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Instant::now);
+        // Another loop already running on this timer keeps the commands to itself
+        let mut commands = self.command_receiver.lock().unwrap().take();
+        tokio::pin!(stop);
+        loop {
+            if self.wheel.is_empty() && !self.in_flight.has_deferred() {
+                // Nothing can fire, so park instead of ticking until a task is added. Check
+                // again once idle is visible, a task added in between wakes the wheels itself.
+                self.idle.store(true, Ordering::SeqCst);
+                if self.wheel.is_empty() {
+                    self.last_tick.store(PARKED, Ordering::SeqCst);
+                    tokio::select! {
+                        _ = self.task_added.notified() => {}
+                        _ = self.raised_panic.raised.notified() => self.raised_panic.propagate(),
+                        Some(command) = Self::next_command(&mut commands) => {
+                            self.wake();
+                            command.apply(&self.wheel);
+                        }
+                        _ = self.shutdown.notified() => break,
+                        _ = &mut stop => break,
+                    }
+                }
+                self.wake();
+                self.beat();
+                // Tick a period after waking rather than bursting the ticks missed while idle
+                clock = Clock::with_period(self.wheel.resolution());
+                continue;
+            }
+            tokio::select! {
+                _ = clock.tick() => {
+                    self.beat();
+                    // Under strict delivery the receivers catch up before more tasks fire
+                    self.events.flush().await;
+                    self.advance();
+                }
+                // The next batch of deferred tasks, after the executor had a turn
+                _ = tokio::task::yield_now(), if self.in_flight.has_deferred() => {
+                    self.dispatch_all(vec![]);
+                }
+                _ = self.raised_panic.raised.notified() => self.raised_panic.propagate(),
+                Some(command) = Self::next_command(&mut commands) => command.apply(&self.wheel),
+                _ = self.shutdown.notified() => break,
+                _ = &mut stop => break,
+            }
+        }
+
+        if let Some(mut receiver) = commands {
+            // Answer the commands queued before the stop rather than leaving them waiting
+            while let Ok(command) = receiver.try_recv() {
+                command.apply(&self.wheel);
+            }
+            *self.command_receiver.lock().unwrap() = Some(receiver);
+        }
+        if self.drain_on_shutdown.swap(false, Ordering::SeqCst) {
+            self.drain().await;
+        }
+        // The deferred tasks already arrived, they aren't held back by the stop
+        while self.in_flight.has_deferred() {
+            self.dispatch_all(vec![]);
+        }
+        self.events.flush().await;
+        self.events.publish(TimerEvent::StopTimer);
+    }
+
+    async fn next_command(
+        commands: &mut Option<mpsc::UnboundedReceiver<TimerCommand>>,
+    ) -> Option<TimerCommand> {
+        match commands {
+            Some(receiver) => receiver.recv().await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Add tasks through `add`, waking the run loop if it idles on an empty wheel.
+    fn adding<T>(&self, add: impl FnOnce(&MulitWheel) -> T) -> T {
+        self.wake();
+        let result = add(&self.wheel);
+        self.task_added.notify_one();
+        result
+    }
+
+    /// Catch the wheels up with the clock after the run loop idled, before anything is
+    /// scheduled relative to them.
+    fn wake(&self) {
+        if self.idle.swap(false, Ordering::SeqCst) {
+            self.dispatch_all(self.wheel.resync(self.now()));
+        }
+    }
+
+    /// Stop the running loop. If the loop is not running yet, it stops as soon as it starts.
+    ///
+    /// The loop delivers its held back events and publishes `TimerEvent::StopTimer` as the last
+    /// event before it returns, `EventReceiver::drain_events` then takes what is buffered.
+    /// Runs in flight keep going and publish their own events when they finish.
+    pub fn shutdown(&self) {
+        self.shutdown.notify_one();
+    }
+
+    /// Stop the running loop like `shutdown`, but first fire the tasks whose alarm already
+    /// passed on the wall clock while the wheels lag behind, so none of them is lost. The loop
+    /// returns once their runners have finished.
+    pub fn shutdown_drain(&self) {
+        self.drain_on_shutdown.store(true, Ordering::SeqCst);
+        self.shutdown.notify_one();
+    }
+
+    /// Fire the tasks whose alarm passed on the wall clock but that the wheels haven't reached
+    /// yet, and wait for their runners to finish.
+    async fn drain(&self) {
+        let mut runs = self.dispatch_all(self.wheel.resync(self.now()));
+        while self.in_flight.has_deferred() {
+            runs.extend(self.dispatch_all(vec![]));
+        }
+        for run in runs {
+            let _ = run.await;
+        }
+    }
+
+    /// Returns the spawned runs, none without auto execution.
+    fn dispatch_all(&self, tasks: Vec<Task>) -> Vec<JoinHandle<()>> {
+        if let Some(due) = &self.due {
+            due.lock().unwrap().extend(self.wheel.hand_over(tasks));
+            return vec![];
+        }
+        let tasks = match &self.fair_dispatch {
+            Some(fair_dispatch) => fair_dispatch.order(tasks),
+            None => tasks,
+        };
+        let tasks = match self.dispatch_batch_size {
+            Some(size) => {
+                let mut deferred = self.in_flight.deferred.lock().unwrap();
+                deferred.extend(tasks);
+                let batch = size.min(deferred.len());
+                deferred.drain(..batch).collect()
+            }
+            None => tasks,
+        };
+        tasks.into_iter().map(|task| self.dispatch(task)).collect()
+    }
+
+    fn dispatch(&self, mut task: Task) -> JoinHandle<()> {
+        let task_id = task.task_id;
+        let name = task.name.clone();
+        self.events.publish(TimerEvent::TaskFired {
+            task_id,
+            name: name.clone(),
+        });
+        if let Some(event) = TimerEvent::deadline_missed(&task, self.now()) {
+            self.events.publish(event);
+        }
+
+        let runner = task.runner.clone();
+        let events = self.events.clone();
+        let wheel = self.wheel.clone();
+        // Slots are requested here, so they are handed out in dispatch order
+        let task_slot = task.max_in_flight.map(|limit| {
+            let mut entry = self
+                .task_slots
+                .entry(task_id)
+                .or_insert_with(|| RunSlots::new(limit));
+            // A task added back with another limit starts over
+            if entry.limit != limit {
+                *entry = RunSlots::new(limit);
+            }
+            entry.request()
+        });
+        // A run waiting for a slot of its task asks for a run slot only once it has one, so it
+        // doesn't hold a run slot other tasks could use meanwhile
+        let run_slot = match task_slot {
+            Some(SlotRequest::Queued(_)) => None,
+            _ => self.run_slots.as_ref().map(|slots| slots.request()),
+        };
+        let run_slots = self.run_slots.clone();
+        let (max_failures, failures) = (task.max_failures, self.failures.clone());
+        let total_failed = self.total_failed.clone();
+        let (panic_strategy, raised_panic) = (self.panic_strategy, self.raised_panic.clone());
+        let (rearm, commands) = (task.rearm_after_failure(), self.commands.clone());
+        // The schedule ran out with this run
+        let completed = match task.peek_state() {
+            SchedulePeek::Exhausted => self.completed.clone(),
+            SchedulePeek::Next(_) => None,
+        };
+        let group_id = task.group_id;
+        // A stale instant of a removed task with the same id is past, it doesn't hold the run
+        let fire_at = self
+            .fire_at
+            .as_ref()
+            .and_then(|fire_at| fire_at.remove(&task_id))
+            .map(|(_, instant)| instant);
+        self.total_fired.fetch_add(1, Ordering::Relaxed);
+        let (run, start) = self.in_flight.spawn(task_id, async move {
+            if let Some(instant) = fire_at {
+                tokio::time::sleep_until(instant).await;
+            }
+            let _task_slot = match task_slot {
+                Some(request) => Some(request.wait().await),
+                None => None,
+            };
+            let _run_slot = match run_slot.or_else(|| run_slots.map(|slots| slots.request())) {
+                Some(request) => Some(request.wait().await),
+                None => None,
+            };
+            let mut run = runner.run();
+            let caught = std::future::poll_fn(|cx| {
+                match std::panic::catch_unwind(AssertUnwindSafe(|| run.as_mut().poll(cx))) {
+                    Ok(poll) => poll.map(Ok),
+                    Err(payload) => Poll::Ready(Err(payload)),
+                }
+            })
+            .await;
+            let result = match caught {
+                Ok(result) => result,
+                Err(payload) if panic_strategy == PanicStrategy::Abort => {
+                    raised_panic.raise(payload);
+                    return;
+                }
+                Err(payload) => {
+                    Err(TaskError::RunnerFailed(panic_message(&*payload).to_string()).into())
+                }
+            };
+            if let Some(completed) = &completed {
+                // A re-armed task isn't done yet
+                if result.is_ok() || rearm.is_none() {
+                    completed.push(CompletedTask {
+                        task_id,
+                        name: name.clone(),
+                        last_record_id: runner.last_record_id(),
+                        outcome: match &result {
+                            Ok(()) => TaskOutcome::Completed,
+                            Err(e) => TaskOutcome::Failed(e.to_string()),
+                        },
+                    });
+                }
+            }
+            match result {
+                Ok(()) => {
+                    if max_failures.is_some() {
+                        failures.remove(&task_id);
+                    }
+                    wheel.record_completion(task_id);
+                    events.publish(TimerEvent::TaskCompleted { task_id, name })
+                }
+                Err(e) => {
+                    total_failed.fetch_add(1, Ordering::Relaxed);
+                    events.publish(TimerEvent::TaskFailed {
+                        task_id,
+                        name,
+                        error: e.to_string(),
+                    });
+                    if let Some(limit) = max_failures {
+                        // The entry is let go before a removal, which locks the same shard
+                        let count = {
+                            let mut count = failures.entry(task_id).or_insert(0);
+                            *count += 1;
+                            *count
+                        };
+                        if count >= limit {
+                            failures.remove(&task_id);
+                            // The run itself keeps going, unlike `Timer::remove_task`
+                            wheel.remove_task(task_id);
+                            events.publish(TimerEvent::TaskDescheduled {
+                                task_id,
+                                failures: count,
+                            });
+                            events.flush().await;
+                            return;
+                        }
+                    }
+                    let aborted = group_id.and_then(|group_id| {
+                        let cancelled = wheel.abort_group(group_id)?;
+                        Some(TimerEvent::GroupAborted {
+                            group_id,
+                            task_id,
+                            cancelled,
+                        })
+                    });
+                    match (aborted, rearm) {
+                        (Some(event), _) => {
+                            wheel.record_failure(task_id);
+                            events.publish(event)
+                        }
+                        // Through the run loop, so a loop parked on the empty wheel wakes for it
+                        (None, Some(task)) => {
+                            let (reply, _) = oneshot::channel();
+                            let _ = commands.send(TimerCommand::Add(Box::new(task), reply));
+                        }
+                        (None, None) => wheel.record_failure(task_id),
+                    }
+                }
+            }
+            events.flush().await;
+        });
+
+        // Put the task back for its next alarm, a finished schedule is simply dropped. The run
+        // starts after, so the timer doesn't look idle in between.
+        if let Err(TaskError::TaskExpired(task_id)) = self.wheel.requeue(task) {
+            self.events.publish(TimerEvent::TaskExpired { task_id });
+        }
+        let _ = start.send(());
+        run
+    }
+}
+
+#[derive(Clone)]
+pub struct TimerBuilder {
+    event_channel_capacity: usize,
+    event_log_capacity: Option<usize>,
+    event_delivery: EventDelivery,
+    wheel: MulitWheelBuilder,
+    now: fn() -> u64,
+    fair_dispatch_tag: Option<String>,
+    group_weights: HashMap<String, usize>,
+    max_concurrent_runs: Option<usize>,
+    dispatch_batch_size: Option<usize>,
+    auto_execution: bool,
+    time_offset: i64,
+    panic_strategy: PanicStrategy,
+    runtime: Option<Handle>,
+    default_frequency: Option<FrequencySeconds>,
+    precise_first_fire: bool,
+    completed_retention: Option<usize>,
+}
+
+impl Default for TimerBuilder {
+    fn default() -> Self {
+        Self {
+            event_channel_capacity: DEFAULT_EVENT_CHANNEL_CAPACITY,
+            event_log_capacity: None,
+            event_delivery: EventDelivery::BestEffort,
+            wheel: MulitWheelBuilder::default(),
+            now: timestamp,
+            fair_dispatch_tag: None,
+            group_weights: HashMap::new(),
+            max_concurrent_runs: None,
+            dispatch_batch_size: None,
+            auto_execution: true,
+            time_offset: 0,
+            panic_strategy: PanicStrategy::Isolate,
+            runtime: None,
+            default_frequency: None,
+            precise_first_fire: false,
+            completed_retention: None,
+        }
+    }
+}
+
+impl TimerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the capacity of the event channel.
+    pub fn with_event_channel_capacity(mut self, capacity: usize) -> Self {
+        self.event_channel_capacity = capacity.max(1);
+        self
+    }
+
+    /// Choose whether the timer waits for slow receivers rather than dropping the events of
+    /// runs, `EventDelivery::BestEffort` by default. See `EventDelivery::Strict` for the cost.
+    pub fn with_event_delivery(mut self, delivery: EventDelivery) -> Self {
+        self.event_delivery = delivery;
+        self
+    }
+
+    /// Retain the last `capacity` events, accessible via `Timer::recent_events`.
+    pub fn with_event_log_capacity(mut self, capacity: usize) -> Self {
+        self.event_log_capacity = Some(capacity);
+        self
+    }
+
+    /// Retain the last `capacity` tasks whose schedule finished, e.g. once tasks after their
+    /// run, accessible via `Timer::completed_tasks`. A once task re-armed after a failure is
+    /// retained when its schedule finishes for good.
+    pub fn with_completed_retention(mut self, capacity: usize) -> Self {
+        self.completed_retention = Some(capacity);
+        self
+    }
+
+    /// Set the order in which tasks arriving in the same second with the same priority are
+    /// dispatched: `TieBreak::Fifo`, the default, keeps the order the tasks were scheduled for
+    /// the alarm in, `TieBreak::TaskId` orders them by id so runs are reproducible however the
+    /// tasks were added. Higher priorities always go first.
+    pub fn with_tie_break(mut self, tie_break: TieBreak) -> Self {
+        self.wheel = self.wheel.with_tie_break(tie_break);
+        self
+    }
+
+    /// Cap the number of scheduled tasks to bound the memory of the timer. Adding a task to a
+    /// full timer fails with `TaskError::CapacityExceeded`, counted against `Timer::len`; a
+    /// repeated task being rescheduled after it fired doesn't count as added.
+    pub fn with_max_tasks(mut self, max_tasks: usize) -> Self {
+        self.wheel = self.wheel.with_max_tasks(max_tasks);
+        self
+    }
+
+    /// Set the source of the random numbers of the timer, e.g. for `TaskBuilder::with_jitter`.
+    /// A `SeededRandSource` makes randomized scheduling reproducible in tests.
+    pub fn with_rand_source(mut self, rand: impl RandSource) -> Self {
+        self.wheel = self.wheel.with_rand_source(rand);
+        self
+    }
+
+    /// Set the capacity a wheel slot keeps when it shrinks. A slot drained below a quarter of
+    /// its capacity after a burst of tasks shrinks, but not below this floor.
+    pub fn with_slot_capacity_floor(mut self, capacity_floor: usize) -> Self {
+        self.wheel = self.wheel.with_slot_capacity_floor(capacity_floor);
+        self
+    }
+
+    /// Use wheels of the given structure, failing if it is not valid.
+    ///
+    /// The slot capacity floor of `wheel` replaces the one set by `with_slot_capacity_floor`.
+    pub fn with_wheel(mut self, wheel: MulitWheelBuilder) -> Result<Self, TaskError> {
+        wheel.validate()?;
+        self.wheel = wheel;
+        Ok(self)
+    }
+
+    /// Dispatch the tasks arriving together fairly across the groups formed by the values of the
+    /// tag `tag_key`, e.g. `tenant`, instead of by priority and scheduling order alone.
+    ///
+    /// Groups take turns, each taking as many tasks per turn as its weight, see
+    /// `with_group_weight`. Within a group tasks keep their order. Combined with
+    /// `with_max_concurrent_runs` this keeps one busy group from taking every run slot.
+    pub fn with_fair_dispatch(mut self, tag_key: impl Into<String>) -> Self {
+        self.fair_dispatch_tag = Some(tag_key.into());
+        self
+    }
+
+    /// Let the group with the tag value `group` take `weight` tasks per turn under fair
+    /// dispatch, instead of 1.
+    pub fn with_group_weight(mut self, group: impl Into<String>, weight: usize) -> Self {
+        self.group_weights.insert(group.into(), weight);
+        self
+    }
+
+    /// Run at most `limit` runners at once. Dispatched runners wait for a free slot in the order
+    /// they were dispatched. [`TimerEvent::TaskFired`] is still published at dispatch, before
+    /// the run has a slot.
+    pub fn with_max_concurrent_runs(mut self, limit: usize) -> Self {
+        self.max_concurrent_runs = Some(limit.max(1));
+        self
+    }
+
+    /// Dispatch at most `size` of the tasks arriving together at once, e.g. so a slot of
+    /// thousands of tasks doesn't hold up the tick that reaches it. The rest are deferred, in
+    /// order, and the run loop dispatches them in further batches between ticks, yielding to
+    /// the executor after each, so they still fire well within the second. Deferred tasks go
+    /// before the tasks of later ticks and count as in flight.
+    ///
+    /// Without the run loop, each `tick_once` dispatches the next batch.
+    pub fn with_dispatch_batch_size(mut self, size: usize) -> Self {
+        self.dispatch_batch_size = Some(size.max(1));
+        self
+    }
+
+    /// Set the frequency of the tasks built with `Timer::task_builder` that don't set their own,
+    /// e.g. when most tasks of an application share a cadence. Without it such tasks fail to
+    /// build like any task without a frequency.
+    pub fn with_default_frequency(mut self, frequency: FrequencySeconds) -> Self {
+        self.default_frequency = Some(frequency);
+        self
+    }
+
+    /// Run the tasks of `Timer::at_instant` at their exact instant rather than on the whole
+    /// second tick after it. Such a task arrives on the tick before its instant and its run
+    /// sleeps for the rest, later alarms and other tasks follow the ticks as before.
+    pub fn with_precise_first_fire(mut self) -> Self {
+        self.precise_first_fire = true;
+        self
+    }
+
+    /// Set what the timer does when a runner panics, `PanicStrategy::Isolate` by default.
+    pub fn with_panic_strategy(mut self, panic_strategy: PanicStrategy) -> Self {
+        self.panic_strategy = panic_strategy;
+        self
+    }
+
+    /// Spawn the runs of the tasks onto `runtime`, e.g. a runtime dedicated to the tasks while
+    /// the timer is driven from another one. By default the runs are spawned onto the runtime
+    /// the timer is driven from.
+    pub fn with_runtime(mut self, runtime: Handle) -> Self {
+        self.runtime = Some(runtime);
+        self
+    }
+
+    /// Whether the timer runs the tasks that become due, the default. Without auto execution
+    /// the due tasks are collected for `Timer::drain_due` and no run events are published.
+    pub fn with_auto_execution(mut self, enabled: bool) -> Self {
+        self.auto_execution = enabled;
+        self
+    }
+
+    /// Run the timer on a clock shifted from the system clock by `seconds`, e.g. to see what
+    /// a schedule does at 3am without changing the system clock. Frequencies of tasks are
+    /// counted from the shifted clock when the tasks are added.
+    pub fn with_time_offset(mut self, seconds: i64) -> Self {
+        self.time_offset = seconds;
+        self
+    }
+
+    /// Read the wall clock from `now` instead of the system clock.
+    #[cfg(test)]
+    pub(crate) fn with_clock(mut self, now: fn() -> u64) -> Self {
+        self.now = now;
+        self
+    }
+
+    /// Build the timer and schedule the tasks written by `Timer::persist`, resolving their
+    /// runners through the registry. The tasks whose alarm passed while the process was down
+    /// are handled by `policy`, see `Timer::reschedule_overdue`.
+    #[cfg(feature = "serde")]
+    pub fn resume_from(
+        self,
+        path: impl AsRef<Path>,
+        registry: &RunnerRegistry,
+        policy: OverduePolicy,
+    ) -> Result<Timer, TaskError> {
+        let timer = self.build();
+        timer.load_schedule(path, registry)?;
+        timer.reschedule_overdue(policy);
+        Ok(timer)
+    }
+
+    pub fn build(self) -> Timer {
+        let sender = EventSender::new(self.event_channel_capacity);
+        let (commands, command_receiver) = mpsc::unbounded_channel();
+        let wheel = Arc::new(
+            self.wheel
+                .with_time_offset(self.time_offset)
+                .build()
+                .expect("the wheel configuration is validated"),
+        );
+        let events = Arc::new(
+            EventPublisher::new(sender, self.event_log_capacity).with_delivery(self.event_delivery),
+        );
+        Timer {
+            in_flight: Arc::new(InFlightRuns::new(
+                wheel.clone(),
+                events.clone(),
+                self.runtime,
+            )),
+            wheel,
+            now: self.now,
+            time_offset: self.time_offset,
+            events,
+            shutdown: Notify::new(),
+            task_added: Notify::new(),
+            idle: AtomicBool::new(false),
+            drain_on_shutdown: AtomicBool::new(false),
+            commands,
+            command_receiver: Mutex::new(Some(command_receiver)),
+            fair_dispatch: self.fair_dispatch_tag.map(|tag_key| FairDispatch {
+                tag_key,
+                weights: self.group_weights,
+            }),
+            dispatch_batch_size: self.dispatch_batch_size,
+            run_slots: self.max_concurrent_runs.map(RunSlots::new),
+            task_slots: DashMap::new(),
+            failures: Arc::default(),
+            due: (!self.auto_execution).then(|| Mutex::new(vec![])),
+            started_at: Mutex::new(None),
+            total_fired: AtomicU64::new(0),
+            total_failed: Arc::default(),
+            created_at: Instant::now(),
+            last_tick: AtomicU64::new(NEVER_TICKED),
+            panic_strategy: self.panic_strategy,
+            raised_panic: Arc::default(),
+            completed: self
+                .completed_retention
+                .map(|capacity| Arc::new(CompletedTasks::new(capacity))),
+            default_frequency: self.default_frequency,
+            fire_at: self.precise_first_fire.then(DashMap::new),
+            on_tick: Mutex::new(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashSet,
+        sync::atomic::{AtomicU64, Ordering},
+    };
+
+    use super::*;
+    use crate::task::OnceFailurePolicy;
+
+    struct CountingRunner {
+        count: Arc<AtomicU64>,
+    }
+
+    #[async_trait::async_trait]
+    impl TaskRunner for CountingRunner {
+        type Output = ();
+
+        async fn run(&self) -> Result<Self::Output, Box<dyn std::error::Error + Send + Sync>> {
+            self.count.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    fn once_task(task_id: TaskId, seconds: u64, count: &Arc<AtomicU64>) -> Task {
+        TaskBuilder::new(task_id)
+            .with_frequency_once_by_seconds(seconds)
+            .spwan_async(CountingRunner {
+                count: count.clone(),
+            })
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_cancel_running_aborts_long_run() {
+        struct Progress {
+            started: Arc<Notify>,
+            finished: Arc<AtomicBool>,
+            dropped: Arc<AtomicBool>,
+        }
+        struct SetOnDrop(Arc<AtomicBool>);
+        impl Drop for SetOnDrop {
+            fn drop(&mut self) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+
+        #[async_trait::async_trait]
+        impl TaskRunner for Progress {
+            type Output = ();
+
+            async fn run(&self) -> Result<Self::Output, Box<dyn std::error::Error + Send + Sync>> {
+                let _guard = SetOnDrop(self.dropped.clone());
+                self.started.notify_one();
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                self.finished.store(true, Ordering::SeqCst);
+                Ok(())
+            }
+        }
+
+        let timer = Timer::new();
+        let started = Arc::new(Notify::new());
+        let finished = Arc::new(AtomicBool::new(false));
+        let dropped = Arc::new(AtomicBool::new(false));
+        let task = TaskBuilder::new(1)
+            .with_frequency_repeated_by_seconds(1)
+            .spwan_async(Progress {
+                started: started.clone(),
+                finished: finished.clone(),
+                dropped: dropped.clone(),
+            })
+            .unwrap();
+        timer.add_task(task).unwrap();
+        let mut receiver = timer.event_receiver();
+
+        timer.tick_once();
+        tokio::time::timeout(Duration::from_secs(5), started.notified())
+            .await
+            .unwrap();
+        assert_eq!(timer.cancel_running(TaskId::new(1)), 1);
+        assert_eq!(timer.cancel_running(TaskId::new(1)), 0);
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !dropped.load(Ordering::SeqCst) {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .unwrap();
+        assert!(!finished.load(Ordering::SeqCst));
+        // Only the schedule of the run was kept
+        assert!(timer.contains(TaskId::new(1)));
+        assert!(matches!(
+            receiver.try_recv(),
+            Some(TimerEvent::TaskFired { .. })
+        ));
+        assert!(receiver.try_recv().is_none());
+
+        // Removing the task aborts its next run as well
+        timer.tick_once();
+        tokio::time::timeout(Duration::from_secs(5), started.notified())
+            .await
+            .unwrap();
+        assert!(timer.remove_task(TaskId::new(1)).is_some());
+        assert_eq!(timer.cancel_running(TaskId::new(1)), 0);
+        assert!(!finished.load(Ordering::SeqCst));
+    }
+
+    #[cfg(feature = "tokio-util")]
+    #[tokio::test]
+    async fn test_cancel_running_cancels_handed_off_work() {
+        let timer = Timer::new();
+        let (started, mut started_rx) = mpsc::unbounded_channel();
+        let (stopped, mut stopped_rx) = mpsc::unbounded_channel();
+        let task = TaskBuilder::new(1)
+            .with_frequency_repeated_by_seconds(1)
+            .spawn_fn(move |ctx: TaskContext| {
+                let (started, stopped) = (started.clone(), stopped.clone());
+                async move {
+                    // The worker outlives an aborted run unless it watches the token
+                    let worker = tokio::spawn(async move {
+                        tokio::select! {
+                            _ = ctx.cancellation.cancelled() => stopped.send(ctx.record_id).unwrap(),
+                            _ = tokio::time::sleep(Duration::from_secs(60)) => {}
+                        }
+                    });
+                    started.send(()).unwrap();
+                    let _ = worker.await;
+                }
+            })
+            .unwrap();
+        timer.add_task(task).unwrap();
+
+        timer.tick_once();
+        tokio::time::timeout(Duration::from_secs(5), started_rx.recv())
+            .await
+            .unwrap();
+        assert!(stopped_rx.try_recv().is_err());
+        assert_eq!(timer.cancel_running(TaskId::new(1)), 1);
+        let first = tokio::time::timeout(Duration::from_secs(5), stopped_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+
+        // Every run has a token of its own
+        timer.tick_once();
+        tokio::time::timeout(Duration::from_secs(5), started_rx.recv())
+            .await
+            .unwrap();
+        assert!(stopped_rx.try_recv().is_err());
+        assert!(timer.remove_task(TaskId::new(1)).is_some());
+        let second = tokio::time::timeout(Duration::from_secs(5), stopped_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(first, second);
+    }
+
+    #[tokio::test]
+    async fn test_stats_count_runs() {
+        struct FailingRunner;
+
+        #[async_trait::async_trait]
+        impl TaskRunner for FailingRunner {
+            type Output = ();
+
+            async fn run(&self) -> Result<Self::Output, Box<dyn std::error::Error + Send + Sync>> {
+                Err("unavailable".into())
+            }
+        }
+
+        let timer = Arc::new(Timer::new());
+        assert_eq!(timer.stats().uptime, Duration::ZERO);
+        let count = Arc::new(AtomicU64::new(0));
+        timer
+            .add_task(once_task(TaskId::new(1), 1, &count))
+            .unwrap();
+        timer
+            .add_task(once_task(TaskId::new(2), 1, &count))
+            .unwrap();
+        timer
+            .add_task(
+                TaskBuilder::new(3)
+                    .with_frequency_once_by_seconds(1)
+                    .spwan_async(FailingRunner)
+                    .unwrap(),
+            )
+            .unwrap();
+        timer
+            .add_task(once_task(TaskId::new(4), 60, &count))
+            .unwrap();
+        let mut receiver = timer.event_receiver();
+        let running = tokio::spawn({
+            let timer = timer.clone();
+            async move { timer.run().await }
+        });
+
+        let mut finished = 0;
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while finished < 3 {
+                if let Some(TimerEvent::TaskCompleted { .. } | TimerEvent::TaskFailed { .. }) =
+                    receiver.recv().await
+                {
+                    finished += 1;
+                }
+            }
+        })
+        .await
+        .unwrap();
+        timer.shutdown();
+        running.await.unwrap();
+
+        let stats = timer.stats();
+        assert_eq!(stats.total_fired, 3);
+        assert_eq!(stats.total_failed, 1);
+        assert_eq!(stats.pending, 1);
+        assert!(stats.uptime > Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_strict_delivery_keeps_up_with_slow_receiver() {
+        let timer = Arc::new(
+            TimerBuilder::new()
+                .with_event_channel_capacity(2)
+                .with_event_delivery(EventDelivery::Strict)
+                .build(),
+        );
+        let count = Arc::new(AtomicU64::new(0));
+        for task_id in 1..=10 {
+            timer
+                .add_task(once_task(TaskId::new(task_id), 1, &count))
+                .unwrap();
+        }
+        let mut receiver = timer.event_receiver();
+        let running = tokio::spawn({
+            let timer = timer.clone();
+            async move { timer.run().await }
+        });
+
+        let (mut fired, mut completed) = (HashSet::new(), 0);
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while fired.len() < 10 || completed < 10 {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                match receiver.recv().await {
+                    Some(TimerEvent::TaskFired { task_id, .. }) => {
+                        fired.insert(task_id);
+                    }
+                    Some(TimerEvent::TaskCompleted { .. }) => completed += 1,
+                    _ => {}
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(count.load(Ordering::SeqCst), 10);
+        timer.shutdown();
+        running.await.unwrap();
+    }
+
+    struct PanickingRunner;
+
+    #[async_trait::async_trait]
+    impl TaskRunner for PanickingRunner {
+        type Output = ();
+
+        async fn run(&self) -> Result<Self::Output, Box<dyn std::error::Error + Send + Sync>> {
+            panic!("runner exploded")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_frequency_applies_without_explicit_frequency() {
+        let timer = TimerBuilder::new()
+            .with_default_frequency(FrequencySeconds::Repeated(10))
+            .build();
+        let count = Arc::new(AtomicU64::new(0));
+        timer
+            .add_task(
+                timer
+                    .task_builder(1)
+                    .spwan_async(CountingRunner {
+                        count: count.clone(),
+                    })
+                    .unwrap(),
+            )
+            .unwrap();
+        timer
+            .add_task(
+                timer
+                    .task_builder(2)
+                    .with_frequency_once_by_seconds(3)
+                    .spwan_async(CountingRunner {
+                        count: count.clone(),
+                    })
+                    .unwrap(),
+            )
+            .unwrap();
+        assert_eq!(
+            timer
+                .task_location(TaskId::new(1))
+                .unwrap()
+                .ticks_until_fire,
+            10
+        );
+        assert_eq!(
+            timer
+                .task_location(TaskId::new(2))
+                .unwrap()
+                .ticks_until_fire,
+            3
+        );
+
+        let first = timer.next_fire_time(TaskId::new(1)).unwrap();
+        for _ in 0..10 {
+            timer.tick_once();
+        }
+        assert_eq!(timer.next_fire_time(TaskId::new(1)), Some(first + 10));
+        assert!(!timer.contains(TaskId::new(2)));
+
+        assert!(matches!(
+            Timer::new()
+                .task_builder(3)
+                .spwan_async(CountingRunner { count }),
+            Err(TaskError::InvalidFrequency { .. })
+        ));
+    }
+
+    struct FailingOnceRunner {
+        count: Arc<AtomicU64>,
+    }
+
+    #[async_trait::async_trait]
+    impl TaskRunner for FailingOnceRunner {
+        type Output = ();
+
+        async fn run(&self) -> Result<Self::Output, Box<dyn std::error::Error + Send + Sync>> {
+            match self.count.fetch_add(1, Ordering::SeqCst) {
+                0 => Err("first run fails".into()),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_once_task_rearms_once() {
+        let timer = Arc::new(Timer::new());
+        let count = Arc::new(AtomicU64::new(0));
+        timer
+            .add_task(
+                TaskBuilder::new(1)
+                    .with_frequency_once_by_seconds(1)
+                    .with_once_failure_policy(OnceFailurePolicy::Rearm(Duration::from_secs(1)))
+                    .spwan_async(FailingOnceRunner {
+                        count: count.clone(),
+                    })
+                    .unwrap(),
+            )
+            .unwrap();
+        let mut receiver = timer.event_receiver();
+        let running = tokio::spawn({
+            let timer = timer.clone();
+            async move { timer.run().await }
+        });
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !matches!(
+                receiver.recv().await,
+                Some(TimerEvent::TaskCompleted { .. })
+            ) {}
+        })
+        .await
+        .unwrap();
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+        assert!(!timer.contains(TaskId::new(1)));
+        timer.shutdown();
+        running.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_group_aborts_on_failure() {
+        let timer = Timer::new();
+        let count = Arc::new(AtomicU64::new(0));
+        let first = TaskBuilder::new(1)
+            .with_frequency_once_by_seconds(1)
+            .spwan_async(FailingOnceRunner {
+                count: count.clone(),
+            })
+            .unwrap();
+        let group = timer
+            .add_group(vec![
+                first,
+                once_task(TaskId::new(2), 5, &count),
+                once_task(TaskId::new(3), 10, &count),
+            ])
+            .unwrap()
+            .abort_on_failure();
+        let mut receiver = timer.event_receiver();
+
+        timer.fast_forward(1);
+        let aborted = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Some(event @ TimerEvent::GroupAborted { .. }) = receiver.recv().await {
+                    break event;
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            aborted,
+            TimerEvent::GroupAborted {
+                group_id: group.id(),
+                task_id: TaskId::new(1),
+                cancelled: vec![TaskId::new(2), TaskId::new(3)],
+            }
+        );
+        assert!(group.is_empty());
+        assert!(timer.is_empty());
+        timer.fast_forward(10);
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_task_handle_reschedules_and_cancels() {
+        let timer = Timer::new();
+        let count = Arc::new(AtomicU64::new(0));
+        let handle = timer
+            .schedule(
+                TaskBuilder::new(1)
+                    .with_frequency_repeated_by_seconds(5)
+                    .spwan_async(CountingRunner {
+                        count: count.clone(),
+                    })
+                    .unwrap(),
+            )
+            .unwrap();
+        assert_eq!(handle.id(), TaskId::new(1));
+        let runs = |expected| {
+            let count = count.clone();
+            async move {
+                tokio::time::timeout(Duration::from_secs(5), async {
+                    while count.load(Ordering::SeqCst) < expected {
+                        tokio::task::yield_now().await;
+                    }
+                })
+                .await
+                .unwrap()
+            }
+        };
+
+        timer.fast_forward(5);
+        runs(1).await;
+        handle.reschedule(FrequencySeconds::Repeated(2)).unwrap();
+        timer.fast_forward(2);
+        runs(2).await;
+
+        assert!(handle.cancel().is_some());
+        assert!(!handle.is_scheduled());
+        assert!(handle.cancel().is_none());
+        assert!(matches!(
+            handle.reschedule(FrequencySeconds::Repeated(2)),
+            Err(TaskError::TaskNotFound(_))
+        ));
+        timer.fast_forward(10);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_auto_cancel_expires_repeated_task() {
+        let timer = Timer::new();
+        let count = Arc::new(AtomicU64::new(0));
+        timer
+            .add_task(
+                TaskBuilder::new(1)
+                    .with_frequency_repeated_by_seconds(1)
+                    .with_auto_cancel_after(Duration::from_secs(5))
+                    .spwan_async(CountingRunner {
+                        count: count.clone(),
+                    })
+                    .unwrap(),
+            )
+            .unwrap();
+        let mut receiver = timer.event_receiver();
+
+        timer.fast_forward(10);
+        let mut fired = 0;
+        let expired = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                match receiver.recv().await {
+                    Some(TimerEvent::TaskFired { .. }) => fired += 1,
+                    Some(event @ TimerEvent::TaskExpired { .. }) => break event,
+                    _ => {}
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            expired,
+            TimerEvent::TaskExpired {
+                task_id: TaskId::new(1)
+            }
+        );
+        assert_eq!(fired, 5);
+        assert!(!timer.contains(TaskId::new(1)));
+
+        let result = timer.add_task(
+            TaskBuilder::new(2)
+                .with_frequency_once_by_seconds(10)
+                .with_auto_cancel_after(Duration::from_secs(5))
+                .spwan_async(CountingRunner { count })
+                .unwrap(),
+        );
+        assert!(
+            matches!(result, Err(TaskError::TaskExpired(task_id)) if task_id == TaskId::new(2))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_completed_tasks_are_retained() {
+        async fn completions(receiver: &mut EventReceiver, count: usize) {
+            let mut completed = 0;
+            tokio::time::timeout(Duration::from_secs(5), async {
+                while completed < count {
+                    if let Some(TimerEvent::TaskCompleted { .. }) = receiver.recv().await {
+                        completed += 1;
+                    }
+                }
+            })
+            .await
+            .unwrap()
+        }
+
+        let timer = TimerBuilder::new().with_completed_retention(2).build();
+        timer
+            .add_task(
+                TaskBuilder::new(1)
+                    .with_name("report")
+                    .with_frequency_once_by_seconds(1)
+                    .with_first_record_id(500)
+                    .spawn_fn(|_ctx: TaskContext| async {})
+                    .unwrap(),
+            )
+            .unwrap();
+        timer
+            .add_task(
+                TaskBuilder::new(2)
+                    .with_frequency_repeated_by_seconds(1)
+                    .spawn_fn(|_ctx: TaskContext| async {})
+                    .unwrap(),
+            )
+            .unwrap();
+        let mut receiver = timer.event_receiver();
+        timer.fast_forward(1);
+        completions(&mut receiver, 2).await;
+        assert_eq!(
+            timer.completed_tasks(),
+            vec![CompletedTask {
+                task_id: TaskId::new(1),
+                name: Some("report".to_string()),
+                last_record_id: Some(500),
+                outcome: TaskOutcome::Completed,
+            }]
+        );
+
+        // The oldest are dropped beyond the retention
+        let count = Arc::new(AtomicU64::new(0));
+        for task_id in [3, 4] {
+            timer
+                .add_task(once_task(TaskId::new(task_id), 1, &count))
+                .unwrap();
+        }
+        timer.fast_forward(1);
+        completions(&mut receiver, 3).await;
+        let completed: Vec<TaskId> = timer
+            .completed_tasks()
+            .iter()
+            .map(|task| task.task_id)
+            .collect();
+        assert_eq!(completed, [3, 4].map(TaskId::new));
+        assert!(timer.completed_tasks()[0].last_record_id.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_run_slots_are_handed_out_in_request_order() {
+        let slots = RunSlots::new(1);
+        let first = slots.request().wait().await;
+        let second = slots.request();
+        let dropped = slots.request();
+        let third = slots.request();
+        // A waiter dropped before its turn doesn't keep the slot
+        drop(dropped);
+
+        let order = Arc::new(std::sync::Mutex::new(vec![]));
+        let waiting = [(3, third), (2, second)].map(|(n, request)| {
+            let order = order.clone();
+            tokio::spawn(async move {
+                let _slot = request.wait().await;
+                order.lock().unwrap().push(n);
+            })
+        });
+        tokio::task::yield_now().await;
+        drop(first);
+        for handle in waiting {
+            handle.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), [2, 3]);
+        assert_eq!(slots.state.lock().unwrap().taken, 0);
+    }
+
+    #[tokio::test]
+    async fn test_max_in_flight_per_task() {
+        #[derive(Default)]
+        struct Overlapping {
+            running: AtomicU64,
+            most_running: AtomicU64,
+            finished: AtomicU64,
+        }
+
+        #[async_trait::async_trait]
+        impl TaskRunner for Overlapping {
+            type Output = ();
+
+            async fn run(&self) -> Result<Self::Output, Box<dyn std::error::Error + Send + Sync>> {
+                let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+                self.most_running.fetch_max(running, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                self.running.fetch_sub(1, Ordering::SeqCst);
+                self.finished.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        }
+
+        let timer = Timer::new();
+        let runner = Arc::new(Overlapping::default());
+        timer
+            .add_task(
+                TaskBuilder::new(1)
+                    .with_frequency_repeated_by_seconds(1)
+                    .with_max_in_flight(2)
+                    .spawn_async_shared(runner.clone())
+                    .unwrap(),
+            )
+            .unwrap();
+        // Six runs dispatched at once, far faster than they finish
+        for _ in 0..6 {
+            timer.tick_once();
+        }
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while runner.finished.load(Ordering::SeqCst) < 6 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(runner.most_running.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_poisoned_runner_fails_cleanly_until_descheduled() {
+        struct Poisoning(std::sync::Mutex<u64>);
+
+        #[async_trait::async_trait]
+        impl TaskRunner for Poisoning {
+            type Output = ();
+
+            async fn run(&self) -> Result<Self::Output, Box<dyn std::error::Error + Send + Sync>> {
+                let mut runs = self.0.lock().unwrap();
+                *runs += 1;
+                // Panicking with the guard held poisons the mutex for every later run
+                panic!("state corrupted")
+            }
+        }
+
+        let timer = Timer::new();
+        timer
+            .add_task(
+                TaskBuilder::new(1)
+                    .with_frequency_repeated_by_seconds(1)
+                    .with_max_consecutive_failures(3)
+                    .spwan_async(Poisoning(std::sync::Mutex::new(0)))
+                    .unwrap(),
+            )
+            .unwrap();
+        let mut receiver = timer.event_receiver();
+
+        let mut failures = Vec::new();
+        let descheduled = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                timer.tick_once();
+                while let Some(event) = receiver.try_recv() {
+                    match event {
+                        TimerEvent::TaskFailed { error, .. } => failures.push(error),
+                        event @ TimerEvent::TaskDescheduled { .. } => return event,
+                        _ => {}
+                    }
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            descheduled,
+            TimerEvent::TaskDescheduled {
+                task_id: TaskId::new(1),
+                failures: 3
+            }
+        );
+        assert_eq!(failures[0], "runner panicked: state corrupted");
+        assert!(
+            failures[1..]
+                .iter()
+                .all(|e| e.starts_with("runner panicked: ") && e.contains("PoisonError"))
+        );
+        assert!(timer.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_isolated_panic_is_a_failure() {
+        let timer = Arc::new(Timer::new());
+        timer
+            .add_task(
+                TaskBuilder::new(1)
+                    .with_frequency_repeated_by_seconds(1)
+                    .spwan_async(PanickingRunner)
+                    .unwrap(),
+            )
+            .unwrap();
+        let mut receiver = timer.event_receiver();
+        let running = tokio::spawn({
+            let timer = timer.clone();
+            async move { timer.run().await }
+        });
+
+        let mut failures = Vec::new();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while failures.len() < 2 {
+                if let Some(TimerEvent::TaskFailed { error, .. }) = receiver.recv().await {
+                    failures.push(error);
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert!(
+            failures
+                .iter()
+                .all(|e| e == "runner panicked: runner exploded")
+        );
+        assert!(!running.is_finished());
+        timer.shutdown();
+        running.await.unwrap();
+        assert!(timer.stats().total_failed >= 2);
+    }
+
+    #[tokio::test]
+    async fn test_abort_panic_stops_the_timer() {
+        let timer = Arc::new(
+            TimerBuilder::new()
+                .with_panic_strategy(PanicStrategy::Abort)
+                .build(),
+        );
+        timer
+            .add_task(
+                TaskBuilder::new(1)
+                    .with_frequency_once_by_seconds(1)
+                    .spwan_async(PanickingRunner)
+                    .unwrap(),
+            )
+            .unwrap();
+        let running = tokio::spawn({
+            let timer = timer.clone();
+            async move { timer.run().await }
+        });
+
+        let err = tokio::time::timeout(Duration::from_secs(5), running)
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert!(err.is_panic());
+        let payload = err.into_panic();
+        assert_eq!(panic_message(&*payload), "runner exploded");
+    }
+
+    #[tokio::test]
+    async fn test_stats_count_cascades() {
+        let timer = Timer::new();
+        timer.fast_forward(59);
+        assert_eq!(timer.stats().minute_cascades, 0);
+        timer.fast_forward(1);
+        assert_eq!(timer.stats().minute_cascades, 1);
+        timer.fast_forward(4 * 60 + 30);
+        let stats = timer.stats();
+        assert_eq!(stats.minute_cascades, 5);
+        assert_eq!(stats.hour_cascades, 0);
+
+        timer.fast_forward(55 * 60);
+        let stats = timer.stats();
+        assert_eq!(stats.minute_cascades, 60);
+        assert_eq!(stats.hour_cascades, 1);
+    }
+
+    #[tokio::test]
+    async fn test_reset_metrics_starts_a_new_interval() {
+        let timer = Timer::new();
+        let count = Arc::new(AtomicU64::new(0));
+        let fire_batch = |first: u64, size: u64| {
+            for task_id in first..first + size {
+                timer
+                    .add_task(once_task(TaskId::new(task_id), 1, &count))
+                    .unwrap();
+            }
+            timer.fast_forward(60);
+        };
+        let wait_for_runs = |runs: u64| {
+            let count = count.clone();
+            async move {
+                while count.load(Ordering::SeqCst) < runs {
+                    tokio::task::yield_now().await;
+                }
+            }
+        };
+
+        fire_batch(1, 2);
+        wait_for_runs(2).await;
+        let before = timer.reset_metrics();
+        assert_eq!(before.total_fired, 2);
+        assert_eq!(before.minute_cascades, 1);
+        let reset = timer.stats();
+        assert_eq!((reset.total_fired, reset.minute_cascades), (0, 0));
+
+        fire_batch(3, 3);
+        wait_for_runs(5).await;
+        let after = timer.stats();
+        assert_eq!(after.total_fired, 3);
+        assert_eq!(after.total_failed, 0);
+        assert_eq!(after.minute_cascades, 1);
+    }
+
+    #[test]
+    fn test_runs_spawned_onto_given_runtime() {
+        struct ThreadName(Arc<Mutex<Option<String>>>);
+
+        #[async_trait::async_trait]
+        impl TaskRunner for ThreadName {
+            type Output = ();
+
+            async fn run(&self) -> Result<Self::Output, Box<dyn std::error::Error + Send + Sync>> {
+                *self.0.lock().unwrap() = std::thread::current().name().map(String::from);
+                Ok(())
+            }
+        }
+
+        let tasks_runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("tasks-runtime")
+            .enable_all()
+            .build()
+            .unwrap();
+        let timer = TimerBuilder::new()
+            .with_runtime(tasks_runtime.handle().clone())
+            .build();
+        let thread_name = Arc::new(Mutex::new(None));
+        timer
+            .add_task(
+                TaskBuilder::new(1)
+                    .with_frequency_once_by_seconds(1)
+                    .spwan_async(ThreadName(thread_name.clone()))
+                    .unwrap(),
+            )
+            .unwrap();
+
+        let driver = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        driver.block_on(async {
+            let mut receiver = timer.event_receiver();
+            timer.tick_once();
+            while !matches!(
+                receiver.recv().await,
+                Some(TimerEvent::TaskCompleted { .. })
+            ) {}
+        });
+        assert_eq!(
+            thread_name.lock().unwrap().as_deref(),
+            Some("tasks-runtime")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_health_follows_run_loop() {
+        let timer = Arc::new(Timer::new());
+        assert!(!timer.is_healthy(Duration::from_secs(60)));
+
+        // Parked on an empty wheel
+        let running = tokio::spawn({
+            let timer = timer.clone();
+            async move { timer.run().await }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(timer.is_healthy(Duration::from_millis(1)));
+
+        let count = Arc::new(AtomicU64::new(0));
+        let task = TaskBuilder::new(1)
+            .with_frequency_repeated_by_seconds(1)
+            .spwan_async(CountingRunner {
+                count: count.clone(),
+            })
+            .unwrap();
+        timer.add_task(task).unwrap();
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(count.load(Ordering::SeqCst) >= 1);
+        assert!(timer.is_healthy(Duration::from_millis(1100)));
+
+        timer.shutdown();
+        running.await.unwrap();
+        tokio::time::sleep(Duration::from_millis(1200)).await;
+        assert!(!timer.is_healthy(Duration::from_millis(1100)));
+        assert!(timer.is_healthy(Duration::from_secs(60)));
+    }
+
+    #[tokio::test]
+    async fn test_health_after_parked_loop_is_dropped() {
+        let timer = Arc::new(Timer::new());
+        let running = tokio::spawn({
+            let timer = timer.clone();
+            async move { timer.run().await }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(timer.is_healthy(Duration::from_millis(1)));
+
+        // Aborting the task drops the loop without it reaching its stop
+        running.abort();
+        assert!(running.await.unwrap_err().is_cancelled());
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!timer.is_healthy(Duration::from_millis(100)));
+        assert!(timer.is_healthy(Duration::from_secs(60)));
+    }
+
+    #[tokio::test]
+    async fn test_replaced_runner_runs_next() {
+        let timer = Timer::new();
+        let (before, after) = (Arc::new(AtomicU64::new(0)), Arc::new(AtomicU64::new(0)));
+        let task = TaskBuilder::new(1)
+            .with_frequency_repeated_by_seconds(1)
+            .spwan_async(CountingRunner {
+                count: before.clone(),
+            })
+            .unwrap();
+        timer.add_task(task).unwrap();
+        let mut receiver = timer.event_receiver();
+        let completed = |receiver: &mut EventReceiver| {
+            matches!(receiver.try_recv(), Some(TimerEvent::TaskCompleted { .. }))
+        };
+
+        timer.tick_once();
+        let alarm = timer.next_fire_time(TaskId::new(1));
+        timer
+            .replace_runner(
+                TaskId::new(1),
+                CountingRunner {
+                    count: after.clone(),
+                },
+            )
+            .unwrap();
+        assert_eq!(timer.next_fire_time(TaskId::new(1)), alarm);
+        for _ in 0..2 {
+            timer.tick_once();
+        }
+        let mut runs = 0;
+        while runs < 3 {
+            match receiver.recv().await {
+                Some(TimerEvent::TaskCompleted { .. }) => runs += 1,
+                Some(_) => {}
+                None => break,
+            }
+        }
+        assert!(!completed(&mut receiver));
+        assert_eq!(before.load(Ordering::SeqCst), 1);
+        assert_eq!(after.load(Ordering::SeqCst), 2);
+
+        let result = timer.replace_runner(
+            TaskId::new(2),
+            CountingRunner {
+                count: after.clone(),
+            },
+        );
+        assert!(matches!(result, Err(TaskError::TaskNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_idle_after_last_run() {
+        let timer = Timer::new();
+        let count = Arc::new(AtomicU64::new(0));
+        let task = TaskBuilder::new(1)
+            .with_frequency_count_down_by_seconds(1, 1)
+            .spwan_async(CountingRunner {
+                count: count.clone(),
+            })
+            .unwrap();
+        timer.add_task(task).unwrap();
+        timer
+            .add_task(once_task(TaskId::new(2), 60, &count))
+            .unwrap();
+        let mut receiver = timer.event_receiver();
+
+        // The other task is still scheduled
+        timer.tick_once();
+        let mut events = vec![];
+        while let Ok(Some(event)) =
+            tokio::time::timeout(Duration::from_millis(200), receiver.recv()).await
+        {
+            events.push(event);
+        }
+        assert!(matches!(
+            events.as_slice(),
+            [
+                TimerEvent::TaskFired { .. },
+                TimerEvent::TaskCompleted { .. }
+            ]
+        ));
+
+        assert!(timer.remove_task(TaskId::new(2)).is_some());
+        assert_eq!(receiver.recv().await, Some(TimerEvent::Idle));
+
+        // A batch runs to completion
+        timer
+            .add_task(once_task(TaskId::new(3), 1, &count))
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(5), timer.run_until_idle())
+            .await
+            .unwrap();
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+        let events: Vec<TimerEvent> = std::iter::from_fn(|| receiver.try_recv()).collect();
+        assert!(matches!(
+            events.as_slice(),
+            [
+                TimerEvent::TaskFired { .. },
+                TimerEvent::TaskCompleted { .. },
+                TimerEvent::Idle,
+                TimerEvent::StopTimer
+            ]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_tick_once_fires_arrived_task() {
+        let timer = Timer::new();
+        let count = Arc::new(AtomicU64::new(0));
+        timer
+            .add_task(once_task(TaskId::new(1), 2, &count))
+            .unwrap();
+        let mut receiver = timer.event_receiver();
+
+        timer.tick_once();
+        assert!(receiver.is_empty());
+
+        timer.tick_once();
+        assert_eq!(
+            receiver.recv().await.unwrap(),
+            TimerEvent::TaskFired {
+                task_id: TaskId::new(1),
+                name: None
+            }
+        );
+        assert_eq!(
+            receiver.recv().await.unwrap(),
+            TimerEvent::TaskCompleted {
+                task_id: TaskId::new(1),
+                name: None
+            }
+        );
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        // A once task is not rescheduled
+        assert!(timer.remove_task(TaskId::new(1)).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_recent_events_retains_last_capacity() {
+        let timer = TimerBuilder::new().with_event_log_capacity(3).build();
+        let count = Arc::new(AtomicU64::new(0));
+        for task_id in 0..5 {
+            timer
+                .add_task(once_task(task_id.into(), 1, &count))
+                .unwrap();
+        }
+        let mut receiver = timer.event_receiver();
+
+        timer.tick_once();
+        let mut received = vec![];
+        for _ in 0..11 {
+            received.push(receiver.recv().await.unwrap());
+        }
+        assert_eq!(received[10], TimerEvent::Idle);
+
+        // Only the last three of the ten fired/completed events and the idle event are retained
+        assert_eq!(timer.recent_events(), received[8..].to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_run_stops_on_shutdown() {
+        let timer = Arc::new(Timer::new());
+        let mut receiver = timer.event_receiver();
+        let handle = tokio::spawn({
+            let timer = timer.clone();
+            async move { timer.run().await }
+        });
+
+        timer.shutdown();
+        handle.await.unwrap();
+        assert_eq!(receiver.recv().await.unwrap(), TimerEvent::StopTimer);
+    }
+
+    #[tokio::test]
+    async fn test_drain_events_after_shutdown() {
+        let timer = Arc::new(TimerBuilder::new().with_event_log_capacity(8).build());
+        let count = Arc::new(AtomicU64::new(0));
+        timer
+            .add_task(once_task(TaskId::new(1), 1, &count))
+            .unwrap();
+        let mut receiver = timer.event_receiver();
+        let running = tokio::spawn({
+            let timer = timer.clone();
+            async move { timer.run().await }
+        });
+        // Watch the log rather than the receiver, to leave the events buffered
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !timer.recent_events().contains(&TimerEvent::Idle) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        timer.shutdown();
+        running.await.unwrap();
+
+        let events = receiver.drain_events();
+        assert_eq!(
+            events,
+            vec![
+                TimerEvent::TaskFired {
+                    task_id: TaskId::new(1),
+                    name: None
+                },
+                TimerEvent::TaskCompleted {
+                    task_id: TaskId::new(1),
+                    name: None
+                },
+                TimerEvent::Idle,
+                TimerEvent::StopTimer,
+            ]
+        );
+        assert!(receiver.drain_events().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tie_break_orders_equal_priorities() {
+        for (tie_break, expected) in [(TieBreak::Fifo, [3, 1, 2]), (TieBreak::TaskId, [1, 2, 3])] {
+            let timer = TimerBuilder::new()
+                .with_auto_execution(false)
+                .with_tie_break(tie_break)
+                .build();
+            let count = Arc::new(AtomicU64::new(0));
+            let tasks = [3, 1, 2].map(|task_id| once_task(TaskId::new(task_id), 2, &count));
+            for result in timer.add_tasks(tasks.into()) {
+                result.unwrap();
+            }
+            for _ in 0..2 {
+                timer.tick_once();
+            }
+            let order: Vec<TaskId> = timer.drain_due().iter().map(|task| task.task_id).collect();
+            assert_eq!(order, expected.map(TaskId::new), "{tie_break:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drain_runs_overdue_tasks() {
+        // The wall clock is ahead of the wheels, as if ticks were delayed
+        fn clock_ahead() -> u64 {
+            crate::utils::timestamp() + 5
+        }
+        let timer = Arc::new(TimerBuilder::new().with_clock(clock_ahead).build());
+        let count = Arc::new(AtomicU64::new(0));
+        timer
+            .add_task(once_task(TaskId::new(1), 3, &count))
+            .unwrap();
+        timer
+            .add_task(once_task(TaskId::new(2), 30, &count))
+            .unwrap();
+
+        let running = tokio::spawn({
+            let timer = timer.clone();
+            async move { timer.run().await }
+        });
+        timer.shutdown_drain();
+        running.await.unwrap();
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert!(!timer.contains(TaskId::new(1)));
+        assert!(timer.contains(TaskId::new(2)));
+    }
+
+    #[tokio::test]
+    async fn test_idle_while_empty() {
+        let timer = Arc::new(Timer::new());
+        let mut receiver = timer.event_receiver();
+        let running = tokio::spawn({
+            let timer = timer.clone();
+            async move { timer.run().await }
+        });
+
+        // The wheels stand still while there is nothing to fire
+        let start = timer.wheel.current_timestamp();
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        assert_eq!(timer.wheel.current_timestamp(), start);
+        assert_eq!(timer.wheel_positions().second, 0);
+
+        // Once a task is added, the wheels catch up and the task fires on time
+        let count = Arc::new(AtomicU64::new(0));
+        timer
+            .add_task(once_task(TaskId::new(1), 2, &count))
+            .unwrap();
+        let alarm = timer.next_fire_time(TaskId::new(1)).unwrap();
+        assert!(alarm > timestamp());
+        let fired_at = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Some(TimerEvent::TaskFired { .. }) = receiver.recv().await {
+                    return timer.wheel.current_timestamp();
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(fired_at, alarm);
+        assert!(timestamp().abs_diff(alarm) <= 1);
+
+        timer.shutdown();
+        running.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_task_added_through_handle_while_running() {
+        let timer = Arc::new(Timer::new());
+        let mut receiver = timer.event_receiver();
+        let running = tokio::spawn({
+            let timer = timer.clone();
+            async move { timer.run().await }
+        });
+
+        // Add the task from another task, through the run loop
+        let count = Arc::new(AtomicU64::new(0));
+        let handle = timer.handle();
+        let task = once_task(TaskId::new(7), 1, &count);
+        tokio::spawn(async move { handle.add_task(task).await })
+            .await
+            .unwrap()
+            .unwrap();
+
+        let completed = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Some(TimerEvent::TaskCompleted { task_id, .. }) = receiver.recv().await {
+                    return task_id;
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(completed, TaskId::new(7));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        let handle = timer.handle();
+        assert!(matches!(
+            handle
+                .reschedule(TaskId::new(7), FrequencySeconds::Repeated(5))
+                .await,
+            Err(TaskError::TaskNotFound(_))
+        ));
+        timer.shutdown();
+        running.await.unwrap();
+
+        // Commands queued while stopped are applied by the next run
+        let queued = tokio::spawn({
+            let handle = timer.handle();
+            async move { handle.remove_task(TaskId::new(8)).await }
+        });
+        tokio::spawn({
+            let timer = timer.clone();
+            async move { timer.run().await }
+        });
+        assert!(queued.await.unwrap().unwrap().is_none());
+        timer.shutdown();
+    }
+
+    #[tokio::test]
+    async fn test_fast_forward_fires_repeated_task_per_alarm() {
+        let timer = Timer::new();
+        let count = Arc::new(AtomicU64::new(0));
+        let task = TaskBuilder::new(1)
+            .with_frequency_repeated_by_seconds(5)
+            .spwan_async(CountingRunner {
+                count: count.clone(),
+            })
+            .unwrap();
+        timer.add_task(task).unwrap();
+        let mut receiver = timer.event_receiver();
+
+        timer.fast_forward(60);
+        let mut completed = 0;
+        while completed < 12 {
+            if let Some(TimerEvent::TaskCompleted { .. }) = receiver.recv().await {
+                completed += 1;
+            }
+        }
+        assert_eq!(count.load(Ordering::SeqCst), 12);
+        assert!(timer.contains(TaskId::new(1)));
+    }
+
+    #[tokio::test]
+    async fn test_fast_forward_millisecond_wheel() {
+        let wheel = MulitWheelBuilder::new()
+            .with_resolution(Duration::from_millis(1))
+            .with_levels(&[1000, 60, 60]);
+        let timer = TimerBuilder::new().with_wheel(wheel).unwrap().build();
+        let count = Arc::new(AtomicU64::new(0));
+        let task = TaskBuilder::new(1)
+            .with_frequency_repeated_by_seconds(1)
+            .spwan_async(CountingRunner {
+                count: count.clone(),
+            })
+            .unwrap();
+        timer.add_task(task).unwrap();
+        let mut receiver = timer.event_receiver();
+
+        // Three thousand ticks, each alarm fires on the tick its second starts on
+        let start = timer.wheel.current_timestamp();
+        timer.fast_forward(3);
+        assert_eq!(timer.wheel.ticks(), 3000);
+        assert_eq!(timer.wheel.current_timestamp(), start + 3);
+        let mut completed = 0;
+        while completed < 3 {
+            if let Some(TimerEvent::TaskCompleted { .. }) = receiver.recv().await {
+                completed += 1;
+            }
+        }
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+        assert_eq!(timer.next_fire_time(TaskId::new(1)), Some(start + 4));
+    }
+
+    #[tokio::test]
+    async fn test_remove_task_info_drops_deferred_run() {
+        let timer = TimerBuilder::new().with_dispatch_batch_size(1).build();
+        let count = Arc::new(AtomicU64::new(0));
+        timer
+            .add_task(once_task(TaskId::new(1), 1, &count))
+            .unwrap();
+        timer
+            .add_task(once_task(TaskId::new(2), 5, &count))
+            .unwrap();
+        let info = timer.remove_task_info(TaskId::new(2)).unwrap();
+        assert_eq!(info.task_id, TaskId::new(2));
+        assert!(!timer.contains(TaskId::new(2)));
+
+        timer
+            .add_task(once_task(TaskId::new(3), 1, &count))
+            .unwrap();
+        timer.tick_once();
+        assert!(timer.in_flight.has_deferred());
+        // The deferred once task is in no slot, its run is dropped all the same
+        for task_id in [1, 3] {
+            assert!(timer.remove_task_info(TaskId::new(task_id)).is_none());
+        }
+        assert!(!timer.in_flight.has_deferred());
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_batches_spread_a_large_slot() {
+        let timer = Arc::new(TimerBuilder::new().with_dispatch_batch_size(100).build());
+        let count = Arc::new(AtomicU64::new(0));
+        for task_id in 0..1000 {
+            timer
+                .add_task(once_task(TaskId::new(task_id), 1, &count))
+                .unwrap();
+        }
+
+        // The tick reaching the slot only dispatches the first batch
+        let mut receiver = timer.event_receiver();
+        timer.tick_once();
+        let mut fired = 0;
+        while let Some(event) = receiver.try_recv() {
+            if matches!(event, TimerEvent::TaskFired { .. }) {
+                fired += 1;
+            }
+        }
+        assert_eq!(fired, 100);
+        assert!(timer.is_empty());
+        assert!(!timer.in_flight.is_idle());
+        assert!(timer.remove_task(TaskId::new(999)).is_some());
+
+        // The run loop dispatches the rest right away rather than a batch per second
+        let started = Instant::now();
+        tokio::spawn({
+            let timer = timer.clone();
+            async move { timer.run().await }
+        });
+        tokio::time::timeout(Duration::from_millis(500), async {
+            while count.load(Ordering::SeqCst) < 999 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert!(started.elapsed() < Duration::from_millis(500));
+        assert!(!timer.in_flight.has_deferred());
+        timer.run_until_idle().await;
+        assert_eq!(count.load(Ordering::SeqCst), 999);
+        timer.shutdown();
+    }
+
+    #[tokio::test]
+    async fn test_tick_callback_runs_once_per_tick() {
+        static NOW: AtomicU64 = AtomicU64::new(0);
+        let timer = TimerBuilder::new()
+            .with_clock(|| NOW.load(Ordering::SeqCst))
+            .build();
+        NOW.store(timer.wheel.current_timestamp(), Ordering::SeqCst);
+        let seen = Arc::new(Mutex::new(vec![]));
+        timer.on_tick(Arc::new({
+            let seen = seen.clone();
+            move |ticks| seen.lock().unwrap().push(ticks)
+        }));
+
+        for _ in 0..3 {
+            NOW.fetch_add(1, Ordering::SeqCst);
+            timer.advance();
+        }
+        assert_eq!(*seen.lock().unwrap(), vec![1, 2, 3]);
+
+        // The wheels jump to the clock in one resync, reported once
+        NOW.fetch_add(RESYNC_THRESHOLD + 10, Ordering::SeqCst);
+        timer.advance();
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![1, 2, 3, 3 + RESYNC_THRESHOLD + 10]
+        );
+    }
+
+    static MOCK_NOW: AtomicU64 = AtomicU64::new(0);
+
+    fn mock_clock() -> u64 {
+        MOCK_NOW.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn test_once_by_timestamp_fires_on_exact_tick() {
+        let timer = TimerBuilder::new().with_clock(mock_clock).build();
+        MOCK_NOW.store(timer.wheel.current_timestamp(), Ordering::SeqCst);
+        let target = timer.wheel.current_timestamp() + 3;
+        let count = Arc::new(AtomicU64::new(0));
+        let builder = TaskBuilder::new(TaskId::new(1))
+            .with_frequency_once_by_timestamp_seconds(target)
+            .unwrap();
+
+        // Build the task only after the wall clock moved on to the next second
+        let now = timestamp();
+        while timestamp() == now {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let task = builder
+            .spwan_async(CountingRunner {
+                count: count.clone(),
+            })
+            .unwrap();
+        timer.add_task(task).unwrap();
+        assert_eq!(timer.next_fire_time(TaskId::new(1)), Some(target));
+
+        for _ in 0..2 {
+            MOCK_NOW.fetch_add(1, Ordering::SeqCst);
+            timer.advance();
+            assert!(timer.contains(TaskId::new(1)));
+        }
+        MOCK_NOW.fetch_add(1, Ordering::SeqCst);
+        timer.advance();
+        assert_eq!(timer.wheel.current_timestamp(), target);
+        assert!(!timer.contains(TaskId::new(1)));
+    }
+
+    #[tokio::test]
+    async fn test_drain_due_without_auto_execution() {
+        let timer = TimerBuilder::new().with_auto_execution(false).build();
+        let count = Arc::new(AtomicU64::new(0));
+        timer
+            .add_task(once_task(TaskId::new(1), 1, &count))
+            .unwrap();
+        let ticks = timer
+            .task_location(TaskId::new(1))
+            .unwrap()
+            .ticks_until_fire;
+        for _ in 0..ticks {
+            timer.tick_once();
+        }
+        assert!(!timer.contains(TaskId::new(1)));
+
+        let due = timer.drain_due();
+        assert_eq!(due.len(), 1);
+        tokio::task::yield_now().await;
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+        due[0].runner.run().await.unwrap();
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert!(timer.drain_due().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_drained_dependency_counts_once_marked_completed() {
+        let timer = TimerBuilder::new().with_auto_execution(false).build();
+        let count = Arc::new(AtomicU64::new(0));
+        timer
+            .add_task(once_task(TaskId::new(1), 1, &count))
+            .unwrap();
+        let dependent = TaskBuilder::new(2)
+            .with_frequency_once_by_seconds(1)
+            .with_depends_on(1)
+            .spwan_async(CountingRunner {
+                count: count.clone(),
+            })
+            .unwrap();
+        timer.add_task(dependent).unwrap();
+
+        timer.tick_once();
+        let due: Vec<TaskId> = timer.drain_due().iter().map(|task| task.task_id).collect();
+        assert_eq!(due, vec![TaskId::new(1)]);
+        timer.tick_once();
+        assert!(timer.drain_due().is_empty());
+
+        timer.mark_completed(TaskId::new(1));
+        timer.tick_once();
+        let due: Vec<TaskId> = timer.drain_due().iter().map(|task| task.task_id).collect();
+        assert_eq!(due, vec![TaskId::new(2)]);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[tokio::test]
+    async fn test_time_offset_shifts_calendar_alarms() {
+        // Monday 2024-01-01 02:59:00 UTC
+        let shifted_now = 1_704_077_940;
+        let timer = TimerBuilder::new()
+            .with_time_offset(shifted_now as i64 - timestamp() as i64)
+            .build();
+        let count = Arc::new(AtomicU64::new(0));
+        let task = TaskBuilder::new(TaskId::new(1))
+            .with_frequency(FrequencySeconds::Weekly {
+                weekday: chrono::Weekday::Mon,
+                hour: 3,
+                minute: 0,
+            })
+            .spwan_async(CountingRunner { count })
+            .unwrap();
+        timer.add_task(task).unwrap();
+
+        let current = timer.wheel.current_timestamp();
+        assert!((shifted_now..=shifted_now + 1).contains(&current));
+        assert_eq!(timer.next_fire_time(TaskId::new(1)), Some(shifted_now + 60));
+        let location = timer.task_location(TaskId::new(1)).unwrap();
+        assert_eq!(location.ticks_until_fire, shifted_now + 60 - current);
+    }
+
+    #[tokio::test]
+    async fn test_wheel_positions_advance() {
+        let timer = Timer::new();
+        let start = timer.wheel_positions();
+        for _ in 0..3 {
+            timer.tick_once();
+        }
+        let positions = timer.wheel_positions();
+        assert_eq!(positions.second, (start.second + 3) % 60);
+        if start.second < 57 {
+            assert_eq!(
+                (positions.minute, positions.hour),
+                (start.minute, start.hour)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_debug_reports_state_and_task_count() {
+        let timer = Arc::new(Timer::new());
+        let count = Arc::new(AtomicU64::new(0));
+        timer
+            .add_task(once_task(TaskId::new(1), 30, &count))
+            .unwrap();
+        timer
+            .add_task(once_task(TaskId::new(2), 60, &count))
+            .unwrap();
+
+        let debug = format!("{timer:?}");
+        assert!(debug.starts_with("Timer {"));
+        assert!(debug.contains("state: \"idle\""));
+        assert!(debug.contains("pending_tasks: 2"));
+
+        let running = tokio::spawn({
+            let timer = timer.clone();
+            async move { timer.run().await }
+        });
+        while !format!("{timer:?}").contains("state: \"running\"") {
+            tokio::task::yield_now().await;
+        }
+        timer.shutdown();
+        running.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dependent_task_waits_for_dependency() {
+        let timer = Timer::new();
+        let runs = Arc::new(std::sync::Mutex::new(vec![]));
+        let task = |builder: TaskBuilder| {
+            let runs = runs.clone();
+            builder
+                .spawn_fn(move |ctx: TaskContext| {
+                    let runs = runs.clone();
+                    async move { runs.lock().unwrap().push(ctx.task_id) }
+                })
+                .unwrap()
+        };
+        let (a, b) = (TaskId::new(1), TaskId::new(2));
+        timer
+            .add_task(task(
+                TaskBuilder::new(b)
+                    .with_frequency_repeated_by_seconds(1)
+                    .with_depends_on(a),
+            ))
+            .unwrap();
+        timer
+            .add_task(task(TaskBuilder::new(a).with_frequency_once_by_seconds(3)))
+            .unwrap();
+        let mut receiver = timer.event_receiver();
+
+        for _ in 0..3 {
+            timer.tick_once();
+        }
+        // Only the dependency fired, the dependent task was deferred
+        while !matches!(
+            receiver.recv().await,
+            Some(TimerEvent::TaskCompleted { .. })
+        ) {}
+        assert_eq!(*runs.lock().unwrap(), vec![a]);
+
+        timer.tick_once();
+        while !matches!(
+            receiver.recv().await,
+            Some(TimerEvent::TaskCompleted { .. })
+        ) {}
+        assert_eq!(*runs.lock().unwrap(), vec![a, b]);
+    }
+
+    #[tokio::test]
+    async fn test_late_task_publishes_deadline_missed() {
+        // The wall clock runs ten seconds behind the wheels
+        fn delayed_clock() -> u64 {
+            crate::utils::timestamp() + 10
+        }
+        let timer = TimerBuilder::new()
+            .with_event_log_capacity(16)
+            .with_clock(delayed_clock)
+            .build();
+        let count = Arc::new(AtomicU64::new(0));
+        let task = |task_id, max_lateness| {
+            TaskBuilder::new(task_id)
+                .with_frequency_once_by_seconds(2)
+                .with_max_lateness(Duration::from_secs(max_lateness))
+                .spwan_async(CountingRunner {
+                    count: count.clone(),
+                })
+                .unwrap()
+        };
+        timer.add_task(task(1, 3)).unwrap();
+        timer.add_task(task(2, 60)).unwrap();
+        timer
+            .add_task(once_task(TaskId::new(3), 2, &count))
+            .unwrap();
+        let scheduled = timer.next_fire_time(TaskId::new(1)).unwrap();
+
+        timer.tick_once();
+        timer.tick_once();
+        let missed: Vec<TimerEvent> = timer
+            .recent_events()
+            .into_iter()
+            .filter(|event| matches!(event, TimerEvent::DeadlineMissed { .. }))
+            .collect();
+        assert_eq!(missed.len(), 1);
+        let TimerEvent::DeadlineMissed {
+            task_id,
+            scheduled: missed_scheduled,
+            fired,
+            lateness,
+        } = missed[0]
+        else {
+            unreachable!()
+        };
+        assert_eq!(task_id, TaskId::new(1));
+        assert_eq!(missed_scheduled, scheduled);
+        assert_eq!(lateness, Duration::from_secs(fired - scheduled));
+        assert!((8..=9).contains(&lateness.as_secs()));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_fair_dispatch_interleaves_groups() {
+        let run_order = |timer: Timer| async move {
+            let order = Arc::new(std::sync::Mutex::new(vec![]));
+            // The noisy tenant schedules its tasks first
+            for (task_id, tenant) in (1..=6)
+                .map(|id| (id, "noisy"))
+                .chain((7..=9).map(|id| (id, "quiet")))
+            {
+                let task = TaskBuilder::new(task_id)
+                    .with_tags(HashMap::from([("tenant".to_string(), tenant.to_string())]))
+                    .with_frequency_once_by_seconds(1)
+                    .spawn_fn({
+                        let order = order.clone();
+                        move |_| {
+                            let order = order.clone();
+                            async move {
+                                order.lock().unwrap().push(tenant);
+                                tokio::task::yield_now().await;
+                            }
+                        }
+                    })
+                    .unwrap();
+                timer.add_task(task).unwrap();
+            }
+            let mut receiver = timer.event_receiver();
+            timer.tick_once();
+            let mut completed = 0;
+            while completed < 9 {
+                if let Some(TimerEvent::TaskCompleted { .. }) = receiver.recv().await {
+                    completed += 1;
+                }
+            }
+            order.lock().unwrap().clone()
+        };
+
+        let builder = TimerBuilder::new().with_max_concurrent_runs(1);
+        let order = run_order(builder.clone().build()).await;
+        assert_eq!(order[..6], ["noisy"; 6]);
+
+        let order = run_order(builder.clone().with_fair_dispatch("tenant").build()).await;
+        assert_eq!(
+            order,
+            [
+                "noisy", "quiet", "noisy", "quiet", "noisy", "quiet", "noisy", "noisy", "noisy"
+            ]
+        );
+
+        let order = run_order(
+            builder
+                .with_fair_dispatch("tenant")
+                .with_group_weight("noisy", 2)
+                .build(),
+        )
+        .await;
+        assert_eq!(
+            order,
+            [
+                "noisy", "noisy", "quiet", "noisy", "noisy", "quiet", "noisy", "noisy", "quiet"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_every_fires_repeatedly() {
+        let timer = Timer::new();
+        let runs = Arc::new(std::sync::Mutex::new(vec![]));
+        let task_id = timer
+            .every(Duration::from_secs(2), {
+                let runs = runs.clone();
+                move |ctx| {
+                    let runs = runs.clone();
+                    async move { runs.lock().unwrap().push((ctx.task_id, ctx.record_id)) }
+                }
+            })
+            .unwrap();
+        let mut receiver = timer.event_receiver();
+
+        for _ in 0..4 {
+            timer.tick_once();
+        }
+        let mut completed = 0;
+        while completed < 2 {
+            if let Some(TimerEvent::TaskCompleted { .. }) = receiver.recv().await {
+                completed += 1;
+            }
+        }
+        assert_eq!(*runs.lock().unwrap(), vec![(task_id, 1), (task_id, 2)]);
+        assert!(timer.contains(task_id));
+
+        assert!(
+            timer
+                .every(Duration::from_millis(500), |_| async {})
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_removed_task_continues_cadence() {
+        let timer = Timer::new();
+        let task = TaskBuilder::new(TaskId::new(12))
+            .with_frequency_repeated_by_seconds(5)
+            .spwan_async(CountingRunner {
+                count: Arc::new(AtomicU64::new(0)),
+            })
+            .unwrap();
+        timer.add_task(task).unwrap();
+
+        // Fire once, then remove the task two seconds into its next interval
+        let first = timer.next_fire_time(TaskId::new(12)).unwrap();
+        for _ in 0..timer
+            .task_location(TaskId::new(12))
+            .unwrap()
+            .ticks_until_fire
+            + 2
+        {
+            timer.tick_once();
+        }
+        let pending = timer.next_fire_time(TaskId::new(12)).unwrap();
+        assert_eq!(pending, first + 5);
+        let task = timer.remove_task(TaskId::new(12)).unwrap();
+        assert_eq!(task.alarm_timestamp, pending);
+        assert!(!timer.contains(TaskId::new(12)));
+
+        timer.add_task(task).unwrap();
+        assert_eq!(timer.next_fire_time(TaskId::new(12)), Some(pending));
+        let location = timer.task_location(TaskId::new(12)).unwrap();
+        assert_eq!(location.ticks_until_fire, 3);
+        for _ in 0..3 {
+            timer.tick_once();
+        }
+        assert_eq!(timer.next_fire_time(TaskId::new(12)), Some(pending + 5));
+    }
+
+    #[tokio::test]
+    async fn test_update_frequency_keeps_task_state() {
+        let timer = Timer::new();
+        let runs = Arc::new(std::sync::Mutex::new(vec![]));
+        let task = TaskBuilder::new(TaskId::new(9))
+            .with_name("report")
+            .with_tags(std::collections::HashMap::from([(
+                "team".to_string(),
+                "ops".to_string(),
+            )]))
+            .with_frequency_repeated_by_seconds(1)
+            .spawn_fn({
+                let runs = runs.clone();
+                move |ctx: TaskContext| {
+                    let runs = runs.clone();
+                    async move { runs.lock().unwrap().push(ctx.record_id) }
+                }
+            })
+            .unwrap();
+        timer.add_task(task).unwrap();
+        let mut receiver = timer.event_receiver();
+
+        timer.tick_once();
+        timer.tick_once();
+        let mut completed = 0;
+        while completed < 2 {
+            if let Some(TimerEvent::TaskCompleted { .. }) = receiver.recv().await {
+                completed += 1;
+            }
+        }
+
+        timer
+            .update_frequency(TaskId::new(9), FrequencySeconds::Repeated(3))
+            .unwrap();
+        assert_eq!(timer.find_tasks_by_tag("team", "ops"), vec![TaskId::new(9)]);
+        let location = timer.task_location(TaskId::new(9)).unwrap();
+        assert_eq!(location.ticks_until_fire, 3);
+
+        for _ in 0..3 {
+            timer.tick_once();
+        }
+        while !matches!(
+            receiver.recv().await,
+            Some(TimerEvent::TaskCompleted { .. })
+        ) {}
+        assert_eq!(*runs.lock().unwrap(), vec![1, 2, 3]);
+
+        assert!(matches!(
+            timer.update_frequency(TaskId::new(10), FrequencySeconds::Repeated(3)),
+            Err(TaskError::TaskNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_at_fires_once() {
+        let timer = Timer::new();
+        let count = Arc::new(AtomicU64::new(0));
+        let timestamp = crate::utils::timestamp() + 2;
+        let task_id = timer
+            .at(timestamp, {
+                let count = count.clone();
+                move |_| {
+                    let count = count.clone();
+                    async move {
+                        count.fetch_add(1, Ordering::SeqCst);
+                    }
+                }
+            })
+            .unwrap();
+        let mut receiver = timer.event_receiver();
+
+        while timer.contains(task_id) {
+            timer.tick_once();
+        }
+        while !matches!(
+            receiver.recv().await,
+            Some(TimerEvent::TaskCompleted { .. })
+        ) {}
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_at_instant_fires_once() {
+        let timer = Timer::new();
+        let count = Arc::new(AtomicU64::new(0));
+        let task_id = timer
+            .at_instant(
+                Instant::now() + Duration::from_secs(3),
+                CountingRunner {
+                    count: count.clone(),
+                },
+            )
+            .unwrap();
+        assert_eq!(timer.fire_tick(task_id), Some(3));
+        let mut receiver = timer.event_receiver();
+
+        while timer.contains(task_id) {
+            timer.tick_once();
+        }
+        assert_eq!(timer.wheel_positions().second, 3);
+        while !matches!(
+            receiver.recv().await,
+            Some(TimerEvent::TaskCompleted { .. })
+        ) {}
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        let past = timer.at_instant(Instant::now(), CountingRunner { count });
+        assert!(matches!(past, Err(TaskError::InvalidFrequency { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_precise_first_fire_runs_at_the_instant() {
+        struct RanAt(Arc<Mutex<Option<Instant>>>);
+
+        #[async_trait::async_trait]
+        impl TaskRunner for RanAt {
+            type Output = ();
+
+            async fn run(&self) -> Result<Self::Output, Box<dyn std::error::Error + Send + Sync>> {
+                *self.0.lock().unwrap() = Some(Instant::now());
+                Ok(())
+            }
+        }
+
+        let timer = Arc::new(TimerBuilder::new().with_precise_first_fire().build());
+        let ran_at = Arc::new(Mutex::new(None));
+        let start = Instant::now();
+        timer
+            .at_instant(start + Duration::from_millis(1500), RanAt(ran_at.clone()))
+            .unwrap();
+        let running = tokio::spawn({
+            let timer = timer.clone();
+            async move { timer.run().await }
+        });
+
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        timer.shutdown();
+        running.await.unwrap();
+        let elapsed = ran_at.lock().unwrap().unwrap() - start;
+        assert!(elapsed >= Duration::from_millis(1500));
+        assert!(
+            elapsed < Duration::from_millis(1750),
+            "ran after {elapsed:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_at_time_fires_once() {
+        let timer = Timer::new();
+        let count = Arc::new(AtomicU64::new(0));
+        let task_id = timer
+            .at_time(
+                SystemTime::now() + Duration::from_secs(5),
+                CountingRunner {
+                    count: count.clone(),
+                },
+            )
+            .unwrap();
+        let ticks_until_fire = timer.task_location(task_id).unwrap().ticks_until_fire;
+        assert!((5..=7).contains(&ticks_until_fire));
+
+        let mut receiver = timer.event_receiver();
+        for _ in 0..ticks_until_fire {
+            timer.tick_once();
+        }
+        assert_eq!(
+            receiver.recv().await.unwrap(),
+            TimerEvent::TaskFired {
+                task_id,
+                name: None
+            }
+        );
+        assert!(!timer.contains(task_id));
+
+        let past = SystemTime::now() - Duration::from_secs(1);
+        let result = timer.at_time(past, CountingRunner { count });
+        assert!(matches!(result, Err(TaskError::InvalidFrequency { .. })));
+    }
+
+    #[test]
+    fn test_len_and_contains() {
+        let timer = Timer::new();
+        let count = Arc::new(AtomicU64::new(0));
+        assert!(timer.is_empty());
+
+        timer
+            .add_task(once_task(TaskId::new(1), 5, &count))
+            .unwrap();
+        timer
+            .add_task(once_task(TaskId::new(2), 120, &count))
+            .unwrap();
+        assert_eq!(timer.len(), 2);
+        assert!(timer.contains(TaskId::new(1)));
+        let now = crate::utils::timestamp();
+        let next_fire_time = timer.next_fire_time(TaskId::new(2)).unwrap();
+        assert!((now + 119..=now + 120).contains(&next_fire_time));
+        assert_eq!(timer.next_fire_time(TaskId::new(3)), None);
+
+        timer.remove_task(TaskId::new(1));
+        assert_eq!(timer.len(), 1);
+        assert!(!timer.contains(TaskId::new(1)));
+        assert!(timer.contains(TaskId::new(2)));
+
+        timer.remove_task(TaskId::new(2));
+        assert!(timer.is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test]
+    async fn test_save_and_load_schedule() {
+        let path =
+            std::env::temp_dir().join(format!("minitimer-schedule-{}.json", std::process::id()));
+
+        let timer = Timer::new();
+        let count = Arc::new(AtomicU64::new(0));
+        timer
+            .add_task(once_task(TaskId::new(7), 2, &count))
+            .unwrap();
+        timer.save_schedule(&path).unwrap();
+
+        let restored = Timer::new();
+        let mut registry = RunnerRegistry::new();
+        registry.register_task(TaskId::new(7), {
+            let count = count.clone();
+            move || {
+                Arc::new(CountingRunner {
+                    count: count.clone(),
+                })
+            }
+        });
+        restored.load_schedule(&path, &registry).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut receiver = restored.event_receiver();
+        restored.tick_once();
+        restored.tick_once();
+        assert_eq!(
+            receiver.recv().await.unwrap(),
+            TimerEvent::TaskFired {
+                task_id: TaskId::new(7),
+                name: None
+            }
+        );
+        assert_eq!(
+            receiver.recv().await.unwrap(),
+            TimerEvent::TaskCompleted {
+                task_id: TaskId::new(7),
+                name: None
+            }
+        );
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        // The restored once task does not fire again
+        assert!(restored.remove_task(TaskId::new(7)).is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test]
+    async fn test_load_tasks_from_json_reports_each_entry() {
+        let json = r#"{ "tasks": [
+            { "task_id": 1, "name": "cleanup", "runner_name": "cleanup",
+              "frequency": { "type": "Repeated", "seconds": 60 }, "tags": { "team": "ops" } },
+            { "task_id": 2, "runner_name": "missing",
+              "frequency": { "type": "Once", "seconds": 10 } },
+            { "task_id": 3, "runner_name": "cleanup",
+              "frequency": { "type": "Repeated", "seconds": 0 } },
+            { "task_id": 4, "frequency": { "type": "Once", "seconds": 10 } }
+        ] }"#;
+        let count = Arc::new(AtomicU64::new(0));
+        let mut registry = RunnerRegistry::new();
+        registry.register("cleanup", move || {
+            Arc::new(CountingRunner {
+                count: count.clone(),
+            })
+        });
+
+        let timer = Timer::new();
+        let results = timer.load_tasks_from_json(json, &registry).unwrap();
+        assert_eq!(results.len(), 4);
+        assert!(matches!(results[0], Ok(task_id) if task_id == TaskId::new(1)));
+        assert!(
+            matches!(results[1], Err(TaskError::RunnerNotRegistered(task_id)) if task_id == TaskId::new(2))
+        );
+        assert!(matches!(
+            results[2],
+            Err(TaskError::InvalidFrequency { .. })
+        ));
+        assert!(matches!(results[3], Err(TaskError::Persistence(_))));
+        assert_eq!(timer.len(), 1);
+        assert_eq!(timer.find_tasks_by_tag("team", "ops"), vec![TaskId::new(1)]);
+
+        assert!(matches!(
+            timer.load_tasks_from_json("[]", &registry),
+            Err(TaskError::Persistence(_))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test]
+    async fn test_persist_and_resume_keep_the_phase() {
+        let path =
+            std::env::temp_dir().join(format!("minitimer-persist-{}.json", std::process::id()));
+        let count = Arc::new(AtomicU64::new(0));
+        let mut registry = RunnerRegistry::new();
+        for task_id in [1, 2] {
+            registry.register_task(TaskId::new(task_id), {
+                let count = count.clone();
+                move || {
+                    Arc::new(CountingRunner {
+                        count: count.clone(),
+                    })
+                }
+            });
+        }
+
+        let timer = Timer::new();
+        let start = timer.wheel.current_timestamp();
+        let repeated = TaskBuilder::new(1)
+            .with_frequency_repeated_by_seconds(10)
+            .spwan_async(CountingRunner {
+                count: count.clone(),
+            })
+            .unwrap();
+        let countdown = TaskBuilder::new(2)
+            .with_frequency_count_down_by_seconds(3, 7)
+            .spwan_async(CountingRunner {
+                count: count.clone(),
+            })
+            .unwrap();
+        timer.add_task(repeated).unwrap();
+        timer.add_task(countdown).unwrap();
+        timer.fast_forward(7);
+        assert_eq!(timer.remaining_count(TaskId::new(2)), Some(2));
+        timer.persist(&path).unwrap();
+
+        // The new process starts on the wall clock, behind the ticks of the old timer
+        let resumed = Timer::resume_from(&path, &registry).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(resumed.next_fire_time(TaskId::new(1)), Some(start + 10));
+        assert_eq!(resumed.next_fire_time(TaskId::new(2)), Some(start + 14));
+        assert_eq!(resumed.remaining_count(TaskId::new(2)), Some(2));
+
+        let ticks = start + 14 - resumed.wheel.current_timestamp();
+        resumed.fast_forward(ticks);
+        assert_eq!(resumed.next_fire_time(TaskId::new(1)), Some(start + 20));
+        assert_eq!(resumed.next_fire_time(TaskId::new(2)), Some(start + 21));
+        assert_eq!(resumed.remaining_count(TaskId::new(2)), Some(1));
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test]
+    async fn test_resume_from_applies_overdue_policy() {
+        let path = std::env::temp_dir().join(format!(
+            "minitimer-persist-overdue-{}.json",
+            std::process::id()
+        ));
+        let count = Arc::new(AtomicU64::new(0));
+        let mut registry = RunnerRegistry::new();
+        registry.register_task(TaskId::new(3), {
+            let count = count.clone();
+            move || {
+                Arc::new(CountingRunner {
+                    count: count.clone(),
+                })
+            }
+        });
+
+        let timer = Timer::new();
+        let start = timer.wheel.current_timestamp();
+        let task = TaskBuilder::new(3)
+            .with_frequency_repeated_by_seconds(10)
+            .spwan_async(CountingRunner {
+                count: count.clone(),
+            })
+            .unwrap();
+        timer.add_task(task).unwrap();
+        timer.persist(&path).unwrap();
+
+        // A minute later the alarm at start + 10 was missed
+        let resume = |policy| {
+            TimerBuilder::new()
+                .with_time_offset(60)
+                .resume_from(&path, &registry, policy)
+                .unwrap()
+        };
+        let skipped = resume(OverduePolicy::SkipToNext);
+        let now = skipped.wheel.current_timestamp();
+        let next = skipped.next_fire_time(TaskId::new(3)).unwrap();
+        assert!(next > now && next <= now + 10);
+        assert_eq!((next - start) % 10, 0);
+
+        let fired = resume(OverduePolicy::FireOnce);
+        let now = fired.wheel.current_timestamp();
+        assert_eq!(fired.next_fire_time(TaskId::new(3)), Some(now + 1));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test]
+    async fn test_load_schedule_fires_stale_task_immediately() {
+        let path = std::env::temp_dir().join(format!(
+            "minitimer-stale-schedule-{}.json",
+            std::process::id()
+        ));
+        let snapshot = ScheduleSnapshot {
+            tasks: vec![crate::timer::schedule::TaskRecord {
+                task_id: TaskId::new(8),
+                name: None,
+                tags: Default::default(),
+                priority: 0,
+                frequency: crate::task::frequency::FrequencySeconds::Once(5),
+                alarm_timestamp: crate::utils::timestamp() - 60,
+                runner_name: None,
+                dedup_key: None,
+                depends_on: vec![],
+                max_lateness: None,
+                missed_policy: Default::default(),
+                auto_cancel_at: None,
+            }],
+        };
+        std::fs::write(&path, serde_json::to_string(&snapshot).unwrap()).unwrap();
+
+        let timer = Timer::new();
+        let count = Arc::new(AtomicU64::new(0));
+        let mut registry = RunnerRegistry::new();
+        registry.register_task(TaskId::new(8), {
+            let count = count.clone();
+            move || {
+                Arc::new(CountingRunner {
+                    count: count.clone(),
+                })
+            }
+        });
+        timer.load_schedule(&path, &registry).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut receiver = timer.event_receiver();
+        timer.tick_once();
+        assert_eq!(
+            receiver.recv().await.unwrap(),
+            TimerEvent::TaskFired {
+                task_id: TaskId::new(8),
+                name: None
+            }
+        );
+    }
+
+    #[cfg(feature = "tokio-util")]
+    #[tokio::test]
+    async fn test_run_stops_on_cancellation() {
+        let timer = Arc::new(Timer::new());
+        let token = CancellationToken::new();
+        let mut receiver = timer.event_receiver();
+        let handle = tokio::spawn({
+            let timer = timer.clone();
+            let token = token.clone();
+            async move { timer.run_with_cancellation(token).await }
+        });
+
+        token.cancel();
+        handle.await.unwrap();
+        assert_eq!(receiver.recv().await.unwrap(), TimerEvent::StopTimer);
+    }
+
+    #[cfg(feature = "stream")]
+    #[tokio::test]
+    async fn test_event_stream_yields_fired_events() {
+        use futures::StreamExt;
+
+        let timer = Timer::new();
+        let count = Arc::new(AtomicU64::new(0));
+        timer
+            .add_task(once_task(TaskId::new(1), 1, &count))
+            .unwrap();
+        let stream = timer.event_stream();
+
+        timer.tick_once();
+        let events: Vec<TimerEvent> = stream.take(2).collect().await;
+        assert_eq!(
+            events,
+            vec![
+                TimerEvent::TaskFired {
+                    task_id: TaskId::new(1),
+                    name: None
+                },
+                TimerEvent::TaskCompleted {
+                    task_id: TaskId::new(1),
+                    name: None
+                },
+            ]
+        );
+    }
 }
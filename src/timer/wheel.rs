@@ -1,17 +1,53 @@
-use std::sync::{
-    Arc,
-    atomic::{AtomicU64, Ordering},
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    },
+    time::Duration,
 };
 
-use dashmap::DashMap;
+use dashmap::{DashMap, DashSet, mapref::entry::Entry};
+
+#[cfg(feature = "bincode")]
+use crate::{task::RunnerRegistry, timer::schedule::ScheduleSnapshot};
 
 use crate::{
-    error::TaskError,
-    task::{Task, TaskId},
+    error::{InvalidFrequencyReason, TaskError},
+    task::{
+        Task, TaskId, TaskRunner,
+        frequency::{FrequencySeconds, FrequencyState, MissedPolicy},
+        task::AutoCancel,
+    },
     timer::slot::Slot,
-    utils::timestamp,
+    utils::{
+        rand::{OsRandSource, RandSource},
+        since_epoch,
+    },
 };
 
+/// The number of hour wheel revolutions a task can wait by default, until an overflow wheel
+/// takes over longer delays.
+const MAX_ROUNDS: u64 = 366;
+
+/// The slot counts of the second, minute and hour wheels by default.
+const DEFAULT_LEVELS: [u64; 3] = [60, 60, 24];
+
+/// The capacity a slot keeps when it shrinks after a burst of tasks, by default.
+pub(crate) const DEFAULT_SLOT_CAPACITY_FLOOR: usize = 128;
+
+/// The number of seconds the wheels may lag behind the wall clock before they are resynced
+/// rather than caught up one tick at a time, e.g. after the machine was suspended.
+pub(crate) const RESYNC_THRESHOLD: u64 = 60;
+
+/// The completions kept before they are first pruned, see `MulitWheel::prune_completions`.
+const MIN_COMPLETIONS_LIMIT: usize = 1024;
+
+/// Lock order: a tracker entry may be held while locking a slot, never the other way round.
+/// Tasks are moved between slots under their tracker entry, so adding, removing and cascading
+/// a task don't interleave. Ticks must not run concurrently with each other.
 pub(crate) struct MulitWheel {
     sec_wheel: Wheel,
     min_wheel: Wheel,
@@ -19,16 +55,267 @@ pub(crate) struct MulitWheel {
 
     // Task tracking map
     pub(crate) task_tracker_map: DashMap<TaskId, TaskTrackingInfo>,
+
+    // The wall clock in ticks when the wheel was created and the number of ticks since then
+    base_tick: u64,
+    ticks: AtomicU64,
+    // The time a tick spans, a whole fraction of a second
+    resolution: Duration,
+    ticks_per_second: u64,
+
+    // Source of the scheduling order of tasks
+    next_seq: AtomicU64,
+
+    // The number of completed runs of each task that fired, zero while its first run is
+    // pending. Pruned to the tasks that are scheduled or awaited once it reaches the limit.
+    completions: DashMap<TaskId, u64>,
+    completions_limit: AtomicUsize,
+
+    // The number of hour wheel revolutions a task can wait
+    max_rounds: u64,
+    /// The times the minute and hour wheels cascaded their current slot, for diagnostics.
+    minute_cascades: AtomicU64,
+    hour_cascades: AtomicU64,
+    /// The most tasks that can be scheduled at once, unbounded if `None`.
+    max_tasks: Option<usize>,
+
+    // Source of the ids of task groups
+    next_group_id: AtomicU64,
+    // The groups cancelled as a whole when one of their runs fails
+    abort_on_failure: DashSet<u64>,
+
+    // The order of arriving tasks of equal priority
+    tie_break: TieBreak,
+
+    // Draws the jitter of tasks
+    rand: Arc<dyn RandSource>,
 }
 
-impl MulitWheel {
-    pub(crate) fn new() -> Self {
+/// Configures the structure of the wheels of a timer, see `TimerBuilder::with_wheel`.
+///
+/// The wheels have three levels, the second, minute and hour wheels, each slot of a level
+/// spanning a revolution of the level below and a slot of the second wheel spanning a tick.
+/// By default a tick is a second, the levels have 60, 60 and 24 slots and tasks can be
+/// scheduled up to 366 days ahead.
+#[derive(Debug, Clone)]
+pub struct MulitWheelBuilder {
+    resolution: Duration,
+    levels: Vec<u64>,
+    horizon: Option<Duration>,
+    slot_capacity_floor: usize,
+    time_offset: i64,
+    tie_break: TieBreak,
+    rand: Arc<dyn RandSource>,
+    max_tasks: Option<usize>,
+    align_to_wall_clock: bool,
+    // The wall clock since the UNIX epoch, replaced in tests
+    now: fn() -> Duration,
+}
+
+impl Default for MulitWheelBuilder {
+    fn default() -> Self {
         Self {
-            sec_wheel: Wheel::new(60),
-            min_wheel: Wheel::new(60),
-            hour_wheel: Wheel::new(24),
+            resolution: Duration::from_secs(1),
+            levels: DEFAULT_LEVELS.to_vec(),
+            horizon: None,
+            slot_capacity_floor: DEFAULT_SLOT_CAPACITY_FLOOR,
+            time_offset: 0,
+            tie_break: TieBreak::Fifo,
+            rand: Arc::new(OsRandSource::new()),
+            max_tasks: None,
+            align_to_wall_clock: false,
+            now: since_epoch,
+        }
+    }
+}
+
+impl MulitWheelBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the time a tick spans, a second by default. It must divide a second evenly, e.g. a
+    /// millisecond with levels of 1000, 60 and 60 slots for a second wheel counting
+    /// milliseconds, a minute wheel counting seconds and an hour wheel counting minutes.
+    ///
+    /// Alarms are still in whole seconds, a task fires on the tick its second starts on. The
+    /// wheels start at the wall clock to the tick, so with a finer resolution a task fires
+    /// closer to the start of its second of the wall clock.
+    pub fn with_resolution(mut self, resolution: Duration) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    /// Set the slot counts of the three levels, from the second wheel up to the hour wheel.
+    pub fn with_levels(mut self, levels: &[u64]) -> Self {
+        self.levels = levels.to_vec();
+        self
+    }
+
+    /// Set how far ahead tasks can be scheduled, rounded up to whole revolutions of the wheels.
+    pub fn with_horizon(mut self, horizon: Duration) -> Self {
+        self.horizon = Some(horizon);
+        self
+    }
+
+    /// Set the capacity a slot keeps when it shrinks, see `TimerBuilder::with_slot_capacity_floor`.
+    pub fn with_slot_capacity_floor(mut self, capacity_floor: usize) -> Self {
+        self.slot_capacity_floor = capacity_floor;
+        self
+    }
+
+    /// Shift the clock of the wheels from the system clock by `seconds`, see
+    /// `TimerBuilder::with_time_offset`.
+    #[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+    pub(crate) fn with_time_offset(mut self, seconds: i64) -> Self {
+        self.time_offset = seconds;
+        self
+    }
+
+    /// Set the order of tasks arriving in the same second with the same priority.
+    pub fn with_tie_break(mut self, tie_break: TieBreak) -> Self {
+        self.tie_break = tie_break;
+        self
+    }
+
+    /// Set the source of the random numbers of the wheels, see `TimerBuilder::with_rand_source`.
+    pub fn with_rand_source(mut self, rand: impl RandSource) -> Self {
+        self.rand = Arc::new(rand);
+        self
+    }
+
+    /// Cap the number of scheduled tasks, see `TimerBuilder::with_max_tasks`.
+    pub fn with_max_tasks(mut self, max_tasks: usize) -> Self {
+        self.max_tasks = Some(max_tasks);
+        self
+    }
+
+    /// Start the hands where the wall clock in UTC stands, shifted by the time offset, rather
+    /// than at zero, e.g. at its second, minute and hour with the default levels and
+    /// resolution. The slots then line up with real time from the first tick, e.g. the second
+    /// slot 0 is the start of a minute, which keeps positions readable in tests and
+    /// simulations. Scheduling is the same either way.
+    pub fn with_wall_clock_alignment(mut self, aligned: bool) -> Self {
+        self.align_to_wall_clock = aligned;
+        self
+    }
+
+    /// Read the wall clock, the time since the UNIX epoch, from `now` instead of the system
+    /// clock.
+    #[cfg(test)]
+    pub(crate) fn with_clock(mut self, now: fn() -> Duration) -> Self {
+        self.now = now;
+        self
+    }
+
+    /// Check that the configuration describes wheels that can be built.
+    pub fn validate(&self) -> Result<(), TaskError> {
+        self.max_rounds().map(|_| ())
+    }
+
+    /// The number of hour wheel revolutions covering the horizon.
+    fn max_rounds(&self) -> Result<u64, TaskError> {
+        let invalid = |message: String| Err(TaskError::InvalidWheelConfig(message));
+        let second = Duration::from_secs(1).as_nanos();
+        let resolution = self.resolution.as_nanos();
+        if !second.is_multiple_of(resolution) {
+            return invalid(format!(
+                "a resolution of {:?} doesn't divide a second evenly",
+                self.resolution
+            ));
+        }
+        if self.levels.len() != DEFAULT_LEVELS.len() {
+            return invalid(format!(
+                "{} levels given, the wheels have {}",
+                self.levels.len(),
+                DEFAULT_LEVELS.len()
+            ));
+        }
+        if self.levels.iter().any(|&slots| slots < 2) {
+            return invalid(format!(
+                "levels {:?} need at least 2 slots each",
+                self.levels
+            ));
+        }
+        let revolution = self
+            .levels
+            .iter()
+            .fold(resolution, |span, &slots| span * u128::from(slots));
+        let Some(horizon) = self.horizon else {
+            return Ok(MAX_ROUNDS);
+        };
+        if horizon.as_nanos() < revolution {
+            return invalid(format!(
+                "a horizon of {horizon:?} is shorter than a revolution of {:?}",
+                Duration::from_nanos(revolution as u64)
+            ));
+        }
+        Ok(horizon.as_nanos().div_ceil(revolution) as u64)
+    }
+
+    pub(crate) fn build(self) -> Result<MulitWheel, TaskError> {
+        let max_rounds = self.max_rounds()?;
+        let capacity_floor = self.slot_capacity_floor;
+        let ticks_per_second =
+            (Duration::from_secs(1).as_nanos() / self.resolution.as_nanos()) as u64;
+        let now = (self.now)();
+        // To the tick, so that each second of the wheels starts with the second of the wall clock
+        let base_tick = now
+            .as_secs()
+            .saturating_add_signed(self.time_offset)
+            .saturating_mul(ticks_per_second)
+            + (now.subsec_nanos() as u128 / self.resolution.as_nanos()) as u64;
+        let wheel = MulitWheel {
+            sec_wheel: Wheel::new(self.levels[0], capacity_floor),
+            min_wheel: Wheel::new(self.levels[1], capacity_floor),
+            hour_wheel: Wheel::new(self.levels[2], capacity_floor),
             task_tracker_map: DashMap::new(),
+            base_tick,
+            ticks: AtomicU64::new(0),
+            resolution: self.resolution,
+            ticks_per_second,
+            next_seq: AtomicU64::new(0),
+            completions: DashMap::new(),
+            completions_limit: AtomicUsize::new(MIN_COMPLETIONS_LIMIT),
+            max_rounds,
+            minute_cascades: AtomicU64::new(0),
+            hour_cascades: AtomicU64::new(0),
+            next_group_id: AtomicU64::new(1),
+            abort_on_failure: DashSet::new(),
+            tie_break: self.tie_break,
+            rand: self.rand,
+            max_tasks: self.max_tasks,
+        };
+        if self.align_to_wall_clock {
+            let (sec_slots, min_slots) = (self.levels[0], self.levels[1]);
+            let positions = [
+                base_tick % sec_slots,
+                base_tick / sec_slots % min_slots,
+                base_tick / (sec_slots * min_slots) % self.levels[2],
+            ];
+            for (wheel, position) in [&wheel.sec_wheel, &wheel.min_wheel, &wheel.hour_wheel]
+                .into_iter()
+                .zip(positions)
+            {
+                wheel.hand.store(position, Ordering::Relaxed);
+            }
         }
+        Ok(wheel)
+    }
+}
+
+impl MulitWheel {
+    pub(crate) fn new() -> Self {
+        Self::with_slot_capacity_floor(DEFAULT_SLOT_CAPACITY_FLOOR)
+    }
+
+    /// Slots drained below a quarter of their capacity shrink, keeping at least
+    /// `capacity_floor`.
+    pub(crate) fn with_slot_capacity_floor(capacity_floor: usize) -> Self {
+        MulitWheelBuilder::new()
+            .with_slot_capacity_floor(capacity_floor)
+            .build()
+            .expect("the default wheels are valid")
     }
 
     /// Set the positions of all wheels for testing purposes
@@ -39,6 +326,38 @@ impl MulitWheel {
         self.hour_wheel.set_hand_position(hour);
     }
 
+    /// The positions of the hands of the wheels, see `WheelPositions`.
+    pub fn positions(&self) -> WheelPositions {
+        let (second, minute, hour) = self.get_wheel_positions();
+        WheelPositions {
+            second,
+            minute,
+            hour,
+        }
+    }
+
+    /// The times the minute wheel cascaded a slot into the second wheel, once per minute the
+    /// hands passed unless the minute wheel was paused.
+    #[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+    pub(crate) fn minute_cascades(&self) -> u64 {
+        self.minute_cascades.load(Ordering::Relaxed)
+    }
+
+    /// The times the hour wheel cascaded a slot into the minute wheel, see `minute_cascades`.
+    #[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+    pub(crate) fn hour_cascades(&self) -> u64 {
+        self.hour_cascades.load(Ordering::Relaxed)
+    }
+
+    /// Zero both cascade counters, returning the minute and hour cascades counted before.
+    #[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+    pub(crate) fn take_cascades(&self) -> (u64, u64) {
+        (
+            self.minute_cascades.swap(0, Ordering::Relaxed),
+            self.hour_cascades.swap(0, Ordering::Relaxed),
+        )
+    }
+
     /// Get the current positions of all wheels for testing purposes
     pub(crate) fn get_wheel_positions(&self) -> (u64, u64, u64) {
         (
@@ -48,74 +367,478 @@ impl MulitWheel {
         )
     }
 
-    pub(crate) fn cascade_minute_tasks_internal(&self) {
-        let hand = self.min_wheel.hand.load(Ordering::Relaxed);
-        let slot = self.min_wheel.slots.remove(&hand);
-        if let Some((_, slot)) = slot {
-            for task in slot.task_map.into_values() {
-                let slot_num = task.cascade_guide.sec;
-                self.sec_wheel.add_task(task, slot_num);
-            }
+    /// Advance the wheels by one tick, a second unless configured otherwise, see
+    /// `MulitWheelBuilder::with_resolution`.
+    ///
+    /// Hands are moved first, then the upper levels are cascaded top-down so that a task
+    /// moved from the hour wheel into the current minute slot is cascaded again in the same
+    /// tick. Returns the day carry of the hour wheel.
+    ///
+    /// The hands only ever move by one slot here, so no slot is passed without its cascade.
+    /// `Timer::fast_forward` ticks through each slot, `resync` jumps the hands for larger steps.
+    pub(crate) fn tick(&self) -> Option<u64> {
+        self.ticks.fetch_add(1, Ordering::Relaxed);
+
+        let min_carry = self.sec_wheel.hand_move(1);
+        let hour_carry = min_carry.and_then(|carry| self.min_wheel.hand_move(carry));
+        let day_carry = hour_carry.and_then(|carry| self.hour_wheel.hand_move(carry));
+
+        if let Some(days) = day_carry {
+            self.count_down_rounds(days);
+        }
+        if hour_carry.is_some() && !self.hour_wheel.is_paused() {
+            self.cascade_hour_tasks();
+        }
+        if min_carry.is_some() && !self.min_wheel.is_paused() {
+            self.cascade_minute_tasks();
         }
-        self.min_wheel.slots.insert(hand, Slot::new());
+
+        day_carry
+    }
+
+    /// Advance the wheels by `seconds` in one call, returning the tasks that arrived on the way
+    /// in the order they arrived. Each task is requeued as it arrives, see `hand_over`, so a
+    /// repeated task arrives once per alarm passed.
+    ///
+    /// The wheels are ticked through every tick rather than jumped with one `jump`, so
+    /// every minute and hour slot the hands pass over is cascaded, and tasks cascaded into a
+    /// second slot that is passed later are taken when it is. See `resync` for a jump.
+    #[cfg(test)]
+    pub(crate) fn fast_forward(&self, seconds: u64) -> Vec<Task> {
+        let mut arrived = vec![];
+        for _ in 0..seconds * self.ticks_per_second {
+            self.tick();
+            arrived.extend(self.hand_over(self.take_arrived_tasks()));
+        }
+        arrived
+    }
+
+    /// The number of ticks the wheels have been advanced by since they were created.
+    #[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+    pub(crate) fn ticks(&self) -> u64 {
+        self.ticks.load(Ordering::Relaxed)
+    }
+
+    /// The time a tick spans, see `MulitWheelBuilder::with_resolution`.
+    #[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+    pub(crate) fn resolution(&self) -> Duration {
+        self.resolution
+    }
+
+    /// The number of ticks in a second, one unless configured otherwise.
+    #[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+    pub(crate) fn ticks_per_second(&self) -> u64 {
+        self.ticks_per_second
+    }
+
+    /// The timestamp the wheel currently points at.
+    ///
+    /// It starts at the wall-clock time the wheel was created and advances by one second
+    /// per second of ticks, so rescheduling stays consistent with the hands even if ticks lag
+    /// behind.
+    pub(crate) fn current_timestamp(&self) -> u64 {
+        self.current_tick() / self.ticks_per_second
+    }
+
+    /// The wall clock in ticks the wheel currently points at.
+    fn current_tick(&self) -> u64 {
+        self.base_tick + self.ticks.load(Ordering::Relaxed)
+    }
+
+    /// The ticks from now until the start of the second `timestamp`, 0 once it started.
+    fn ticks_until_second(&self, timestamp: u64) -> u64 {
+        timestamp
+            .saturating_mul(self.ticks_per_second)
+            .saturating_sub(self.current_tick())
     }
 
-    pub(crate) fn cascade_hour_tasks_internal(&self) {
-        let hand = self.hour_wheel.hand.load(Ordering::Relaxed);
-        let slot = self.hour_wheel.slots.remove(&hand);
-        let mut new_slot = Slot::new();
-        if let Some((_, slot)) = slot {
-            for mut task in slot.task_map.into_values() {
-                let round = task.cascade_guide.round;
-                if round > 0 {
-                    task.cascade_guide.round = task.cascade_guide.round.saturating_sub(1);
-                    new_slot.add_task(task);
+    /// Reduce the round of every task parked in the hour wheel after the hour hand wrapped.
+    fn count_down_rounds(&self, days: u64) {
+        for slot_num in 0..self.hour_wheel.num_slots {
+            for task_id in self.hour_wheel.task_ids(slot_num) {
+                let Some(mut tracking_info) = self.task_tracker_map.get_mut(&task_id) else {
                     continue;
-                } else {
-                    let slot_num = task.cascade_guide.min.unwrap();
-                    self.min_wheel.add_task(task, slot_num);
+                };
+                let Some(mut slot) = self.hour_wheel.slots.get_mut(&slot_num) else {
+                    continue;
+                };
+                if let Some(task) = slot.task_map.get_mut(&task_id) {
+                    task.cascade_guide.round = task.cascade_guide.round.saturating_sub(days);
+                    tracking_info.cascade_guide = task.cascade_guide;
                 }
             }
         }
-        self.hour_wheel.slots.insert(hand, new_slot);
     }
 
-    pub(crate) fn tick(&self) -> Option<u64> {
-        self.sec_wheel
-            .hand_move(1)
-            .and_then(|carry| {
-                let carry = self.min_wheel.hand_move(carry);
-                self.cascade_minute_tasks_internal();
-                carry
+    /// Remove and return every task in one slot, in the order they arrived, without moving the
+    /// hands, so a test can fire a slot on its own. Unlike `take_arrived_tasks` the tasks are neither
+    /// rescheduled nor checked for being enabled or blocked.
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn fire_slot(&self, wheel_type: WheelType, slot_num: u64) -> Vec<Task> {
+        let wheel = self.wheel(wheel_type);
+        let Some(slot) = wheel.slots.get(&slot_num) else {
+            return vec![];
+        };
+        let task_ids = slot.arrival_time_tasks(self.tie_break);
+        drop(slot);
+
+        task_ids
+            .into_iter()
+            .filter_map(|task_id| {
+                let Entry::Occupied(entry) = self.task_tracker_map.entry(task_id) else {
+                    return None;
+                };
+                let task = wheel.remove_task(task_id, slot_num)?;
+                entry.remove();
+                Some(task)
             })
-            .and_then(|carry| {
-                let carry = self.hour_wheel.hand_move(carry);
-                self.cascade_hour_tasks_internal();
-                carry
+            .collect()
+    }
+
+    /// Requeue a copy of each task for its next alarm and return the tasks.
+    pub(crate) fn hand_over(&self, tasks: Vec<Task>) -> Vec<Task> {
+        for task in tasks.iter() {
+            let _ = self.requeue(task.clone());
+        }
+        tasks
+    }
+
+    /// Remove and return the enabled tasks that are due in the current second slot.
+    ///
+    /// Disabled tasks are rescheduled for their next alarm instead, so a disabled task skips
+    /// its occurrences rather than deferring them, and a once task arriving disabled is dropped.
+    /// The same goes for tasks coalesced with an earlier task carrying the same dedup key.
+    pub(crate) fn take_arrived_tasks(&self) -> Vec<Task> {
+        if self.sec_wheel.is_paused() {
+            return vec![];
+        }
+        let hand = self.sec_wheel.hand_position();
+        let Some(slot) = self.sec_wheel.slots.get(&hand) else {
+            return vec![];
+        };
+        let task_ids = slot.arrival_time_tasks(self.tie_break);
+        drop(slot);
+
+        let mut tasks = Vec::with_capacity(task_ids.len());
+        let mut dedup_keys = HashSet::new();
+        let mut waiting = vec![];
+        for task_id in task_ids {
+            let Entry::Occupied(mut entry) = self.task_tracker_map.entry(task_id) else {
+                continue;
+            };
+            let Some(mut task) = self.sec_wheel.remove_task(task_id, hand) else {
+                continue;
+            };
+            let enabled = entry.get().enabled;
+            let blocked = enabled && !self.dependencies_completed(&task);
+            let coalesced = enabled
+                && !blocked
+                && task
+                    .dedup_key
+                    .as_ref()
+                    .is_some_and(|key| !dedup_keys.insert(key.clone()));
+            if enabled && !blocked && !coalesced {
+                entry.remove();
+                self.release(&mut task);
+                tasks.push(task);
+                continue;
+            }
+            if blocked {
+                waiting.push((task_id, task.depends_on.clone()));
+            }
+
+            // A blocked task retries on the next second, keeping its schedule. A schedule that is
+            // finished or out of range ends like it would when enabled.
+            let next_exec_timestamp = if blocked {
+                Some(self.current_timestamp() + 1)
+            } else {
+                task.next_alarm_timestamp()
+            };
+            match next_exec_timestamp
+                .map(|next_exec_timestamp| self.locate(&mut task, next_exec_timestamp))
+            {
+                Some(Ok((wheel_type, slot_num))) => {
+                    *entry.get_mut() = Self::tracking_info(&task, wheel_type, slot_num, enabled);
+                    self.wheel(wheel_type).add_task(task, slot_num);
+                }
+                _ => {
+                    entry.remove();
+                }
+            }
+        }
+        self.sec_wheel.shrink_slot(hand);
+        self.remove_orphaned(waiting);
+        tasks
+    }
+
+    /// The ids of the tasks the next `tick` brings into the second slot under the hand and
+    /// `take_arrived_tasks` would then dispatch, in dispatch order, without moving the hands.
+    ///
+    /// Disabled tasks, tasks waiting for their dependencies, tasks coalesced by their dedup key
+    /// and tasks held up by a paused wheel are left out, as they don't fire.
+    pub(crate) fn due_tasks_preview(&self) -> Vec<TaskId> {
+        let mut due = vec![];
+        for info in self.task_tracker_map.iter() {
+            let held_up = match info.wheel_type {
+                WheelType::Second => self.sec_wheel.is_paused(),
+                WheelType::Minute => self.sec_wheel.is_paused() || self.min_wheel.is_paused(),
+                WheelType::Hour => {
+                    self.sec_wheel.is_paused()
+                        || self.min_wheel.is_paused()
+                        || self.hour_wheel.is_paused()
+                }
+            };
+            if !info.enabled
+                || held_up
+                || self.ticks_until(info.wheel_type, info.cascade_guide) != 1
+            {
+                continue;
+            }
+            // Tracker entry before slot, the lock order of the wheel
+            let Some(slot) = self.wheel(info.wheel_type).slots.get(&info.slot_num) else {
+                continue;
+            };
+            let Some(task) = slot.task_map.get(info.key()) else {
+                continue;
+            };
+            if self.dependencies_completed(task) {
+                due.push((
+                    Reverse(task.priority),
+                    self.tie_break.key(task),
+                    task.task_id,
+                    task.dedup_key.clone(),
+                ));
+            }
+        }
+        due.sort_unstable_by_key(|(priority, key, task_id, _)| (*priority, *key, *task_id));
+
+        let mut dedup_keys = HashSet::new();
+        due.into_iter()
+            .filter(|(_, _, _, dedup_key)| {
+                dedup_key
+                    .as_ref()
+                    .is_none_or(|key| dedup_keys.insert(key.clone()))
             })
+            .map(|(_, _, task_id, _)| task_id)
+            .collect()
+    }
+
+    /// Count a completed run of the task, for the tasks depending on it.
+    pub(crate) fn record_completion(&self, task_id: TaskId) {
+        *self.completions.entry(task_id).or_default() += 1;
+        self.prune_completions();
+    }
+
+    /// Note a failed run of the task. A task that never completed and is no longer scheduled,
+    /// e.g. a once task, never will, so the tasks waiting for it are removed on their next try.
+    pub(crate) fn record_failure(&self, task_id: TaskId) {
+        if !self.task_tracker_map.contains_key(&task_id) {
+            self.completions.remove_if(&task_id, |_, count| *count == 0);
+        }
+    }
+
+    /// A task leaves the wheels to fire: it no longer waits for its dependencies, and it counts
+    /// as pending for the tasks depending on it until its run is recorded.
+    fn release(&self, task: &mut Task) {
+        task.depends_on.clear();
+        self.completions.entry(task.task_id).or_default();
+    }
+
+    /// Whether every task the task depends on has completed at least once.
+    fn dependencies_completed(&self, task: &Task) -> bool {
+        task.depends_on
+            .iter()
+            .all(|&task_id| self.has_completed(task_id))
+    }
+
+    /// Whether a run of the task completed since its completions were last pruned.
+    pub(crate) fn has_completed(&self, task_id: TaskId) -> bool {
+        self.completions
+            .get(&task_id)
+            .is_some_and(|count| *count > 0)
+    }
+
+    /// Whether a task can still complete or already has: it is scheduled, pending or completed.
+    ///
+    /// Must not be called while holding a tracker entry.
+    fn is_known(&self, task_id: TaskId) -> bool {
+        self.task_tracker_map.contains_key(&task_id) || self.completions.contains_key(&task_id)
+    }
+
+    /// Remove the waiting tasks whose dependencies include a task that is gone without having
+    /// completed, e.g. a removed task or a once task whose run failed, as they would retry
+    /// forever.
+    ///
+    /// Must not be called while holding a tracker entry.
+    fn remove_orphaned(&self, waiting: Vec<(TaskId, Vec<TaskId>)>) {
+        for (task_id, depends_on) in waiting {
+            if let Some(&dependency) = depends_on.iter().find(|&&id| !self.is_known(id)) {
+                tracing::warn!(%task_id, %dependency, "removing task waiting for a task that is gone");
+                self.remove_task(task_id);
+            }
+        }
+    }
+
+    /// Once the completions reach their limit, keep only those of the scheduled tasks and of the
+    /// tasks a scheduled task waits for, and double the limit from what is left. The map stays
+    /// in proportion to the scheduled tasks however many tasks a long running timer has seen.
+    fn prune_completions(&self) {
+        if self.completions.len() < self.completions_limit.load(Ordering::Relaxed) {
+            return;
+        }
+        // Collect first, the tracker must not be locked under a completion
+        let mut kept = HashSet::new();
+        for info in self.task_tracker_map.iter() {
+            kept.insert(info.task_id);
+            kept.extend(info.depends_on.iter().copied());
+        }
+        self.completions.retain(|task_id, _| kept.contains(task_id));
+        self.completions_limit.store(
+            (self.completions.len() * 2).max(MIN_COMPLETIONS_LIMIT),
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Fail if the task depends on itself through the dependencies of the scheduled tasks.
+    ///
+    /// Must not be called while holding a tracker entry.
+    fn check_dependency_cycle(&self, task: &Task) -> Result<(), TaskError> {
+        let mut pending = task.depends_on.clone();
+        let mut visited = HashSet::new();
+        while let Some(task_id) = pending.pop() {
+            if task_id == task.task_id {
+                return Err(TaskError::DependencyCycle(task.task_id));
+            }
+            if visited.insert(task_id)
+                && let Some(info) = self.task_tracker_map.get(&task_id)
+            {
+                pending.extend_from_slice(&info.depends_on);
+            }
+        }
+        Ok(())
     }
 
-    pub(crate) fn cal_next_hand_position(&self, next_alarm_sec: u64) -> WheelCascadeGuide {
+    /// The number of seconds the wheels are behind the wall-clock timestamp `now`.
+    pub(crate) fn lag_behind(&self, now: u64) -> u64 {
+        now.saturating_sub(self.current_timestamp())
+    }
+
+    /// Jump the wheels forward to the wall-clock timestamp `now` in one go, instead of ticking
+    /// through the gap.
+    ///
+    /// Every task is put back into place from its absolute alarm timestamp. The enabled tasks
+    /// that became overdue during the gap are removed and returned, ordered by alarm, so they
+    /// fire once; the occurrences they missed are skipped, or left to fire one per tick for a
+    /// `MissedPolicy::CatchUp` task. Disabled and coalesced overdue tasks are rescheduled for
+    /// their next alarm. Paused wheels are moved as well.
+    pub(crate) fn resync(&self, now: u64) -> Vec<Task> {
+        if self.lag_behind(now) == 0 {
+            return vec![];
+        }
+        let gap = self.ticks_until_second(now);
+        self.ticks.fetch_add(gap, Ordering::Relaxed);
+        let min_carry = self.sec_wheel.jump(gap);
+        let hour_carry = min_carry.and_then(|carry| self.min_wheel.jump(carry));
+        hour_carry.and_then(|carry| self.hour_wheel.jump(carry));
+
+        let mut task_ids: Vec<TaskId> = self.task_tracker_map.iter().map(|e| *e.key()).collect();
+        task_ids.sort_unstable();
+        let mut overdue = vec![];
+        let mut dedup_keys = HashSet::new();
+        let mut waiting = vec![];
+        for task_id in task_ids {
+            let Entry::Occupied(mut entry) = self.task_tracker_map.entry(task_id) else {
+                continue;
+            };
+            let (wheel_type, slot_num) = (entry.get().wheel_type, entry.get().slot_num);
+            let Some(mut task) = self.wheel(wheel_type).remove_task(task_id, slot_num) else {
+                continue;
+            };
+            let enabled = entry.get().enabled;
+
+            let (seq, moved) = (task.seq, task.alarm_timestamp > now);
+            let mut alarm_timestamp = Some(task.alarm_timestamp);
+            if !moved {
+                // Disabled tasks don't catch up, they would not have fired anyway
+                if task.missed_policy == MissedPolicy::Skip || !enabled {
+                    Self::skip_missed_alarms(&mut task, now);
+                }
+                let blocked = enabled && !self.dependencies_completed(&task);
+                let coalesced = enabled
+                    && !blocked
+                    && task
+                        .dedup_key
+                        .as_ref()
+                        .is_some_and(|key| !dedup_keys.insert(key.clone()));
+                if enabled && !blocked && !coalesced {
+                    entry.remove();
+                    self.release(&mut task);
+                    overdue.push(task);
+                    continue;
+                }
+                if blocked {
+                    waiting.push((task_id, task.depends_on.clone()));
+                }
+                alarm_timestamp = if blocked {
+                    Some(now + 1)
+                } else {
+                    task.next_alarm_timestamp()
+                };
+            }
+
+            match alarm_timestamp.map(|alarm| self.locate(&mut task, alarm)) {
+                Some(Ok((wheel_type, slot_num))) => {
+                    // Keep the scheduling order of tasks that are only moved
+                    if moved {
+                        task.seq = seq;
+                    }
+                    *entry.get_mut() = Self::tracking_info(&task, wheel_type, slot_num, enabled);
+                    self.wheel(wheel_type).add_task(task, slot_num);
+                }
+                _ => {
+                    entry.remove();
+                }
+            }
+        }
+        self.remove_orphaned(waiting);
+        overdue.sort_by_key(|task| {
+            (
+                task.alarm_timestamp,
+                Reverse(task.priority),
+                self.tie_break.key(task),
+                task.task_id,
+            )
+        });
+        overdue
+    }
+
+    pub(crate) fn cal_next_hand_position(&self, next_alarm_ticks: u64) -> WheelCascadeGuide {
         let (current_second, current_minute, current_hour) = self.get_wheel_positions();
 
-        let total_seconds = current_second + next_alarm_sec;
-        let final_sec = total_seconds % 60;
+        let (sec_slots, min_slots, hour_slots) = (
+            self.sec_wheel.num_slots,
+            self.min_wheel.num_slots,
+            self.hour_wheel.num_slots,
+        );
+
+        let total_seconds = current_second + next_alarm_ticks;
+        let final_sec = total_seconds % sec_slots;
 
-        let total_minutes = current_minute + (total_seconds / 60);
-        let final_min = total_minutes % 60;
+        let total_minutes = current_minute + (total_seconds / sec_slots);
+        let final_min = total_minutes % min_slots;
 
         // Check if there will be a carry from seconds to minutes
-        let has_min_carry = total_seconds >= 60;
+        let has_min_carry = total_seconds >= sec_slots;
 
         if has_min_carry {
             // Check if there will be a carry from minutes to hours
-            let has_hour_carry = total_minutes >= 60;
+            let has_hour_carry = total_minutes >= min_slots;
 
             if has_hour_carry {
                 // There will be carry to hours, we need to calculate rounds as well
-                let total_hours = current_hour + (total_minutes / 60);
-                let final_hour = total_hours % 24;
-                let round = total_hours / 24;
+                let total_hours = current_hour + (total_minutes / min_slots);
+                let final_hour = total_hours % hour_slots;
+                let round = total_hours / hour_slots;
 
                 WheelCascadeGuide {
                     sec: final_sec,
@@ -148,10 +871,12 @@ pub(crate) struct Wheel {
     slots: DashMap<u64, Slot>,
     hand: Arc<AtomicU64>,
     num_slots: u64,
+    paused: AtomicBool,
+    capacity_floor: usize,
 }
 
 impl Wheel {
-    pub(crate) fn new(num_slots: u64) -> Self {
+    pub(crate) fn new(num_slots: u64, capacity_floor: usize) -> Self {
         let slots = DashMap::new();
         for i in 0..num_slots {
             slots.insert(i, Slot::new());
@@ -161,17 +886,33 @@ impl Wheel {
             slots,
             hand: Arc::new(AtomicU64::new(0)),
             num_slots,
+            paused: AtomicBool::new(false),
+            capacity_floor,
         }
     }
 
-    /// Move the hand to the next slot.
-    /// Returns the carry amount.
+    /// Move the hand to the next slot, or keep it for a step of 0.
+    /// Returns the carry, one when the hand wrapped around.
+    ///
+    /// Only single steps, a longer step would skip the slots in between without cascading
+    /// them. See `jump` for `resync`.
     pub(crate) fn hand_move(&self, step: u64) -> Option<u64> {
+        debug_assert!(
+            step <= 1,
+            "a hand_move by {step} skips the slots in between, see `jump`"
+        );
+        self.jump(step)
+    }
+
+    /// Move the hand by `step` slots at once, returning the number of rotations as the carry,
+    /// e.g. 125 from slot 0 of a 60-slot wheel lands at 5 with a carry of 2. The hand jumps over
+    /// the slots in between, so the caller is responsible for the tasks in them, as `resync` is
+    /// by relocating every task.
+    pub(crate) fn jump(&self, step: u64) -> Option<u64> {
         if step == 0 {
             return None;
         }
         let pre_hand = self.hand.fetch_add(step, Ordering::Relaxed);
-        println!("pre_hand: {}", pre_hand);
         let new_hand = pre_hand + step;
         let carry = new_hand / self.num_slots;
 
@@ -189,6 +930,10 @@ impl Wheel {
         self.hand.load(Ordering::Relaxed)
     }
 
+    pub(crate) fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
     /// Set the hand position of the wheel for testing purposes
     #[cfg(test)]
     pub(crate) fn set_hand_position(&self, position: u64) {
@@ -199,10 +944,29 @@ impl Wheel {
     pub(crate) fn add_task(&self, task: Task, slot_num: u64) {
         self.slots.get_mut(&slot_num).unwrap().add_task(task);
     }
+
+    /// Shrink the slot after tasks were drained from it, see `Slot::shrink`.
+    pub(crate) fn shrink_slot(&self, slot_num: u64) {
+        if let Some(mut slot) = self.slots.get_mut(&slot_num) {
+            slot.shrink(self.capacity_floor);
+        }
+    }
+
+    pub(crate) fn reserve(&self, slot_num: u64, additional: usize) {
+        self.slots
+            .get_mut(&slot_num)
+            .unwrap()
+            .task_map
+            .reserve(additional);
+    }
 }
 
-#[derive(Debug, Default, Copy, Clone)]
-pub(crate) struct WheelCascadeGuide {
+/// The slots a task passes through on its way to firing: the second slot it fires in, the
+/// minute and hour slots it waits in before, and the revolutions of the hour wheel it waits
+/// for. A task waits in the highest level that is set, see `TaskBuilder::with_cascade_guide`.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WheelCascadeGuide {
     pub sec: u64,
     pub min: Option<u64>,
     pub hour: Option<u64>,
@@ -210,150 +974,1288 @@ pub(crate) struct WheelCascadeGuide {
 }
 
 impl WheelCascadeGuide {
+    /// Rounds are only consumed in the hour wheel, so a task is due once it has none left.
     pub(crate) fn is_arrived(&self) -> bool {
-        todo!()
+        self.round == 0
     }
 }
 
 // Task tracking information structure - contains task ID and cascade guide
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TaskTrackingInfo {
     pub task_id: TaskId,
+    pub name: Option<String>,
+    pub tags: HashMap<String, String>,
     pub cascade_guide: WheelCascadeGuide,
     pub wheel_type: WheelType,
     pub slot_num: u64,
+    /// Disabled tasks keep their schedule but are not dispatched.
+    pub enabled: bool,
+    /// The timestamp of the alarm the task is scheduled for.
+    pub alarm_timestamp: u64,
+    /// The tasks that must have completed once before the task first runs, empty once it ran.
+    pub depends_on: Vec<TaskId>,
+    /// The id of the `TaskGroup` the task was scheduled with.
+    pub group_id: Option<u64>,
+    /// The alarm the task was scheduled for when it was already past, the task then fires on
+    /// the next tick instead. See `MulitWheel::reschedule_overdue`.
+    pub missed_alarm: Option<u64>,
+    /// The frequency the task follows, see `Task::frequency_kind`.
+    pub frequency: Option<FrequencySeconds>,
+}
+
+/// The positions of the hands of the wheels, in tick units: the slot each hand points at.
+///
+/// The second hand moves every tick, the minute hand every revolution of the second wheel and
+/// the hour hand every revolution of the minute wheel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WheelPositions {
+    pub second: u64,
+    pub minute: u64,
+    pub hour: u64,
+}
+
+/// Where a task currently sits in the wheels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaskLocation {
+    pub wheel_type: WheelType,
+    pub slot_num: u64,
+    /// The number of ticks until the task fires.
+    pub ticks_until_fire: u64,
+}
+
+/// The bytes the name, tags and dependencies of a task allocate, see
+/// `MulitWheel::estimated_memory_bytes`.
+fn heap_bytes(
+    name: &Option<String>,
+    tags: &HashMap<String, String>,
+    depends_on: &[TaskId],
+) -> usize {
+    name.as_ref().map_or(0, String::capacity)
+        + tags.capacity() * size_of::<(String, String)>()
+        + tags
+            .iter()
+            .map(|(key, value)| key.capacity() + value.capacity())
+            .sum::<usize>()
+        + size_of_val(depends_on)
+}
+
+/// A mismatch between the slots of the wheels and the task tracker, see `MulitWheel::audit`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditError {
+    /// A task sits in a slot but has no tracker entry.
+    Untracked {
+        task_id: TaskId,
+        wheel_type: WheelType,
+        slot_num: u64,
+    },
+    /// The tracker has an entry for a task that sits in no slot.
+    Missing {
+        task_id: TaskId,
+        wheel_type: WheelType,
+        slot_num: u64,
+    },
+    /// A task sits in another slot than the one its tracker entry points at.
+    Misplaced {
+        task_id: TaskId,
+        tracked: (WheelType, u64),
+        found: (WheelType, u64),
+    },
+}
+
+/// What `MulitWheel::reschedule_overdue` does with a task whose alarm already passed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OverduePolicy {
+    /// Fire once on the next tick, then continue with the first alarm after now.
+    FireOnce,
+    /// Don't fire for the missed alarms, continue with the first alarm after now.
+    SkipToNext,
+}
+
+/// The order of tasks arriving in the same second with the same priority, see
+/// `TimerBuilder::with_tie_break`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum TieBreak {
+    /// In the order the tasks were scheduled for the alarm, the default.
+    #[default]
+    Fifo,
+    /// In ascending order of task id, regardless of when the tasks were scheduled.
+    TaskId,
+}
+
+impl TieBreak {
+    /// The sort key of a task among tasks of equal priority.
+    pub(crate) fn key(self, task: &Task) -> u64 {
+        match self {
+            Self::Fifo => task.seq,
+            // Leave the order to the task id that follows the key
+            Self::TaskId => 0,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WheelType {
     Second,
     Minute,
     Hour,
 }
 
+impl fmt::Display for WheelType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            WheelType::Second => "second",
+            WheelType::Minute => "minute",
+            WheelType::Hour => "hour",
+        };
+        f.write_str(name)
+    }
+}
+
 impl MulitWheel {
+    /// The number of scheduled tasks.
+    pub fn len(&self) -> usize {
+        self.task_tracker_map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.task_tracker_map.is_empty()
+    }
+
+    pub fn contains(&self, task_id: TaskId) -> bool {
+        self.task_tracker_map.contains_key(&task_id)
+    }
+
+    /// The timestamp of the next alarm of a scheduled task.
+    pub fn next_fire_time(&self, task_id: TaskId) -> Option<u64> {
+        self.task_tracker_map
+            .get(&task_id)
+            .map(|info| info.alarm_timestamp)
+    }
+
     /// Quickly query task tracking information
     pub fn get_task_tracking_info(&self, task_id: TaskId) -> Option<TaskTrackingInfo> {
-        self.task_tracker_map.get(&task_id).map(|info| info.clone())
+        self.task_tracker_map
+            .get(&task_id)
+            .map(|info| info.value().clone())
     }
 
-    /// Add task and initialize tracking information
-    pub fn add_task(&self, mut task: Task) -> Result<(), TaskError> {
-        let next_exec_timestamp = match task.next_alarm_timestamp() {
-            Some(t) => t,
-            None => return Ok(()),
-        };
+    /// Locate a scheduled task, e.g. to find out why it fired late.
+    pub fn task_location(&self, task_id: TaskId) -> Option<TaskLocation> {
+        let info = self.task_tracker_map.get(&task_id)?;
+        Some(TaskLocation {
+            wheel_type: info.wheel_type,
+            slot_num: info.slot_num,
+            ticks_until_fire: self.ticks_until_second(info.alarm_timestamp),
+        })
+    }
 
-        let next_alarm_sec = next_exec_timestamp - timestamp();
-        let next_guide = self.cal_next_hand_position(next_alarm_sec);
-        task.cascade_guide = next_guide;
+    /// The tick, counted from the creation of the wheels, on which the task is next due.
+    ///
+    /// Derived from the slot the task waits in, its cascade guide and the positions of the
+    /// hands, rather than from its alarm timestamp, so it is what the wheels will actually do.
+    /// Paused wheels are not accounted for.
+    pub fn fire_tick(&self, task_id: TaskId) -> Option<u64> {
+        let info = self.task_tracker_map.get(&task_id)?;
+        let ticks_until = self.ticks_until(info.wheel_type, info.cascade_guide);
+        Some(self.ticks.load(Ordering::Relaxed) + ticks_until)
+    }
 
-        // Determine the wheel where the task should be placed based on the calculated cascade guide and record position information
-        let tracking_info = if let Some(hour) = next_guide.hour {
-            self.hour_wheel.add_task(task.clone(), hour);
-            TaskTrackingInfo {
-                task_id: task.task_id,
-                cascade_guide: next_guide,
-                wheel_type: WheelType::Hour,
-                slot_num: hour,
-            }
-        } else if let Some(min) = next_guide.min {
-            self.min_wheel.add_task(task.clone(), min);
-            TaskTrackingInfo {
-                task_id: task.task_id,
-                cascade_guide: next_guide,
-                wheel_type: WheelType::Minute,
-                slot_num: min,
+    /// The ticks until a task waiting in the wheel with the cascade guide is due.
+    fn ticks_until(&self, wheel_type: WheelType, guide: WheelCascadeGuide) -> u64 {
+        let (sec, min, hour) = self.get_wheel_positions();
+        let (sec_slots, min_slots, hour_slots) = (
+            self.sec_wheel.num_slots,
+            self.min_wheel.num_slots,
+            self.hour_wheel.num_slots,
+        );
+        let target_sec = guide.sec;
+        let target_min = guide.min.unwrap_or(min) * sec_slots + target_sec;
+        match wheel_type {
+            WheelType::Second => (target_sec + sec_slots - sec) % sec_slots,
+            WheelType::Minute => {
+                let hour_span = min_slots * sec_slots;
+                (target_min + hour_span - (min * sec_slots + sec)) % hour_span
             }
-        } else {
-            self.sec_wheel.add_task(task.clone(), next_guide.sec);
-            TaskTrackingInfo {
-                task_id: task.task_id,
-                cascade_guide: next_guide,
-                wheel_type: WheelType::Second,
-                slot_num: next_guide.sec,
+            WheelType::Hour => {
+                let day = hour_slots * min_slots * sec_slots;
+                let target = guide.round * day
+                    + guide.hour.unwrap_or(hour) * min_slots * sec_slots
+                    + target_min;
+                target.saturating_sub((hour * min_slots + min) * sec_slots + sec)
             }
-        };
-
-        // Update task tracking map
-        self.task_tracker_map.insert(task.task_id, tracking_info);
-        Ok(())
+        }
     }
 
-    /// Update task tracking information when cascading from minute wheel to second wheel
-    pub fn cascade_minute_tasks(&self) {
-        let hand = self.min_wheel.hand.load(Ordering::Relaxed);
-        let slot = self.min_wheel.slots.remove(&hand);
-        if let Some((_, slot)) = slot {
-            for task in slot.task_map.into_values() {
-                let slot_num = task.cascade_guide.sec;
-
-                // Update information from tracking map
-                if let Some(mut tracking_info) = self.task_tracker_map.get_mut(&task.task_id) {
-                    tracking_info.wheel_type = WheelType::Second;
-                    tracking_info.slot_num = slot_num;
-                    tracking_info.cascade_guide = task.cascade_guide;
-                }
+    /// The enabled tasks whose next alarm is within `within_secs` seconds from now, with the
+    /// timestamp of that alarm, soonest first.
+    pub fn upcoming(&self, within_secs: u64) -> Vec<(TaskId, u64)> {
+        let until = self.current_timestamp().saturating_add(within_secs);
+        let mut upcoming: Vec<(TaskId, u64)> = self
+            .task_tracker_map
+            .iter()
+            .filter(|info| info.enabled && info.alarm_timestamp <= until)
+            .map(|info| (*info.key(), info.alarm_timestamp))
+            .collect();
+        upcoming.sort_by_key(|&(task_id, alarm_timestamp)| (alarm_timestamp, task_id));
+        upcoming
+    }
 
-                // Add task to second wheel
-                self.sec_wheel.add_task(task, slot_num);
-            }
-        }
-        self.min_wheel.slots.insert(hand, Slot::new());
+    /// Swap the runner of a scheduled task, keeping its schedule, state and completions. A run
+    /// in flight finishes with the previous runner, the next run uses the new one.
+    ///
+    /// Fails for a task that is not scheduled, e.g. a once task whose run is in flight.
+    pub fn replace_runner(
+        &self,
+        task_id: TaskId,
+        runner: Arc<dyn TaskRunner<Output = ()> + Send + Sync>,
+    ) -> Result<(), TaskError> {
+        self.with_task_mut(task_id, |task| {
+            task.runner = runner;
+            Ok(())
+        })
     }
 
-    /// Update task tracking information when cascading from hour wheel to minute wheel
-    pub fn cascade_hour_tasks(&self) {
-        let hand = self.hour_wheel.hand.load(Ordering::Relaxed);
-        let slot = self.hour_wheel.slots.remove(&hand);
-        let mut new_slot = Slot::new();
-        if let Some((_, slot)) = slot {
-            for mut task in slot.task_map.into_values() {
-                let round = task.cascade_guide.round;
-                if round > 0 {
-                    // Update round in tracking information
-                    if let Some(mut tracking_info) = self.task_tracker_map.get_mut(&task.task_id) {
-                        task.cascade_guide.round = task.cascade_guide.round.saturating_sub(1);
-                        tracking_info.cascade_guide = task.cascade_guide;
-                    }
-                    new_slot.add_task(task);
-                    continue;
-                } else {
-                    // Move from hour wheel to minute wheel
-                    if let Some(mut tracking_info) = self.task_tracker_map.get_mut(&task.task_id) {
-                        tracking_info.wheel_type = WheelType::Minute;
-                        tracking_info.slot_num = task.cascade_guide.min.unwrap();
-                        tracking_info.cascade_guide = task.cascade_guide;
-                    }
+    /// The number of alarms a countdown task, or a task with delays, has left, including the
+    /// pending one. `None` if the task is not scheduled or has no count.
+    pub fn remaining_count(&self, task_id: TaskId) -> Option<u64> {
+        self.with_task_mut(task_id, |task| match task.frequency_seconds {
+            Some(FrequencySeconds::CountDown(..)) | None => Ok(task.frequency.remaining_count()),
+            _ => Ok(None),
+        })
+        .ok()
+        .flatten()
+        .map(|count| count + 1)
+    }
 
-                    let slot_num = task.cascade_guide.min.unwrap();
-                    self.min_wheel.add_task(task, slot_num);
+    /// Set the number of alarms a countdown task has left, including the pending one, e.g. to
+    /// extend it without recreating the task. The pending alarm and the interval are kept.
+    ///
+    /// Fails if the task is not scheduled, not a countdown, or the count is zero; remove the
+    /// task to end it instead.
+    pub fn set_remaining_count(&self, task_id: TaskId, count: u64) -> Result<(), TaskError> {
+        if count == 0 {
+            return Err(TaskError::invalid_frequency(
+                InvalidFrequencyReason::ZeroCount,
+                Some(0),
+                format!("countdown of task {task_id} needs a count, remove the task to end it"),
+            ));
+        }
+        self.with_task_mut(task_id, |task| {
+            match task.frequency_seconds {
+                Some(FrequencySeconds::CountDown(..)) => {}
+                _ => {
+                    return Err(TaskError::invalid_frequency(
+                        InvalidFrequencyReason::Unsupported,
+                        None,
+                        format!("task {task_id} is not a countdown"),
+                    ));
                 }
             }
+            task.frequency.set_remaining_count(count - 1);
+            Ok(())
+        })
+    }
+
+    /// Apply `f` to a scheduled task where it waits in its slot.
+    fn with_task_mut<T>(
+        &self,
+        task_id: TaskId,
+        f: impl FnOnce(&mut Task) -> Result<T, TaskError>,
+    ) -> Result<T, TaskError> {
+        // The tracker entry keeps the task from moving to another slot meanwhile
+        let info = self
+            .task_tracker_map
+            .get(&task_id)
+            .ok_or(TaskError::TaskNotFound(task_id))?;
+        let mut slot = self
+            .wheel(info.wheel_type)
+            .slots
+            .get_mut(&info.slot_num)
+            .ok_or(TaskError::TaskNotFound(task_id))?;
+        let task = slot
+            .task_map
+            .get_mut(&task_id)
+            .ok_or(TaskError::TaskNotFound(task_id))?;
+        f(task)
+    }
+
+    /// Freeze the tasks on a wheel level: they neither cascade down nor fire while it is paused,
+    /// including tasks cascading into the level from above.
+    ///
+    /// The hands keep moving, so a task whose slot the hand passed while paused moves on when
+    /// the hand comes around again after `resume_wheel`, i.e. a whole revolution later.
+    pub fn pause_wheel(&self, wheel_type: WheelType) {
+        self.wheel(wheel_type).paused.store(true, Ordering::Relaxed);
+    }
+
+    /// Let the tasks on a paused wheel level cascade and fire again.
+    pub fn resume_wheel(&self, wheel_type: WheelType) {
+        self.wheel(wheel_type)
+            .paused
+            .store(false, Ordering::Relaxed);
+    }
+
+    /// Stop dispatching the task while keeping its schedule, see `take_arrived_tasks`.
+    /// Returns `false` if the task is not scheduled.
+    pub fn disable_task(&self, task_id: TaskId) -> bool {
+        self.set_task_enabled(task_id, false)
+    }
+
+    /// Resume dispatching a disabled task from its next alarm.
+    /// Returns `false` if the task is not scheduled.
+    pub fn enable_task(&self, task_id: TaskId) -> bool {
+        self.set_task_enabled(task_id, true)
+    }
+
+    fn set_task_enabled(&self, task_id: TaskId, enabled: bool) -> bool {
+        match self.task_tracker_map.get_mut(&task_id) {
+            Some(mut info) => {
+                info.enabled = enabled;
+                true
+            }
+            None => false,
         }
-        self.hour_wheel.slots.insert(hand, new_slot);
     }
 
-    /// Remove task and clean up from tracking map
-    pub fn remove_task(&self, task_id: TaskId) -> Option<Task> {
-        if let Some((_, tracking_info)) = self.task_tracker_map.remove(&task_id) {
-            let tracking_info = tracking_info.clone();
-            // Remove task from corresponding wheel
-            let removed_task = match tracking_info.wheel_type {
-                WheelType::Second => self.sec_wheel.remove_task(task_id, tracking_info.slot_num),
-                WheelType::Minute => self.min_wheel.remove_task(task_id, tracking_info.slot_num),
-                WheelType::Hour => self.hour_wheel.remove_task(task_id, tracking_info.slot_num),
+    /// Schedule tasks as a group, returning the id of the group. Either all tasks are added or
+    /// none: on the first failure the tasks added so far are removed and the error returned.
+    pub(crate) fn add_group(&self, tasks: Vec<Task>) -> Result<u64, TaskError> {
+        let group_id = self.next_group_id.fetch_add(1, Ordering::Relaxed);
+        let tasks = tasks
+            .into_iter()
+            .map(|mut task| {
+                task.group_id = Some(group_id);
+                task
+            })
+            .collect();
+        if let Some(e) = self.add_tasks(tasks).into_iter().find_map(Result::err) {
+            self.remove_tasks_where(|info| info.group_id == Some(group_id));
+            return Err(e);
+        }
+        Ok(group_id)
+    }
+
+    /// Cancel the whole group when a run of one of its tasks fails, see `abort_group`.
+    pub(crate) fn set_abort_on_failure(&self, group_id: u64) {
+        self.abort_on_failure.insert(group_id);
+    }
+
+    /// Remove the scheduled tasks of a group after a run of one of them failed, if the group
+    /// aborts on failure, returning the ids of the removed tasks in ascending order. A group is
+    /// aborted once, later failures of runs still in flight leave it alone.
+    #[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+    pub(crate) fn abort_group(&self, group_id: u64) -> Option<Vec<TaskId>> {
+        self.abort_on_failure.remove(&group_id)?;
+        Some(self.remove_tasks_where(|info| info.group_id == Some(group_id)))
+    }
+
+    /// The ids of the scheduled tasks of a group, in ascending order.
+    pub(crate) fn group_task_ids(&self, group_id: u64) -> Vec<TaskId> {
+        let mut task_ids: Vec<TaskId> = self
+            .task_tracker_map
+            .iter()
+            .filter(|info| info.group_id == Some(group_id))
+            .map(|info| info.task_id)
+            .collect();
+        task_ids.sort_unstable();
+        task_ids
+    }
+
+    /// Find the ids of the tasks tagged with `key=value`, in ascending order.
+    pub fn find_tasks_by_tag(&self, key: &str, value: &str) -> Vec<TaskId> {
+        let mut task_ids: Vec<TaskId> = self
+            .task_tracker_map
+            .iter()
+            .filter(|info| info.tags.get(key).is_some_and(|v| v == value))
+            .map(|info| info.task_id)
+            .collect();
+        task_ids.sort_unstable();
+        task_ids
+    }
+
+    /// Count the scheduled tasks per value of the tag `key`, e.g. the tasks of each tenant.
+    /// Tasks without the tag are left out.
+    pub fn task_count_by_tag(&self, key: &str) -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        for info in self.task_tracker_map.iter() {
+            if let Some(value) = info.tags.get(key) {
+                *counts.entry(value.clone()).or_default() += 1;
+            }
+        }
+        counts
+    }
+
+    /// The number of scheduled tasks in the second, minute and hour wheel, to check that the
+    /// granularity of the wheels fits the workload.
+    pub fn level_distribution(&self) -> (usize, usize, usize) {
+        let mut distribution = (0, 0, 0);
+        for info in self.task_tracker_map.iter() {
+            match info.wheel_type {
+                WheelType::Second => distribution.0 += 1,
+                WheelType::Minute => distribution.1 += 1,
+                WheelType::Hour => distribution.2 += 1,
+            }
+        }
+        distribution
+    }
+
+    /// A rough estimate of the memory the wheels take in bytes, for capacity planning: the
+    /// capacities of the slots and of the task tracker, and the names, tags and dependencies of
+    /// the tasks. Runners, their state and the overhead of the allocator are not counted.
+    pub fn estimated_memory_bytes(&self) -> usize {
+        let mut bytes = size_of::<Self>();
+        for wheel in [&self.sec_wheel, &self.min_wheel, &self.hour_wheel] {
+            bytes += wheel.slots.capacity() * size_of::<(u64, Slot)>();
+            for slot in wheel.slots.iter() {
+                bytes += slot.task_map.capacity() * size_of::<(TaskId, Task)>();
+                bytes += slot
+                    .task_map
+                    .values()
+                    .map(|task| heap_bytes(&task.name, &task.tags, &task.depends_on))
+                    .sum::<usize>();
+            }
+        }
+        bytes += self.task_tracker_map.capacity() * size_of::<(TaskId, TaskTrackingInfo)>();
+        bytes += self
+            .task_tracker_map
+            .iter()
+            .map(|info| heap_bytes(&info.name, &info.tags, &info.depends_on))
+            .sum::<usize>();
+        bytes + self.completions.capacity() * size_of::<(TaskId, u64)>()
+    }
+
+    /// Check that every task in a slot has a tracker entry pointing at that slot and every
+    /// tracker entry has its task in a slot, for debugging the cascade and reschedule logic.
+    /// The discrepancies are reported in ascending order of task id.
+    ///
+    /// Meant for a wheel at rest, tasks moved by a concurrent tick or add may be reported.
+    pub fn audit(&self) -> Result<(), Vec<AuditError>> {
+        let mut found: HashMap<TaskId, Vec<(WheelType, u64)>> = HashMap::new();
+        for wheel_type in [WheelType::Second, WheelType::Minute, WheelType::Hour] {
+            for slot in self.wheel(wheel_type).slots.iter() {
+                for &task_id in slot.task_map.keys() {
+                    found
+                        .entry(task_id)
+                        .or_default()
+                        .push((wheel_type, *slot.key()));
+                }
+            }
+        }
+
+        let mut errors = vec![];
+        for info in self.task_tracker_map.iter() {
+            let tracked = (info.wheel_type, info.slot_num);
+            match found.remove(&info.task_id) {
+                None => errors.push(AuditError::Missing {
+                    task_id: info.task_id,
+                    wheel_type: tracked.0,
+                    slot_num: tracked.1,
+                }),
+                Some(locations) => errors.extend(
+                    locations
+                        .into_iter()
+                        .filter(|&location| location != tracked)
+                        .map(|location| AuditError::Misplaced {
+                            task_id: info.task_id,
+                            tracked,
+                            found: location,
+                        }),
+                ),
+            }
+        }
+        for (task_id, locations) in found {
+            errors.extend(locations.into_iter().map(|(wheel_type, slot_num)| {
+                AuditError::Untracked {
+                    task_id,
+                    wheel_type,
+                    slot_num,
+                }
+            }));
+        }
+
+        if errors.is_empty() {
+            return Ok(());
+        }
+        errors.sort_by_key(|error| match error {
+            AuditError::Untracked { task_id, .. }
+            | AuditError::Missing { task_id, .. }
+            | AuditError::Misplaced { task_id, .. } => *task_id,
+        });
+        Err(errors)
+    }
+
+    /// Reconcile the discrepancies `audit` finds, returning how many were fixed: tracker entries
+    /// of tasks in no slot are dropped, tasks in a slot without a tracker entry are removed, and
+    /// a tracker entry pointing at the wrong slot follows its task.
+    ///
+    /// Each discrepancy is checked again before it is fixed, so a task moved meanwhile is left
+    /// alone.
+    pub fn gc_orphans(&self) -> usize {
+        let Err(errors) = self.audit() else {
+            return 0;
+        };
+        errors
+            .into_iter()
+            .filter(|error| self.reconcile(error))
+            .count()
+    }
+
+    fn reconcile(&self, error: &AuditError) -> bool {
+        match *error {
+            AuditError::Missing {
+                task_id,
+                wheel_type,
+                slot_num,
+            } => {
+                let Entry::Occupied(entry) = self.task_tracker_map.entry(task_id) else {
+                    return false;
+                };
+                if (entry.get().wheel_type, entry.get().slot_num) != (wheel_type, slot_num)
+                    || self.wheel(wheel_type).contains(task_id, slot_num)
+                {
+                    return false;
+                }
+                entry.remove();
+                true
+            }
+            AuditError::Untracked {
+                task_id,
+                wheel_type,
+                slot_num,
+            } => {
+                // Hold the vacant entry so the task isn't added back meanwhile
+                let Entry::Vacant(_entry) = self.task_tracker_map.entry(task_id) else {
+                    return false;
+                };
+                self.wheel(wheel_type)
+                    .remove_task(task_id, slot_num)
+                    .is_some()
+            }
+            AuditError::Misplaced {
+                task_id,
+                tracked,
+                found,
+            } => {
+                let Entry::Occupied(mut entry) = self.task_tracker_map.entry(task_id) else {
+                    return false;
+                };
+                if (entry.get().wheel_type, entry.get().slot_num) != tracked {
+                    return false;
+                }
+                // A stray copy beside the tracked task goes, otherwise the entry follows the task
+                if self.wheel(tracked.0).contains(task_id, tracked.1) {
+                    return self.wheel(found.0).remove_task(task_id, found.1).is_some();
+                }
+                let Some(slot) = self.wheel(found.0).slots.get(&found.1) else {
+                    return false;
+                };
+                let Some(task) = slot.task_map.get(&task_id) else {
+                    return false;
+                };
+                let info = entry.get_mut();
+                (info.wheel_type, info.slot_num) = found;
+                info.cascade_guide = task.cascade_guide;
+                info.alarm_timestamp = task.alarm_timestamp;
+                true
+            }
+        }
+    }
+
+    /// Remove every task tagged with `key=value`, returning how many were removed.
+    pub fn remove_tasks_by_tag(&self, key: &str, value: &str) -> usize {
+        self.remove_tasks_where(|info| info.tags.get(key).is_some_and(|v| v == value))
+            .len()
+    }
+
+    /// Remove every task whose tracking information matches the predicate, returning the ids
+    /// of the removed tasks in ascending order.
+    pub fn remove_tasks_where(&self, predicate: impl Fn(&TaskTrackingInfo) -> bool) -> Vec<TaskId> {
+        // Collect first, removing while iterating the tracker would deadlock
+        let task_ids: Vec<TaskId> = self
+            .task_tracker_map
+            .iter()
+            .filter(|info| predicate(info))
+            .map(|info| info.task_id)
+            .collect();
+        let mut removed: Vec<TaskId> = task_ids
+            .into_iter()
+            .filter(|&task_id| self.remove_task(task_id).is_some())
+            .collect();
+        removed.sort_unstable();
+        removed
+    }
+
+    /// Reposition the tasks whose alarm was already past when they were scheduled, e.g. after
+    /// restoring an old snapshot, returning how many were rescheduled. By default such tasks
+    /// fire on the next tick and then continue with their sequence, which may be in the past
+    /// as well.
+    ///
+    /// With `OverduePolicy::FireOnce` each task still fires on the next tick, but once for all
+    /// its missed alarms, and then continues with its first alarm after now. With
+    /// `OverduePolicy::SkipToNext` the task doesn't fire for the missed alarms at all and moves
+    /// to its first alarm after now; a task without one, such as a once task, is removed.
+    pub fn reschedule_overdue(&self, policy: OverduePolicy) -> usize {
+        let now = self.current_timestamp();
+        let mut task_ids: Vec<TaskId> = self
+            .task_tracker_map
+            .iter()
+            .filter(|info| info.missed_alarm.is_some())
+            .map(|info| info.task_id)
+            .collect();
+        task_ids.sort_unstable();
+
+        let mut rescheduled = 0;
+        for task_id in task_ids {
+            let Entry::Occupied(mut entry) = self.task_tracker_map.entry(task_id) else {
+                continue;
+            };
+            let (wheel_type, slot_num) = (entry.get().wheel_type, entry.get().slot_num);
+            let Some(mut task) = self.wheel(wheel_type).remove_task(task_id, slot_num) else {
+                continue;
             };
+            Self::skip_missed_alarms(&mut task, now);
+            let alarm_timestamp = match policy {
+                OverduePolicy::FireOnce => Some(now + 1),
+                OverduePolicy::SkipToNext => task.next_alarm_timestamp(),
+            };
+            match alarm_timestamp.map(|alarm| self.locate(&mut task, alarm)) {
+                Some(Ok((wheel_type, slot_num))) => {
+                    let enabled = entry.get().enabled;
+                    *entry.get_mut() = Self::tracking_info(&task, wheel_type, slot_num, enabled);
+                    self.wheel(wheel_type).add_task(task, slot_num);
+                }
+                _ => {
+                    entry.remove();
+                }
+            }
+            rescheduled += 1;
+        }
+        rescheduled
+    }
+
+    /// Remove and return every task whose alarm is not after the current timestamp, wherever
+    /// it waits, e.g. after a restore left the hands behind the clock so that overdue tasks
+    /// still sit in minute and hour slots. A task placed late keeps its missed alarm, see
+    /// `TaskTrackingInfo::missed_alarm`, and counts as overdue by it.
+    ///
+    /// The tasks are ordered as they would fire, disabled tasks included. The hands are not
+    /// moved; `requeue` puts a fired task back for its next alarm.
+    pub(crate) fn collect_overdue(&self) -> Vec<Task> {
+        let now = self.current_timestamp();
+        let mut task_ids: Vec<TaskId> = self
+            .task_tracker_map
+            .iter()
+            .filter(|info| info.missed_alarm.unwrap_or(info.alarm_timestamp) <= now)
+            .map(|info| info.task_id)
+            .collect();
+        task_ids.sort_unstable();
+        let mut overdue: Vec<Task> = task_ids
+            .into_iter()
+            .filter_map(|task_id| self.remove_task(task_id))
+            .collect();
+        overdue.sort_by_key(|task| {
+            (
+                task.missed_alarm.unwrap_or(task.alarm_timestamp),
+                Reverse(task.priority),
+                self.tie_break.key(task),
+                task.task_id,
+            )
+        });
+        overdue
+    }
+
+    /// Put a task that just fired back for its next alarm. With `MissedPolicy::Skip` the alarms
+    /// the wheels have already passed are skipped, with `CatchUp` they fire one per tick.
+    ///
+    /// The next alarm is at least the next tick, never the slot the task was just taken from,
+    /// so a task repeating every second fires once per tick.
+    pub(crate) fn requeue(&self, mut task: Task) -> Result<(), TaskError> {
+        if task.missed_policy == MissedPolicy::Skip {
+            Self::skip_missed_alarms(&mut task, self.current_timestamp());
+        }
+        self.schedule(task)
+    }
+
+    fn skip_missed_alarms(task: &mut Task, now: u64) {
+        while task
+            .frequency
+            .peek_alarm_timestamp()
+            .is_some_and(|next| next <= now)
+        {
+            task.next_alarm_timestamp();
+        }
+    }
+
+    /// Count the frequency of a task being added from now, delaying its first alarm by its
+    /// jitter.
+    fn start_frequency(&self, task: &mut Task) {
+        if task.jitter > 0 {
+            task.frequency
+                .delay_start(self.rand.below(task.jitter.saturating_add(1)));
+        }
+        task.frequency.start(self.current_timestamp());
+        if let Some(AutoCancel::After(seconds)) = task.auto_cancel {
+            task.auto_cancel = Some(AutoCancel::At(
+                self.current_timestamp().saturating_add(seconds),
+            ));
+        }
+    }
+
+    /// Fail with `TaskError::CapacityExceeded` if no more tasks can be scheduled.
+    ///
+    /// Checked before the task is inserted, as the tracker can't be counted while an entry of
+    /// it is held, so adds racing for the last place may overshoot the cap by one each.
+    fn check_capacity(&self) -> Result<(), TaskError> {
+        match self.max_tasks {
+            Some(max_tasks) if self.len() >= max_tasks => {
+                Err(TaskError::CapacityExceeded(max_tasks))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check whether `add_tasks` would add each task, without changing the wheels: the same
+    /// frequency, capacity, dependency, duplicate id and horizon checks are made, in order.
+    ///
+    /// The tasks are checked as a batch, so a task repeating the id of an earlier valid task
+    /// is a duplicate, and the earlier valid tasks count against the maximum of tasks. Jitter
+    /// is counted at its maximum.
+    pub fn validate(&self, tasks: &[Task]) -> Vec<Result<(), TaskError>> {
+        let mut accepted = HashSet::new();
+        tasks
+            .iter()
+            .map(|task| {
+                let result = self.validate_task(task, &accepted);
+                if result.is_ok() {
+                    accepted.insert(task.task_id);
+                }
+                result
+            })
+            .collect()
+    }
+
+    fn validate_task(&self, task: &Task, accepted: &HashSet<TaskId>) -> Result<(), TaskError> {
+        if let Some(frequency) = task.frequency_seconds {
+            frequency.validate()?;
+        }
+        if let Some(max_tasks) = self.max_tasks
+            && self.len() + accepted.len() >= max_tasks
+        {
+            return Err(TaskError::CapacityExceeded(max_tasks));
+        }
+        let now = self.current_timestamp();
+        let mut frequency = task.frequency.clone();
+        if task.jitter > 0 {
+            frequency.delay_start(task.jitter);
+        }
+        frequency.start(now);
+        let Some(alarm_timestamp) = frequency.next_alarm_timestamp() else {
+            return Ok(());
+        };
+        self.check_dependency_cycle(task)?;
+        if self.contains(task.task_id) || accepted.contains(&task.task_id) {
+            return Err(TaskError::DuplicateTask(task.task_id));
+        }
+        let requested = alarm_timestamp.saturating_sub(now).max(1);
+        let max = self.max_delay();
+        if requested > max {
+            return Err(TaskError::DelayTooLong { requested, max });
+        }
+        Ok(())
+    }
+
+    /// Add task and initialize tracking information, failing if the id is already scheduled
+    /// or the maximum of tasks is reached.
+    pub fn add_task(&self, task: Task) -> Result<(), TaskError> {
+        self.check_capacity()?;
+        self.schedule(task)
+    }
+
+    /// Add a task regardless of the maximum of tasks, e.g. one that is already counted.
+    fn schedule(&self, mut task: Task) -> Result<(), TaskError> {
+        if let Some(guide) = task.placement.take() {
+            self.place(&mut task, guide)?;
+        }
+        self.start_frequency(&mut task);
+        let next_exec_timestamp = match task.next_alarm_timestamp() {
+            Some(t) => t,
+            None => return Ok(()),
+        };
+        if task
+            .auto_cancel_at()
+            .is_some_and(|deadline| next_exec_timestamp > deadline)
+        {
+            return Err(TaskError::TaskExpired(task.task_id));
+        }
+
+        self.add_task_at(task, next_exec_timestamp)
+    }
+
+    /// Start the frequency of a task at the alarm the cascade guide leads to, so it is placed
+    /// where the guide points. The guide has to be what the wheels would work out for that
+    /// alarm themselves, anything else would never fire in time.
+    fn place(&self, task: &mut Task, guide: WheelCascadeGuide) -> Result<(), TaskError> {
+        let invalid = |message: String| Err(TaskError::InvalidPlacement(message));
+        let (sec_slots, min_slots, hour_slots) = (
+            self.sec_wheel.num_slots,
+            self.min_wheel.num_slots,
+            self.hour_wheel.num_slots,
+        );
+        if guide.sec >= sec_slots
+            || guide.min.is_some_and(|min| min >= min_slots)
+            || guide.hour.is_some_and(|hour| hour >= hour_slots)
+        {
+            return invalid(format!(
+                "{guide:?} is outside of wheels of {sec_slots}, {min_slots} and {hour_slots} slots"
+            ));
+        }
+        let wheel_type = match (guide.min, guide.hour) {
+            (None, Some(_)) => {
+                return invalid(format!("{guide:?} has an hour slot but no minute slot"));
+            }
+            (Some(_), Some(_)) => WheelType::Hour,
+            (Some(_), None) => WheelType::Minute,
+            (None, None) => WheelType::Second,
+        };
+        let ticks = self.ticks_until(wheel_type, guide);
+        if ticks == 0 || self.cal_next_hand_position(ticks) != guide {
+            return invalid(format!(
+                "{guide:?} is not ahead of the hands at {:?}",
+                self.get_wheel_positions()
+            ));
+        }
+        // Alarms are in whole seconds, so only the slot a second starts in can be reached
+        let fire_tick = self.current_tick() + ticks;
+        if !fire_tick.is_multiple_of(self.ticks_per_second) {
+            return invalid(format!(
+                "{guide:?} is not the start of a second with {} ticks per second",
+                self.ticks_per_second
+            ));
+        }
+        let Some(frequency) = task.frequency_seconds else {
+            return Err(TaskError::invalid_frequency(
+                InvalidFrequencyReason::Unsupported,
+                None,
+                "a custom schedule can't be placed",
+            ));
+        };
+        task.frequency = FrequencyState::resume(frequency, fire_tick / self.ticks_per_second);
+        Ok(())
+    }
+
+    /// Add a task whose first alarm is `offset_secs` seconds after the next alarm of the
+    /// scheduled task `reference`, later alarms follow from the frequency of the task. Jitter
+    /// isn't applied.
+    ///
+    /// Fails with `TaskError::TaskNotFound` if `reference` isn't scheduled, and for a task with
+    /// a custom schedule, which picks its own alarms.
+    pub fn add_task_after(
+        &self,
+        reference: TaskId,
+        offset_secs: u64,
+        mut task: Task,
+    ) -> Result<(), TaskError> {
+        let anchor = self
+            .next_fire_time(reference)
+            .ok_or(TaskError::TaskNotFound(reference))?;
+        let Some(frequency) = task.frequency_seconds else {
+            return Err(TaskError::invalid_frequency(
+                InvalidFrequencyReason::Unsupported,
+                None,
+                "a custom schedule can't be offset from another task",
+            ));
+        };
+        self.check_capacity()?;
+        task.frequency = FrequencyState::resume(frequency, anchor.saturating_add(offset_secs));
+        match task.next_alarm_timestamp() {
+            Some(alarm_timestamp) => self.add_task_at(task, alarm_timestamp),
+            None => Ok(()),
+        }
+    }
+
+    /// Replace the frequency of a scheduled task in place, its next alarm is counted from now.
+    /// The task moves to the wheel and slot of the new alarm, e.g. from the minute wheel to the
+    /// second wheel when a 90 second interval becomes 10 seconds.
+    ///
+    /// Everything else about the task is kept, including whether it is enabled and the state of
+    /// its runner. A frequency that is exhausted right away removes the task.
+    pub fn update_frequency(
+        &self,
+        task_id: TaskId,
+        frequency: FrequencySeconds,
+    ) -> Result<(), TaskError> {
+        frequency.validate()?;
+        let Entry::Occupied(mut entry) = self.task_tracker_map.entry(task_id) else {
+            return Err(TaskError::TaskNotFound(task_id));
+        };
+        let (wheel_type, slot_num) = (entry.get().wheel_type, entry.get().slot_num);
+        let mut task = self
+            .wheel(wheel_type)
+            .remove_task(task_id, slot_num)
+            .ok_or(TaskError::TaskNotFound(task_id))?;
+
+        let mut frequency_state = FrequencyState::starting_at(frequency, self.current_timestamp());
+        let Some(next_exec_timestamp) = frequency_state.next_alarm_timestamp() else {
+            entry.remove();
+            return Ok(());
+        };
+        let previous = std::mem::replace(&mut task.frequency, frequency_state);
+        match self.locate(&mut task, next_exec_timestamp) {
+            Ok((wheel_type, slot_num)) => {
+                task.frequency_seconds = Some(frequency);
+                let enabled = entry.get().enabled;
+                *entry.get_mut() = Self::tracking_info(&task, wheel_type, slot_num, enabled);
+                self.wheel(wheel_type).add_task(task, slot_num);
+                Ok(())
+            }
+            Err(e) => {
+                // Leave the task as it was
+                task.frequency = previous;
+                self.wheel(wheel_type).add_task(task, slot_num);
+                Err(e)
+            }
+        }
+    }
+
+    /// Move the next alarm of a scheduled task to `timestamp`, e.g. to run it at exactly 14:00
+    /// today. Later alarms follow the frequency of the task from there on, and a countdown
+    /// keeps the number of alarms it has left.
+    ///
+    /// Fails with `TaskError::TaskNotFound` if the task isn't scheduled, and with
+    /// `TaskError::InvalidFrequency` if `timestamp` is not in the future or the task has a
+    /// custom schedule, which picks its own alarms. The task is left as it was on failure.
+    pub fn set_next_fire(&self, task_id: TaskId, timestamp: u64) -> Result<(), TaskError> {
+        if timestamp <= self.current_timestamp() {
+            return Err(TaskError::invalid_frequency(
+                InvalidFrequencyReason::PastTimestamp,
+                Some(timestamp),
+                format!("timestamp {timestamp} is not in the future"),
+            ));
+        }
+        let Entry::Occupied(mut entry) = self.task_tracker_map.entry(task_id) else {
+            return Err(TaskError::TaskNotFound(task_id));
+        };
+        let (wheel_type, slot_num) = (entry.get().wheel_type, entry.get().slot_num);
+        let mut task = self
+            .wheel(wheel_type)
+            .remove_task(task_id, slot_num)
+            .ok_or(TaskError::TaskNotFound(task_id))?;
+        let Some(frequency) = task.frequency_seconds else {
+            self.wheel(wheel_type).add_task(task, slot_num);
+            return Err(TaskError::invalid_frequency(
+                InvalidFrequencyReason::Unsupported,
+                None,
+                format!("task {task_id} has a custom schedule, its next alarm can't be set"),
+            ));
+        };
+
+        let mut frequency_state = FrequencyState::resume(frequency, timestamp);
+        frequency_state.next_alarm_timestamp();
+        if let Some(remaining) = task.frequency.remaining_count() {
+            frequency_state.set_remaining_count(remaining);
+        }
+        let previous = std::mem::replace(&mut task.frequency, frequency_state);
+        match self.locate(&mut task, timestamp) {
+            Ok((wheel_type, slot_num)) => {
+                let enabled = entry.get().enabled;
+                *entry.get_mut() = Self::tracking_info(&task, wheel_type, slot_num, enabled);
+                self.wheel(wheel_type).add_task(task, slot_num);
+                Ok(())
+            }
+            Err(e) => {
+                // Leave the task as it was
+                task.frequency = previous;
+                self.wheel(wheel_type).add_task(task, slot_num);
+                Err(e)
+            }
+        }
+    }
+
+    /// Add tasks in a batch, returning the result of each task in order.
+    ///
+    /// Every task is located first so the tracker and each slot grow once for the whole batch.
+    pub fn add_tasks(&self, tasks: Vec<Task>) -> Vec<Result<(), TaskError>> {
+        self.reserve(tasks.len());
+        let ticks = self.ticks.load(Ordering::Relaxed);
+        let mut slot_sizes: HashMap<(WheelType, u64), usize> = HashMap::new();
+        let located: Vec<_> = tasks
+            .into_iter()
+            .map(|mut task| {
+                self.start_frequency(&mut task);
+                let placement = task.next_alarm_timestamp().map(|alarm_timestamp| {
+                    let location = self.locate(&mut task, alarm_timestamp)?;
+                    *slot_sizes.entry(location).or_default() += 1;
+                    Ok((alarm_timestamp, location))
+                });
+                (task, placement)
+            })
+            .collect();
+        for ((wheel_type, slot_num), additional) in slot_sizes {
+            self.wheel(wheel_type).reserve(slot_num, additional);
+        }
+
+        located
+            .into_iter()
+            .map(|(mut task, placement)| {
+                let Some((alarm_timestamp, location)) = placement.transpose()? else {
+                    return Ok(());
+                };
+                self.check_capacity()?;
+                self.check_dependency_cycle(&task)?;
+                let Entry::Vacant(entry) = self.task_tracker_map.entry(task.task_id) else {
+                    return Err(TaskError::DuplicateTask(task.task_id));
+                };
+                // The wheels ticked since the task was located
+                let (wheel_type, slot_num) = if self.ticks.load(Ordering::Relaxed) == ticks {
+                    location
+                } else {
+                    self.locate(&mut task, alarm_timestamp)?
+                };
+                let _tracking_info =
+                    entry.insert(Self::tracking_info(&task, wheel_type, slot_num, true));
+                self.wheel(wheel_type).add_task(task, slot_num);
+                Ok(())
+            })
+            .collect()
+    }
+
+    /// Make room in the tracker for at least `additional` more tasks.
+    pub(crate) fn reserve(&self, additional: usize) {
+        let shards = self.task_tracker_map.shards();
+        let per_shard = additional.div_ceil(shards.len());
+        for shard in shards {
+            shard.write().reserve(per_shard, |(task_id, _)| {
+                self.task_tracker_map.hash_usize(task_id) as u64
+            });
+        }
+    }
+
+    /// Add task for an alarm at the given timestamp, without advancing its frequency state.
+    /// An alarm that is already due fires on the next tick.
+    pub(crate) fn add_task_at(
+        &self,
+        mut task: Task,
+        alarm_timestamp: u64,
+    ) -> Result<(), TaskError> {
+        self.check_dependency_cycle(&task)?;
+        let Entry::Vacant(entry) = self.task_tracker_map.entry(task.task_id) else {
+            return Err(TaskError::DuplicateTask(task.task_id));
+        };
+        let (wheel_type, slot_num) = self.locate(&mut task, alarm_timestamp)?;
+        // Keep the entry locked until the task is in its slot
+        let _tracking_info = entry.insert(Self::tracking_info(&task, wheel_type, slot_num, true));
+        self.wheel(wheel_type).add_task(task, slot_num);
+        Ok(())
+    }
+
+    /// The longest delay in seconds the wheels can schedule: a day per revolution of the hour
+    /// wheel by default, for up to `MAX_ROUNDS` revolutions unless configured otherwise.
+    pub(crate) fn max_delay(&self) -> u64 {
+        self.sec_wheel.num_slots
+            * self.min_wheel.num_slots
+            * self.hour_wheel.num_slots
+            * self.max_rounds
+            / self.ticks_per_second
+    }
+
+    /// Work out where the task goes for the alarm, updating its cascade guide.
+    /// Returns the wheel and slot to put the task into.
+    fn locate(&self, task: &mut Task, alarm_timestamp: u64) -> Result<(WheelType, u64), TaskError> {
+        // A task is never placed into the current slot, it has already been dispatched.
+        let next_alarm_sec = alarm_timestamp
+            .saturating_sub(self.current_timestamp())
+            .max(1);
+        let max = self.max_delay();
+        if next_alarm_sec > max {
+            return Err(TaskError::DelayTooLong {
+                requested: next_alarm_sec,
+                max,
+            });
+        }
+        task.alarm_timestamp = self.current_timestamp() + next_alarm_sec;
+        let next_guide = self.cal_next_hand_position(self.ticks_until_second(task.alarm_timestamp));
+        task.cascade_guide = next_guide;
+        task.missed_alarm = (alarm_timestamp < task.alarm_timestamp).then_some(alarm_timestamp);
+        task.seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
 
-            removed_task
+        // Determine the wheel where the task should be placed based on the calculated cascade guide
+        Ok(if let Some(hour) = next_guide.hour {
+            (WheelType::Hour, hour)
+        } else if let Some(min) = next_guide.min {
+            (WheelType::Minute, min)
         } else {
-            None
+            (WheelType::Second, next_guide.sec)
+        })
+    }
+
+    fn tracking_info(
+        task: &Task,
+        wheel_type: WheelType,
+        slot_num: u64,
+        enabled: bool,
+    ) -> TaskTrackingInfo {
+        TaskTrackingInfo {
+            task_id: task.task_id,
+            name: task.name.clone(),
+            tags: task.tags.clone(),
+            cascade_guide: task.cascade_guide,
+            wheel_type,
+            slot_num,
+            enabled,
+            alarm_timestamp: task.alarm_timestamp,
+            depends_on: task.depends_on.clone(),
+            group_id: task.group_id,
+            missed_alarm: task.missed_alarm,
+            frequency: task.frequency_seconds,
+        }
+    }
+
+    fn wheel(&self, wheel_type: WheelType) -> &Wheel {
+        match wheel_type {
+            WheelType::Second => &self.sec_wheel,
+            WheelType::Minute => &self.min_wheel,
+            WheelType::Hour => &self.hour_wheel,
+        }
+    }
+
+    /// Update task tracking information when cascading from minute wheel to second wheel
+    pub fn cascade_minute_tasks(&self) {
+        self.minute_cascades.fetch_add(1, Ordering::Relaxed);
+        let hand = self.min_wheel.hand_position();
+        for task_id in self.min_wheel.task_ids(hand) {
+            let Some(mut tracking_info) = self.task_tracker_map.get_mut(&task_id) else {
+                continue;
+            };
+            let Some(task) = self.min_wheel.remove_task(task_id, hand) else {
+                continue;
+            };
+            let slot_num = task.cascade_guide.sec;
+            tracking_info.wheel_type = WheelType::Second;
+            tracking_info.slot_num = slot_num;
+            tracking_info.cascade_guide = task.cascade_guide;
+
+            // Add task to second wheel
+            self.sec_wheel.add_task(task, slot_num);
+        }
+        self.min_wheel.shrink_slot(hand);
+    }
+
+    /// Update task tracking information when cascading from hour wheel to minute wheel
+    pub fn cascade_hour_tasks(&self) {
+        self.hour_cascades.fetch_add(1, Ordering::Relaxed);
+        let hand = self.hour_wheel.hand_position();
+        for task_id in self.hour_wheel.task_ids(hand) {
+            let Some(mut tracking_info) = self.task_tracker_map.get_mut(&task_id) else {
+                continue;
+            };
+            // Tasks with rounds left wait for the hour hand to come around again
+            if !tracking_info.cascade_guide.is_arrived() {
+                continue;
+            }
+            let Some(task) = self.hour_wheel.remove_task(task_id, hand) else {
+                continue;
+            };
+
+            // Move from hour wheel to minute wheel
+            let slot_num = task.cascade_guide.min.unwrap_or_default();
+            tracking_info.wheel_type = WheelType::Minute;
+            tracking_info.slot_num = slot_num;
+            tracking_info.cascade_guide = task.cascade_guide;
+
+            self.min_wheel.add_task(task, slot_num);
         }
+        self.hour_wheel.shrink_slot(hand);
+    }
+
+    /// Call `f` with every scheduled task, from the second wheel up to the hour wheel.
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub(crate) fn for_each_task(&self, mut f: impl FnMut(&Task)) {
+        self.sec_wheel.for_each_task(&mut f);
+        self.min_wheel.for_each_task(&mut f);
+        self.hour_wheel.for_each_task(&mut f);
+    }
+
+    /// Encode the metadata of the scheduled tasks like a JSON snapshot, but in a compact
+    /// binary form for large schedules. Fails if a task has a custom schedule.
+    #[cfg(feature = "bincode")]
+    pub(crate) fn snapshot_bincode(&self) -> Result<Vec<u8>, TaskError> {
+        ScheduleSnapshot::capture(self)?.to_bytes()
+    }
+
+    /// Add the tasks encoded by `snapshot_bincode`, resolving their runners through the
+    /// registry. Alarms that are already past fire on the next tick.
+    #[cfg(feature = "bincode")]
+    pub(crate) fn restore_bincode(
+        &self,
+        bytes: &[u8],
+        registry: &RunnerRegistry,
+    ) -> Result<(), TaskError> {
+        ScheduleSnapshot::from_bytes(bytes)?.restore(self, registry)
+    }
+
+    /// Remove task and clean up from tracking map.
+    ///
+    /// The pending alarm is put back into the frequency of the returned task, so adding it again
+    /// continues the schedule where it was rather than skipping an alarm.
+    pub fn remove_task(&self, task_id: TaskId) -> Option<Task> {
+        let Entry::Occupied(entry) = self.task_tracker_map.entry(task_id) else {
+            return None;
+        };
+        // Remove task from corresponding wheel
+        let (wheel_type, slot_num) = (entry.get().wheel_type, entry.get().slot_num);
+        let task = self.wheel(wheel_type).remove_task(task_id, slot_num);
+        entry.remove();
+        // A removed task that never completed won't, see `remove_orphaned`
+        self.completions.remove_if(&task_id, |_, count| *count == 0);
+        task.map(|mut task| {
+            task.frequency
+                .rewind(task.frequency_seconds, task.alarm_timestamp);
+            task
+        })
+    }
+
+    /// Remove a task like `remove_task`, returning only its tracking information, for cleanup
+    /// paths that have no use for the task. The task and its runner are dropped in place.
+    pub fn remove_task_info(&self, task_id: TaskId) -> Option<TaskTrackingInfo> {
+        let Entry::Occupied(entry) = self.task_tracker_map.entry(task_id) else {
+            return None;
+        };
+        // Take the task out of its slot while the entry is held, so it can't move meanwhile
+        let (wheel_type, slot_num) = (entry.get().wheel_type, entry.get().slot_num);
+        drop(self.wheel(wheel_type).remove_task(task_id, slot_num));
+        let info = entry.remove();
+        self.completions.remove_if(&task_id, |_, count| *count == 0);
+        Some(info)
     }
 }
 
 // Implement remove_task method for Wheel
 impl Wheel {
+    /// Call `f` with every task in the wheel.
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub(crate) fn for_each_task(&self, mut f: impl FnMut(&Task)) {
+        for slot in self.slots.iter() {
+            slot.task_map.values().for_each(&mut f);
+        }
+    }
+
+    pub(crate) fn contains(&self, task_id: TaskId, slot_num: u64) -> bool {
+        self.slots
+            .get(&slot_num)
+            .is_some_and(|slot| slot.task_map.contains_key(&task_id))
+    }
+
+    /// The ids of the tasks in the slot.
+    pub(crate) fn task_ids(&self, slot_num: u64) -> Vec<TaskId> {
+        self.slots
+            .get(&slot_num)
+            .map(|slot| slot.task_map.keys().copied().collect())
+            .unwrap_or_default()
+    }
+
     pub fn remove_task(&self, task_id: TaskId, slot_num: u64) -> Option<Task> {
         if let Some(mut slot) = self.slots.get_mut(&slot_num) {
             slot.remove_task(task_id)
@@ -482,42 +2384,576 @@ mod tests {
     }
 
     #[test]
-    fn test_tick_without_cascade() {
+    fn test_jump_across_rotations() {
         let multi_wheel = MulitWheel::new();
+        assert_eq!(multi_wheel.sec_wheel.jump(125), Some(2));
+        assert_eq!(multi_wheel.sec_wheel.hand_position(), 5);
+        // The minute hand moves by the carry, the same way as for a single rotation
+        assert_eq!(multi_wheel.min_wheel.jump(2), None);
+        assert_eq!(multi_wheel.min_wheel.hand_position(), 2);
 
-        // Test tick without any cascade (no carry-over between wheels)
-        // This verifies that the second wheel moves normally without triggering minute or hour cascades
-        let result = multi_wheel.tick();
-        assert_eq!(result, None);
-        assert_eq!(multi_wheel.sec_wheel.hand_position(), 1);
+        assert_eq!(multi_wheel.sec_wheel.jump(54), None);
+        assert_eq!(multi_wheel.sec_wheel.hand_position(), 59);
+        assert_eq!(multi_wheel.sec_wheel.jump(121), Some(3));
+        assert_eq!(multi_wheel.sec_wheel.hand_position(), 0);
+        assert_eq!(multi_wheel.sec_wheel.jump(0), None);
+        assert_eq!(multi_wheel.sec_wheel.hand_position(), 0);
 
-        // Test another tick to ensure continuous movement
-        let result = multi_wheel.tick();
-        assert_eq!(result, None);
-        assert_eq!(multi_wheel.sec_wheel.hand_position(), 2);
+        // A jump is what `resync` does, every task lands at its alarm whatever was jumped over
+        let wheel = MulitWheel::new();
+        let start = wheel.current_timestamp();
+        for (task_id, seconds) in [(1, 30), (2, 90), (3, 150), (4, 200)] {
+            wheel
+                .add_task(
+                    TaskBuilder::new(task_id)
+                        .with_frequency_once_by_seconds(seconds)
+                        .spwan_async(TestTaskRunner::new())
+                        .unwrap(),
+                )
+                .unwrap();
+        }
+        let overdue: Vec<TaskId> = wheel
+            .resync(start + 125)
+            .iter()
+            .map(|task| task.task_id)
+            .collect();
+        assert_eq!(overdue, [1, 2].map(TaskId::new));
+        assert_eq!(wheel.get_wheel_positions(), (5, 2, 0));
+        assert_eq!(wheel.next_fire_time(TaskId::new(3)), Some(start + 150));
+        assert_eq!(wheel.next_fire_time(TaskId::new(4)), Some(start + 200));
     }
 
+    #[cfg(debug_assertions)]
     #[test]
-    fn test_tick_with_minute_cascade() {
-        let multi_wheel = MulitWheel::new();
+    #[should_panic(expected = "skips the slots in between")]
+    fn test_hand_move_rejects_multi_slot_steps() {
+        let wheel = MulitWheel::new();
+        assert_eq!(wheel.sec_wheel.hand_move(1), None);
+        wheel.sec_wheel.hand_move(2);
+    }
 
-        // Add a task to minute wheel slot 0
-        let task = TaskBuilder::new(1)
-            .with_frequency_once_by_seconds(60)
-            .spwan_async(TestTaskRunner::new())
-            .unwrap();
-        multi_wheel.min_wheel.add_task(task, 0);
+    #[test]
+    fn test_fast_forward_cascades_every_minute_passed() {
+        let wheel = MulitWheel::new();
+        for (task_id, seconds) in [(1, 61), (2, 121), (3, 181)] {
+            wheel
+                .add_task(
+                    TaskBuilder::new(task_id)
+                        .with_frequency_once_by_seconds(seconds)
+                        .spwan_async(TestTaskRunner::new())
+                        .unwrap(),
+                )
+                .unwrap();
+        }
+        for (task_id, slot_num) in [(1, 1), (2, 2), (3, 3)] {
+            let location = wheel.task_location(TaskId::new(task_id)).unwrap();
+            assert_eq!(
+                (location.wheel_type, location.slot_num),
+                (WheelType::Minute, slot_num)
+            );
+        }
 
-        // Set second wheel hand position to 59 (last second of a minute)
-        // This will trigger a cascade to the minute wheel on the next tick
-        multi_wheel.sec_wheel.set_hand_position(59);
+        // One call, but ticked through all 180 seconds, so the minute hand passes slots 1 to 3
+        // one at a time and each of them is cascaded
+        let arrived: Vec<TaskId> = wheel
+            .fast_forward(180)
+            .iter()
+            .map(|task| task.task_id)
+            .collect();
+        assert_eq!(wheel.get_wheel_positions(), (0, 3, 0));
+        assert_eq!(arrived, [1, 2].map(TaskId::new));
+        let location = wheel.task_location(TaskId::new(3)).unwrap();
+        assert_eq!(
+            (location.wheel_type, location.slot_num),
+            (WheelType::Second, 1)
+        );
+        for slot_num in 1..=3 {
+            assert!(wheel.min_wheel.task_ids(slot_num).is_empty());
+        }
+    }
 
-        // Execute tick which should trigger minute cascade
-        // The task should be moved from minute wheel to second wheel for execution
-        multi_wheel.tick();
+    #[test]
+    fn test_one_second_repeat_fires_once_per_tick() {
+        let wheel = MulitWheel::new();
+        // Across the end of a minute, an hour and a day
+        wheel.set_wheel_positions(55, 59, 23);
+        wheel
+            .add_task(
+                TaskBuilder::new(1)
+                    .with_frequency_repeated_by_seconds(1)
+                    .spwan_async(TestTaskRunner::new())
+                    .unwrap(),
+            )
+            .unwrap();
 
-        // Verify that the task is no longer in the minute wheel slot 0
-        // It should have been cascaded down to the second wheel for execution
+        for _ in 0..10 {
+            wheel.tick();
+            let arrived = wheel.take_arrived_tasks();
+            assert_eq!(arrived.len(), 1);
+            for task in arrived {
+                wheel.requeue(task).unwrap();
+            }
+            // Put back for the next second, not into the slot just drained
+            assert!(wheel.take_arrived_tasks().is_empty());
+            assert_eq!(
+                wheel.next_fire_time(TaskId::new(1)),
+                Some(wheel.current_timestamp() + 1)
+            );
+        }
+        assert_eq!(wheel.audit(), Ok(()));
+    }
+
+    #[test]
+    fn test_seeded_jitter_is_reproducible() {
+        use crate::utils::rand::SeededRandSource;
+
+        fn jittered_delays(seed: u64) -> Vec<u64> {
+            let wheel = MulitWheelBuilder::new()
+                .with_rand_source(SeededRandSource::new(seed))
+                .build()
+                .unwrap();
+            let start = wheel.current_timestamp();
+            (1..=8)
+                .map(|task_id| {
+                    let task = TaskBuilder::new(task_id)
+                        .with_frequency_repeated_by_seconds(60)
+                        .with_jitter(Duration::from_secs(30))
+                        .spwan_async(TestTaskRunner::new())
+                        .unwrap();
+                    wheel.add_task(task).unwrap();
+                    wheel.next_fire_time(TaskId::new(task_id)).unwrap() - start
+                })
+                .collect()
+        }
+
+        let delays = jittered_delays(7);
+        assert_eq!(delays, jittered_delays(7));
+        assert_ne!(delays, jittered_delays(8));
+        assert!(delays.iter().all(|delay| (60..=90).contains(delay)));
+        assert!(delays.iter().any(|&delay| delay != delays[0]));
+    }
+
+    #[test]
+    fn test_jittered_start_of_aligned_repeat() {
+        use crate::utils::rand::SeededRandSource;
+
+        let mut jitters = vec![];
+        for seed in 1..=8 {
+            let wheel = MulitWheelBuilder::new()
+                .with_rand_source(SeededRandSource::new(seed))
+                .with_clock(|| Duration::from_secs(1_000_007))
+                .build()
+                .unwrap();
+            let task = TaskBuilder::new(1)
+                .with_frequency_repeated_by_seconds(60)
+                .with_aligned_repeat()
+                .with_jitter(Duration::from_secs(30))
+                .spwan_async(TestTaskRunner::new())
+                .unwrap();
+            wheel.add_task(task).unwrap();
+
+            // The first alarm is jittered off the next minute, at most by the jitter
+            let first = wheel.next_fire_time(TaskId::new(1)).unwrap();
+            assert!((1_000_020..=1_000_050).contains(&first));
+            jitters.push(first - 1_000_020);
+
+            // The second snaps back to the minute after
+            for _ in wheel.current_timestamp()..first {
+                wheel.tick();
+            }
+            assert_eq!(wheel.hand_over(wheel.take_arrived_tasks()).len(), 1);
+            assert_eq!(wheel.next_fire_time(TaskId::new(1)), Some(1_000_080));
+        }
+        assert!(jitters.iter().any(|&jitter| jitter != jitters[0]));
+    }
+
+    #[test]
+    fn test_set_next_fire_moves_task_and_keeps_cadence() {
+        let wheel = MulitWheel::new();
+        let task = TaskBuilder::new(TaskId::new(1))
+            .with_frequency_repeated_by_seconds(10)
+            .spwan_async(TestTaskRunner::new())
+            .unwrap();
+        wheel.set_wheel_positions(0, 0, 0);
+        wheel.add_task(task).unwrap();
+        let now = wheel.current_timestamp();
+
+        // 2 minutes and 5 seconds from now lands on the minute wheel
+        wheel.set_next_fire(TaskId::new(1), now + 125).unwrap();
+        let info = wheel.get_task_tracking_info(TaskId::new(1)).unwrap();
+        assert_eq!(info.wheel_type, WheelType::Minute);
+        assert_eq!(info.slot_num, 2);
+        assert_eq!(info.alarm_timestamp, now + 125);
+        assert_eq!(
+            wheel
+                .task_location(TaskId::new(1))
+                .unwrap()
+                .ticks_until_fire,
+            125
+        );
+
+        assert_eq!(wheel.fast_forward(125).len(), 1);
+        assert_eq!(wheel.next_fire_time(TaskId::new(1)), Some(now + 135));
+
+        assert!(matches!(
+            wheel.set_next_fire(TaskId::new(1), wheel.current_timestamp()),
+            Err(TaskError::InvalidFrequency { .. })
+        ));
+        assert_eq!(wheel.next_fire_time(TaskId::new(1)), Some(now + 135));
+        assert!(matches!(
+            wheel.set_next_fire(TaskId::new(2), now + 200),
+            Err(TaskError::TaskNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_collect_overdue_across_wheels() {
+        let wheel = MulitWheel::new();
+        wheel.set_wheel_positions(0, 0, 0);
+        for (task_id, seconds) in [(1, 30), (2, 300), (3, 7200), (4, 20_000)] {
+            wheel
+                .add_task(
+                    TaskBuilder::new(task_id)
+                        .with_frequency_once_by_seconds(seconds)
+                        .spwan_async(TestTaskRunner::new())
+                        .unwrap(),
+                )
+                .unwrap();
+        }
+        let wheel_type = |task_id| {
+            wheel
+                .get_task_tracking_info(TaskId::new(task_id))
+                .unwrap()
+                .wheel_type
+        };
+        assert_eq!(wheel_type(1), WheelType::Second);
+        assert_eq!(wheel_type(2), WheelType::Minute);
+        assert_eq!(wheel_type(3), WheelType::Hour);
+        assert!(wheel.collect_overdue().is_empty());
+
+        // The clock moves on while the hands stay behind, as after a stale restore
+        wheel.ticks.fetch_add(10_000, Ordering::Relaxed);
+        let overdue: Vec<TaskId> = wheel
+            .collect_overdue()
+            .iter()
+            .map(|task| task.task_id)
+            .collect();
+        assert_eq!(overdue, [1, 2, 3].map(TaskId::new));
+        assert_eq!(wheel.len(), 1);
+        assert!(wheel.contains(TaskId::new(4)));
+    }
+
+    #[test]
+    fn test_handed_over_overdue_tasks_stay_scheduled() {
+        let wheel = MulitWheel::new();
+        wheel
+            .add_task(
+                TaskBuilder::new(5)
+                    .with_frequency_repeated_by_seconds(300)
+                    .spwan_async(TestTaskRunner::new())
+                    .unwrap(),
+            )
+            .unwrap();
+        wheel.ticks.fetch_add(1_000, Ordering::Relaxed);
+
+        let overdue = wheel.hand_over(wheel.collect_overdue());
+        assert_eq!(overdue.len(), 1);
+        assert!(wheel.contains(TaskId::new(5)));
+        assert!(wheel.next_fire_time(TaskId::new(5)).unwrap() > wheel.current_timestamp());
+    }
+
+    #[test]
+    fn test_set_next_fire_keeps_countdown() {
+        let wheel = MulitWheel::new();
+        let task = TaskBuilder::new(TaskId::new(1))
+            .with_frequency_count_down_by_seconds(3, 5)
+            .spwan_async(TestTaskRunner::new())
+            .unwrap();
+        wheel.add_task(task).unwrap();
+        let now = wheel.current_timestamp();
+        assert_eq!(wheel.remaining_count(TaskId::new(1)), Some(3));
+
+        wheel.set_next_fire(TaskId::new(1), now + 30).unwrap();
+        assert_eq!(wheel.next_fire_time(TaskId::new(1)), Some(now + 30));
+        assert_eq!(wheel.remaining_count(TaskId::new(1)), Some(3));
+    }
+
+    #[test]
+    fn test_validate_batch_without_adding() {
+        let wheel = MulitWheel::new();
+        let every_minute = |task_id: u64| {
+            TaskBuilder::new(task_id)
+                .with_frequency_repeated_by_seconds(60)
+                .spwan_async(TestTaskRunner::new())
+                .unwrap()
+        };
+        wheel.add_task(every_minute(1)).unwrap();
+        let beyond_horizon = TaskBuilder::new(3)
+            .with_frequency_once_by_days(400)
+            .unwrap()
+            .spwan_async(TestTaskRunner::new())
+            .unwrap();
+        let batch = [
+            every_minute(2),
+            beyond_horizon,
+            every_minute(1),
+            every_minute(2),
+            every_minute(4),
+        ];
+
+        let results = wheel.validate(&batch);
+        assert!(results[0].is_ok());
+        assert!(matches!(
+            results[1],
+            Err(TaskError::DelayTooLong { requested, .. }) if requested == 400 * 24 * 60 * 60
+        ));
+        assert!(
+            matches!(results[2], Err(TaskError::DuplicateTask(task_id)) if task_id == TaskId::new(1))
+        );
+        assert!(
+            matches!(results[3], Err(TaskError::DuplicateTask(task_id)) if task_id == TaskId::new(2))
+        );
+        assert!(results[4].is_ok());
+        assert_eq!(wheel.len(), 1);
+
+        // The same outcome as adding the batch
+        let added = wheel.add_tasks(batch.to_vec());
+        let outcome = |results: &[Result<(), TaskError>]| {
+            results.iter().map(Result::is_ok).collect::<Vec<_>>()
+        };
+        assert_eq!(outcome(&added), outcome(&results));
+    }
+
+    #[test]
+    fn test_max_tasks_caps_scheduled_tasks() {
+        let wheel = MulitWheelBuilder::new().with_max_tasks(2).build().unwrap();
+        let task = |task_id: u64| {
+            TaskBuilder::new(task_id)
+                .with_frequency_repeated_by_seconds(1)
+                .spwan_async(TestTaskRunner::new())
+                .unwrap()
+        };
+        wheel.add_task(task(1)).unwrap();
+        wheel.add_task(task(2)).unwrap();
+        assert!(matches!(
+            wheel.add_task(task(3)),
+            Err(TaskError::CapacityExceeded(2))
+        ));
+        assert!(matches!(
+            wheel.add_tasks(vec![task(3)])[0],
+            Err(TaskError::CapacityExceeded(2))
+        ));
+        assert_eq!(wheel.len(), 2);
+
+        // Fired tasks are rescheduled at full capacity
+        wheel.tick();
+        assert_eq!(wheel.hand_over(wheel.take_arrived_tasks()).len(), 2);
+        assert_eq!(wheel.len(), 2);
+        wheel.remove_task(TaskId::new(1));
+        wheel.add_task(task(3)).unwrap();
+    }
+
+    #[test]
+    fn test_add_task_after_reference() {
+        let wheel = MulitWheel::new();
+        let task = |task_id: u64| {
+            TaskBuilder::new(task_id)
+                .with_frequency_repeated_by_seconds(10)
+                .spwan_async(TestTaskRunner::new())
+                .unwrap()
+        };
+        wheel.add_task(task(1)).unwrap();
+        wheel.fast_forward(3);
+        wheel.add_task_after(TaskId::new(1), 30, task(2)).unwrap();
+        let a_fire = wheel.next_fire_time(TaskId::new(1)).unwrap();
+        assert_eq!(wheel.next_fire_time(TaskId::new(2)), Some(a_fire + 30));
+        assert_eq!(wheel.fire_tick(TaskId::new(2)), Some(10 + 30));
+
+        assert!(matches!(
+            wheel.add_task_after(TaskId::new(7), 30, task(3)),
+            Err(TaskError::TaskNotFound(task_id)) if task_id == TaskId::new(7)
+        ));
+        assert!(!wheel.contains(TaskId::new(3)));
+    }
+
+    #[test]
+    fn test_upcoming_within_window() {
+        let wheel = MulitWheel::new();
+        for (task_id, seconds) in [(3, 300), (2, 30), (1, 5)] {
+            let task = TaskBuilder::new(task_id)
+                .with_frequency_once_by_seconds(seconds)
+                .spwan_async(TestTaskRunner::new())
+                .unwrap();
+            wheel.add_task(task).unwrap();
+        }
+        let now = wheel.current_timestamp();
+        assert_eq!(
+            wheel.upcoming(60),
+            vec![(TaskId::new(1), now + 5), (TaskId::new(2), now + 30)]
+        );
+        assert_eq!(wheel.upcoming(4), vec![]);
+        assert_eq!(wheel.upcoming(300).len(), 3);
+    }
+
+    #[test]
+    fn test_fire_tick_from_slot_and_hands() {
+        let wheel = MulitWheel::new();
+        let add = |task_id: u64, seconds: u64| {
+            let task = TaskBuilder::new(task_id)
+                .with_frequency_once_by_seconds(seconds)
+                .spwan_async(TestTaskRunner::new())
+                .unwrap();
+            wheel.add_task(task).unwrap();
+        };
+        add(1, 90);
+        assert_eq!(wheel.fire_tick(TaskId::new(1)), Some(90));
+
+        wheel.fast_forward(37);
+        let delays = [
+            5, 59, 60, 61, 90, 3599, 3600, 3661, 86_399, 86_400, 90_061, 200_000,
+        ];
+        for (task_id, seconds) in (2..).zip(delays) {
+            add(task_id, seconds);
+            assert_eq!(
+                wheel.fire_tick(TaskId::new(task_id)),
+                Some(37 + seconds),
+                "{seconds}s"
+            );
+        }
+        // The fire tick holds while the task cascades down
+        wheel.fast_forward(4000);
+        for (task_id, seconds) in (2..).zip(delays).filter(|&(_, seconds)| seconds > 4000) {
+            assert_eq!(
+                wheel.fire_tick(TaskId::new(task_id)),
+                Some(37 + seconds),
+                "{seconds}s"
+            );
+        }
+        assert_eq!(wheel.fire_tick(TaskId::new(1)), None);
+    }
+
+    #[test]
+    fn test_task_placed_by_cascade_guide() {
+        let wheel = MulitWheel::new();
+        let guide = WheelCascadeGuide {
+            sec: 5,
+            min: Some(10),
+            hour: Some(2),
+            round: 0,
+        };
+        let task = TaskBuilder::new(1)
+            .with_frequency_repeated_by_seconds(60)
+            .with_cascade_guide(guide)
+            .spwan_async(TestTaskRunner::new())
+            .unwrap();
+        wheel.add_task(task).unwrap();
+        let now = wheel.current_timestamp();
+        let location = wheel.task_location(TaskId::new(1)).unwrap();
+        assert_eq!(
+            (location.wheel_type, location.slot_num),
+            (WheelType::Hour, 2)
+        );
+        assert_eq!(location.ticks_until_fire, 2 * 3600 + 10 * 60 + 5);
+        assert_eq!(wheel.fire_tick(TaskId::new(1)), Some(7805));
+
+        // Cascaded into the minute wheel at 2:00:00 and the second wheel at 2:10:00
+        assert!(wheel.fast_forward(2 * 3600).is_empty());
+        let location = wheel.task_location(TaskId::new(1)).unwrap();
+        assert_eq!(
+            (location.wheel_type, location.slot_num),
+            (WheelType::Minute, 10)
+        );
+        assert!(wheel.fast_forward(10 * 60).is_empty());
+        let location = wheel.task_location(TaskId::new(1)).unwrap();
+        assert_eq!(
+            (location.wheel_type, location.slot_num),
+            (WheelType::Second, 5)
+        );
+        let arrived = wheel.fast_forward(5);
+        assert_eq!(arrived.len(), 1);
+        assert_eq!(arrived[0].alarm_timestamp, now + 7805);
+        assert_eq!(wheel.next_fire_time(TaskId::new(1)), Some(now + 7865));
+
+        let place = |task_id: u64, guide| {
+            wheel.add_task(
+                TaskBuilder::new(task_id)
+                    .with_frequency_once_by_seconds(1)
+                    .with_cascade_guide(guide)
+                    .spwan_async(TestTaskRunner::new())
+                    .unwrap(),
+            )
+        };
+        let (sec, min, hour) = wheel.get_wheel_positions();
+        for guide in [
+            // Beyond the 60 slots of the second wheel
+            WheelCascadeGuide {
+                sec: 60,
+                ..Default::default()
+            },
+            // The slot the second hand points at was dispatched already
+            WheelCascadeGuide {
+                sec,
+                ..Default::default()
+            },
+            // Due within the minute, so it belongs into the second wheel
+            WheelCascadeGuide {
+                sec: sec + 1,
+                min: Some(min),
+                hour: None,
+                round: 0,
+            },
+            WheelCascadeGuide {
+                sec: 0,
+                min: None,
+                hour: Some(hour + 1),
+                round: 0,
+            },
+        ] {
+            assert!(matches!(
+                place(2, guide),
+                Err(TaskError::InvalidPlacement(_))
+            ));
+        }
+        assert!(!wheel.contains(TaskId::new(2)));
+    }
+
+    #[test]
+    fn test_tick_without_cascade() {
+        let multi_wheel = MulitWheel::new();
+
+        // Test tick without any cascade (no carry-over between wheels)
+        // This verifies that the second wheel moves normally without triggering minute or hour cascades
+        let result = multi_wheel.tick();
+        assert_eq!(result, None);
+        assert_eq!(multi_wheel.sec_wheel.hand_position(), 1);
+
+        // Test another tick to ensure continuous movement
+        let result = multi_wheel.tick();
+        assert_eq!(result, None);
+        assert_eq!(multi_wheel.sec_wheel.hand_position(), 2);
+    }
+
+    #[test]
+    fn test_tick_with_minute_cascade() {
+        let multi_wheel = MulitWheel::new();
+
+        // Add a task to minute wheel slot 0
+        let task = TaskBuilder::new(1)
+            .with_frequency_once_by_seconds(60)
+            .spwan_async(TestTaskRunner::new())
+            .unwrap();
+        multi_wheel.min_wheel.add_task(task, 0);
+
+        // Set second wheel hand position to 59 (last second of a minute)
+        // This will trigger a cascade to the minute wheel on the next tick
+        multi_wheel.sec_wheel.set_hand_position(59);
+
+        // Execute tick which should trigger minute cascade
+        // The task should be moved from minute wheel to second wheel for execution
+        multi_wheel.tick();
+
+        // Verify that the task is no longer in the minute wheel slot 0
+        // It should have been cascaded down to the second wheel for execution
         assert!(
             !multi_wheel
                 .sec_wheel
@@ -525,7 +2961,7 @@ mod tests {
                 .get(&0)
                 .unwrap()
                 .task_map
-                .contains_key(&1)
+                .contains_key(&TaskId::new(1))
         );
     }
 
@@ -567,8 +3003,40 @@ mod tests {
                 .get(&0)
                 .unwrap()
                 .task_map
-                .contains_key(&2)
+                .contains_key(&TaskId::new(2))
+        );
+    }
+
+    #[test]
+    fn test_tick_arrives_across_day_boundary() {
+        let wheel = MulitWheel::new();
+        // 23:59:55
+        wheel.set_wheel_positions(55, 59, 23);
+
+        let task = TaskBuilder::new(3)
+            .with_frequency_once_by_seconds(10)
+            .spwan_async(TestTaskRunner::new())
+            .unwrap();
+        wheel.add_task(task).unwrap();
+        assert_eq!(
+            wheel
+                .get_task_tracking_info(TaskId::new(3))
+                .unwrap()
+                .wheel_type,
+            WheelType::Hour
         );
+
+        for _ in 0..9 {
+            wheel.tick();
+            assert!(wheel.take_arrived_tasks().is_empty());
+        }
+
+        // The round is consumed by the day carry, so the task arrives at 00:00:05
+        wheel.tick();
+        let arrived = wheel.take_arrived_tasks();
+        assert_eq!(arrived.len(), 1);
+        assert_eq!(arrived[0].task_id, TaskId::new(3));
+        assert!(wheel.get_task_tracking_info(TaskId::new(3)).is_none());
     }
 
     #[test]
@@ -583,35 +3051,52 @@ mod tests {
         wheel.add_task(task).unwrap();
 
         // Verify task tracking information
-        let tracking_info = wheel.get_task_tracking_info(100).unwrap();
-        assert_eq!(tracking_info.task_id, 100);
+        let tracking_info = wheel.get_task_tracking_info(TaskId::new(100)).unwrap();
+        assert_eq!(tracking_info.task_id, TaskId::new(100));
         assert_eq!(tracking_info.wheel_type, WheelType::Second); // 10 seconds should go to second wheel
     }
 
+    #[test]
+    fn test_task_tracking_keeps_name() {
+        let wheel = MulitWheel::new();
+        let task = TaskBuilder::new(104)
+            .with_frequency_repeated_by_seconds(90)
+            .with_name("billing-sync")
+            .spwan_async(TestTaskRunner::new())
+            .unwrap();
+        assert_eq!(task.name(), Some("billing-sync"));
+
+        wheel.add_task(task).unwrap();
+        let tracking_info = wheel.get_task_tracking_info(TaskId::new(104)).unwrap();
+        assert_eq!(tracking_info.name.as_deref(), Some("billing-sync"));
+    }
+
     #[test]
     fn test_task_tracking_direct_cascade_update() {
         let wheel = MulitWheel::new();
-        
+
         // Manually create a task and add it to minute wheel slot 5
         let mut task = TaskBuilder::new(105)
             .with_frequency_once_by_seconds(60) // Next execution in 60 seconds
             .spwan_async(TestTaskRunner::new())
             .unwrap();
-        
+
         // Set up cascade guide to place task in minute wheel slot 5
         task.cascade_guide = WheelCascadeGuide {
-            sec: 10, // Will be placed in sec wheel slot 10 when cascaded
+            sec: 10,      // Will be placed in sec wheel slot 10 when cascaded
             min: Some(5), // Currently in min wheel slot 5
             hour: None,
             round: 0,
         };
-        
+
         // Add task directly to minute wheel slot 5
         wheel.min_wheel.add_task(task, 5);
-        
+
         // Initialize tracking info for the task before cascade
         let initial_tracking = TaskTrackingInfo {
-            task_id: 105,
+            task_id: TaskId::new(105),
+            name: None,
+            tags: HashMap::new(),
             cascade_guide: WheelCascadeGuide {
                 sec: 10,
                 min: Some(5),
@@ -620,17 +3105,25 @@ mod tests {
             },
             wheel_type: WheelType::Minute,
             slot_num: 5,
+            enabled: true,
+            alarm_timestamp: 0,
+            depends_on: vec![],
+            group_id: None,
+            missed_alarm: None,
+            frequency: None,
         };
-        wheel.task_tracker_map.insert(105, initial_tracking);
-        
+        wheel
+            .task_tracker_map
+            .insert(TaskId::new(105), initial_tracking);
+
         // Simulate cascade minute to second - manually move the wheel hand to 5 to trigger cascade
         wheel.min_wheel.set_hand_position(5);
-        
+
         // Call the cascade function that updates tracking
         wheel.cascade_minute_tasks(); // Use the version that updates tracking
-        
+
         // Verify the tracking information was updated correctly
-        if let Some(updated_info) = wheel.get_task_tracking_info(105) {
+        if let Some(updated_info) = wheel.get_task_tracking_info(TaskId::new(105)) {
             // After cascading from minute to second, the task should be in second wheel
             assert_eq!(updated_info.wheel_type, WheelType::Second);
             assert_eq!(updated_info.slot_num, 10); // Based on cascade guide sec value
@@ -659,7 +3152,9 @@ mod tests {
 
         // Initialize tracking info for the task
         let tracking_info = TaskTrackingInfo {
-            task_id: 102,
+            task_id: TaskId::new(102),
+            name: None,
+            tags: HashMap::new(),
             cascade_guide: WheelCascadeGuide {
                 sec: 5,
                 min: Some(1),
@@ -668,14 +3163,22 @@ mod tests {
             },
             wheel_type: WheelType::Hour,
             slot_num: 1,
+            enabled: true,
+            alarm_timestamp: 0,
+            depends_on: vec![],
+            group_id: None,
+            missed_alarm: None,
+            frequency: None,
         };
-        wheel.task_tracker_map.insert(102, tracking_info);
+        wheel
+            .task_tracker_map
+            .insert(TaskId::new(102), tracking_info);
 
         // Simulate cascading by directly calling cascade method
         wheel.cascade_hour_tasks();
 
         // Verify the task is now tracked as being in minute wheel
-        if let Some(_updated_info) = wheel.get_task_tracking_info(102) {
+        if let Some(_updated_info) = wheel.get_task_tracking_info(TaskId::new(102)) {
             // If the task didn't get moved to minute wheel due to round > 0 logic,
             // the tracking would still reflect its current state
             // If moved to minute wheel, wheel_type should be Minute
@@ -694,14 +3197,84 @@ mod tests {
         wheel.add_task(task).unwrap();
 
         // Verify task exists in tracking
-        assert!(wheel.get_task_tracking_info(103).is_some());
+        assert!(wheel.get_task_tracking_info(TaskId::new(103)).is_some());
 
         // Remove task
-        let removed_task = wheel.remove_task(103);
+        let removed_task = wheel.remove_task(TaskId::new(103));
         assert!(removed_task.is_some());
 
         // Verify task no longer exists in tracking
-        assert!(wheel.get_task_tracking_info(103).is_none());
+        assert!(wheel.get_task_tracking_info(TaskId::new(103)).is_none());
+    }
+
+    #[test]
+    fn test_extend_count_down_mid_flight() {
+        let wheel = MulitWheel::new();
+        let task = TaskBuilder::new(105)
+            .with_frequency_count_down_by_seconds(3, 2)
+            .spwan_async(TestTaskRunner::new())
+            .unwrap();
+        wheel.add_task(task).unwrap();
+        assert_eq!(wheel.remaining_count(TaskId::new(105)), Some(3));
+
+        let mut fired = wheel.fast_forward(2).len();
+        assert_eq!(fired, 1);
+        assert_eq!(wheel.remaining_count(TaskId::new(105)), Some(2));
+        let alarm = wheel.next_fire_time(TaskId::new(105));
+
+        wheel.set_remaining_count(TaskId::new(105), 4).unwrap();
+        assert_eq!(wheel.remaining_count(TaskId::new(105)), Some(4));
+        assert_eq!(wheel.next_fire_time(TaskId::new(105)), alarm);
+        fired += wheel.fast_forward(20).len();
+        assert_eq!(fired, 5);
+        assert!(!wheel.contains(TaskId::new(105)));
+
+        let task = TaskBuilder::new(106)
+            .with_frequency_repeated_by_seconds(2)
+            .spwan_async(TestTaskRunner::new())
+            .unwrap();
+        wheel.add_task(task).unwrap();
+        assert_eq!(wheel.remaining_count(TaskId::new(106)), None);
+        for (task_id, count) in [(106, 3), (105, 3), (106, 0)] {
+            assert!(
+                wheel
+                    .set_remaining_count(TaskId::new(task_id), count)
+                    .is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn test_remove_task_info() {
+        let wheel = MulitWheel::new();
+        let task = TaskBuilder::new(104)
+            .with_frequency_repeated_by_seconds(90)
+            .with_name("cleanup")
+            .spwan_async(TestTaskRunner::new())
+            .unwrap();
+        wheel.add_task(task).unwrap();
+        let expected = wheel.get_task_tracking_info(TaskId::new(104)).unwrap();
+
+        let info = wheel.remove_task_info(TaskId::new(104)).unwrap();
+        assert_eq!(info.task_id, TaskId::new(104));
+        assert_eq!(info.name.as_deref(), Some("cleanup"));
+        assert_eq!(
+            (info.wheel_type, info.slot_num, info.alarm_timestamp),
+            (
+                expected.wheel_type,
+                expected.slot_num,
+                expected.alarm_timestamp
+            )
+        );
+        assert!(!wheel.contains(TaskId::new(104)));
+        assert!(wheel.is_empty());
+        assert!(
+            wheel
+                .wheel(info.wheel_type)
+                .task_ids(info.slot_num)
+                .is_empty()
+        );
+        assert!(wheel.remove_task_info(TaskId::new(104)).is_none());
     }
 
     #[test]
@@ -714,13 +3287,21 @@ mod tests {
         };
 
         let tracking_info = TaskTrackingInfo {
-            task_id: 999,
+            task_id: TaskId::new(999),
+            name: None,
+            tags: HashMap::new(),
             cascade_guide,
             wheel_type: WheelType::Minute,
             slot_num: 20,
+            enabled: true,
+            alarm_timestamp: 0,
+            depends_on: vec![],
+            group_id: None,
+            missed_alarm: None,
+            frequency: None,
         };
 
-        assert_eq!(tracking_info.task_id, 999);
+        assert_eq!(tracking_info.task_id, TaskId::new(999));
         assert_eq!(tracking_info.cascade_guide.sec, 10);
         assert_eq!(tracking_info.cascade_guide.min, Some(20));
         assert_eq!(tracking_info.cascade_guide.hour, Some(3));
@@ -728,4 +3309,1208 @@ mod tests {
         assert_eq!(tracking_info.wheel_type, WheelType::Minute);
         assert_eq!(tracking_info.slot_num, 20);
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_task_tracking_info_serde_round_trip() {
+        let tracking_info = TaskTrackingInfo {
+            task_id: TaskId::new(999),
+            name: None,
+            tags: HashMap::new(),
+            cascade_guide: WheelCascadeGuide {
+                sec: 10,
+                min: Some(20),
+                hour: Some(3),
+                round: 1,
+            },
+            wheel_type: WheelType::Hour,
+            slot_num: 3,
+            enabled: true,
+            alarm_timestamp: 0,
+            depends_on: vec![],
+            group_id: None,
+            missed_alarm: None,
+            frequency: None,
+        };
+
+        let json = serde_json::to_string(&tracking_info).unwrap();
+        let restored: TaskTrackingInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.task_id, tracking_info.task_id);
+        assert_eq!(restored.cascade_guide, tracking_info.cascade_guide);
+        assert_eq!(restored.wheel_type, tracking_info.wheel_type);
+        assert_eq!(restored.slot_num, tracking_info.slot_num);
+
+        // Unset levels serialize as null
+        let guide = WheelCascadeGuide {
+            sec: 5,
+            min: None,
+            hour: None,
+            round: 0,
+        };
+        let json = serde_json::to_value(guide).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "sec": 5, "min": null, "hour": null, "round": 0 })
+        );
+    }
+
+    #[test]
+    fn test_find_tasks_by_tag() {
+        let wheel = MulitWheel::new();
+        for task_id in 200..205 {
+            let mut builder = TaskBuilder::new(task_id).with_frequency_repeated_by_seconds(30);
+            if task_id % 2 == 0 {
+                builder =
+                    builder.with_tags(HashMap::from([("tenant".to_string(), "42".to_string())]));
+            }
+            wheel
+                .add_task(builder.spwan_async(TestTaskRunner::new()).unwrap())
+                .unwrap();
+        }
+
+        assert_eq!(
+            wheel.find_tasks_by_tag("tenant", "42"),
+            [200, 202, 204].map(TaskId::new)
+        );
+        assert!(wheel.find_tasks_by_tag("tenant", "7").is_empty());
+        assert!(wheel.find_tasks_by_tag("region", "42").is_empty());
+    }
+
+    #[test]
+    fn test_task_count_by_tag() {
+        let wheel = MulitWheel::new();
+        for (task_id, tenant) in [
+            (210, Some("a")),
+            (211, Some("b")),
+            (212, Some("a")),
+            (213, None),
+        ] {
+            let mut builder = TaskBuilder::new(task_id).with_frequency_repeated_by_seconds(30);
+            if let Some(tenant) = tenant {
+                builder =
+                    builder.with_tags(HashMap::from([("tenant".to_string(), tenant.to_string())]));
+            }
+            wheel
+                .add_task(builder.spwan_async(TestTaskRunner::new()).unwrap())
+                .unwrap();
+        }
+
+        assert_eq!(
+            wheel.task_count_by_tag("tenant"),
+            HashMap::from([("a".to_string(), 2), ("b".to_string(), 1)])
+        );
+        assert!(wheel.task_count_by_tag("region").is_empty());
+    }
+
+    #[test]
+    fn test_level_distribution() {
+        let wheel = MulitWheel::new();
+        assert_eq!(wheel.level_distribution(), (0, 0, 0));
+        for (task_id, seconds) in [(220, 5), (221, 90), (222, 4000)] {
+            let task = TaskBuilder::new(task_id)
+                .with_frequency_repeated_by_seconds(seconds)
+                .spwan_async(TestTaskRunner::new())
+                .unwrap();
+            wheel.add_task(task).unwrap();
+        }
+        assert_eq!(wheel.level_distribution(), (1, 1, 1));
+    }
+
+    #[test]
+    fn test_estimated_memory_follows_tasks() {
+        let wheel = MulitWheel::with_slot_capacity_floor(16);
+        let empty = wheel.estimated_memory_bytes();
+        assert!(empty > 0);
+
+        for task_id in 0..2000 {
+            let task = TaskBuilder::new(task_id)
+                .with_name(format!("task-{task_id}"))
+                .with_frequency_once_by_seconds(5)
+                .spwan_async(TestTaskRunner::new())
+                .unwrap();
+            wheel.add_task(task).unwrap();
+        }
+        let full = wheel.estimated_memory_bytes();
+        assert!(full > empty + 2000 * size_of::<Task>());
+
+        // The slot shrinks once drained, the tracker keeps its capacity
+        assert_eq!(wheel.fast_forward(5).len(), 2000);
+        assert!(wheel.is_empty());
+        let cleared = wheel.estimated_memory_bytes();
+        assert!(cleared < full - 2000 * size_of::<Task>());
+    }
+
+    #[test]
+    fn test_fire_slot_drains_only_that_slot() {
+        let wheel = MulitWheel::new();
+        for (task_id, seconds) in [(240, 120), (241, 125), (242, 180), (243, 5)] {
+            let task = TaskBuilder::new(task_id)
+                .with_frequency_once_by_seconds(seconds)
+                .spwan_async(TestTaskRunner::new())
+                .unwrap();
+            wheel.add_task(task).unwrap();
+        }
+        let info = wheel
+            .task_tracker_map
+            .get(&TaskId::new(240))
+            .unwrap()
+            .clone();
+        assert_eq!(info.wheel_type, WheelType::Minute);
+        let positions = wheel.get_wheel_positions();
+
+        let fired = wheel.fire_slot(WheelType::Minute, info.slot_num);
+        let mut fired: Vec<_> = fired.iter().map(|task| task.task_id).collect();
+        fired.sort();
+        assert_eq!(fired, [TaskId::new(240), TaskId::new(241)]);
+        assert_eq!(wheel.get_wheel_positions(), positions);
+        assert!(!wheel.task_tracker_map.contains_key(&TaskId::new(240)));
+        assert_eq!(wheel.level_distribution(), (1, 1, 0));
+        assert_eq!(wheel.audit(), Ok(()));
+
+        assert!(wheel.fire_slot(WheelType::Minute, info.slot_num).is_empty());
+    }
+
+    #[test]
+    fn test_audit_reports_desynced_tracker() {
+        let wheel = MulitWheel::new();
+        for (task_id, seconds) in [(230, 5), (231, 90), (232, 4000)] {
+            let task = TaskBuilder::new(task_id)
+                .with_frequency_repeated_by_seconds(seconds)
+                .spwan_async(TestTaskRunner::new())
+                .unwrap();
+            wheel.add_task(task).unwrap();
+        }
+        wheel.tick();
+        assert_eq!(wheel.audit(), Ok(()));
+
+        let location = |task_id| {
+            let info = wheel.task_tracker_map.get(&TaskId::new(task_id)).unwrap();
+            (info.wheel_type, info.slot_num)
+        };
+        let (second, minute) = (location(230), location(231));
+        wheel
+            .task_tracker_map
+            .get_mut(&TaskId::new(230))
+            .unwrap()
+            .slot_num += 1;
+        let hour = wheel.task_tracker_map.remove(&TaskId::new(232)).unwrap().1;
+        let mut stale = wheel
+            .task_tracker_map
+            .get(&TaskId::new(231))
+            .unwrap()
+            .clone();
+        stale.task_id = TaskId::new(233);
+        wheel.task_tracker_map.insert(TaskId::new(233), stale);
+
+        assert_eq!(
+            wheel.audit(),
+            Err(vec![
+                AuditError::Misplaced {
+                    task_id: TaskId::new(230),
+                    tracked: (second.0, second.1 + 1),
+                    found: second,
+                },
+                AuditError::Untracked {
+                    task_id: TaskId::new(232),
+                    wheel_type: hour.wheel_type,
+                    slot_num: hour.slot_num,
+                },
+                AuditError::Missing {
+                    task_id: TaskId::new(233),
+                    wheel_type: minute.0,
+                    slot_num: minute.1,
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_gc_orphans_reconciles_tracker() {
+        let wheel = MulitWheel::new();
+        for (task_id, seconds) in [(240, 5), (241, 90), (242, 4000)] {
+            let task = TaskBuilder::new(task_id)
+                .with_frequency_repeated_by_seconds(seconds)
+                .spwan_async(TestTaskRunner::new())
+                .unwrap();
+            wheel.add_task(task).unwrap();
+        }
+        assert_eq!(wheel.gc_orphans(), 0);
+
+        // A tracker entry without its task, a task without its entry, and a stale slot number
+        let mut orphan = wheel
+            .task_tracker_map
+            .get(&TaskId::new(240))
+            .unwrap()
+            .clone();
+        orphan.task_id = TaskId::new(243);
+        wheel.task_tracker_map.insert(TaskId::new(243), orphan);
+        wheel.task_tracker_map.remove(&TaskId::new(242));
+        let slot_num = wheel.task_location(TaskId::new(241)).unwrap().slot_num;
+        wheel
+            .task_tracker_map
+            .get_mut(&TaskId::new(241))
+            .unwrap()
+            .slot_num += 1;
+        assert!(wheel.contains(TaskId::new(243)));
+        assert_eq!(wheel.len(), 3);
+
+        assert_eq!(wheel.gc_orphans(), 3);
+        assert_eq!(wheel.audit(), Ok(()));
+        assert!(!wheel.contains(TaskId::new(243)));
+        assert_eq!(wheel.len(), 2);
+        let mut remaining = vec![];
+        wheel.for_each_task(|task| remaining.push(task.task_id));
+        remaining.sort_unstable();
+        assert_eq!(remaining, [240, 241].map(TaskId::new));
+        assert_eq!(
+            wheel.task_location(TaskId::new(241)).unwrap().slot_num,
+            slot_num
+        );
+        assert!(wheel.remove_task(TaskId::new(241)).is_some());
+    }
+
+    #[test]
+    fn test_remove_tasks_by_tag() {
+        let wheel = MulitWheel::new();
+        for (task_id, seconds) in [(300, 5), (301, 90), (302, 4000), (303, 5), (304, 90)] {
+            let mut builder = TaskBuilder::new(task_id).with_frequency_repeated_by_seconds(seconds);
+            if task_id < 303 {
+                builder =
+                    builder.with_tags(HashMap::from([("tenant".to_string(), "x".to_string())]));
+            }
+            wheel
+                .add_task(builder.spwan_async(TestTaskRunner::new()).unwrap())
+                .unwrap();
+        }
+
+        assert_eq!(wheel.remove_tasks_by_tag("tenant", "x"), 3);
+        assert_eq!(wheel.remove_tasks_by_tag("tenant", "x"), 0);
+
+        let mut remaining = vec![];
+        wheel.for_each_task(|task| remaining.push(task.task_id));
+        remaining.sort_unstable();
+        assert_eq!(remaining, [303, 304].map(TaskId::new));
+        assert_eq!(wheel.task_tracker_map.len(), 2);
+    }
+
+    #[test]
+    fn test_remove_tasks_where() {
+        let wheel = MulitWheel::new();
+        for (task_id, seconds) in [(310, 30), (311, 4000), (312, 600), (313, 90_000)] {
+            let task = TaskBuilder::new(task_id)
+                .with_frequency_once_by_seconds(seconds)
+                .spwan_async(TestTaskRunner::new())
+                .unwrap();
+            wheel.add_task(task).unwrap();
+        }
+
+        let removed = wheel.remove_tasks_where(|info| info.wheel_type == WheelType::Hour);
+        assert_eq!(removed, [311, 313].map(TaskId::new));
+        assert_eq!(wheel.len(), 2);
+        let mut scheduled = 0;
+        wheel.for_each_task(|task| {
+            assert!(!removed.contains(&task.task_id));
+            scheduled += 1;
+        });
+        assert_eq!(scheduled, 2);
+    }
+
+    #[test]
+    fn test_paused_minute_wheel_holds_tasks() {
+        let wheel = MulitWheel::new();
+        for (task_id, seconds) in [(320, 5), (321, 90)] {
+            let task = TaskBuilder::new(task_id)
+                .with_frequency_once_by_seconds(seconds)
+                .spwan_async(TestTaskRunner::new())
+                .unwrap();
+            wheel.add_task(task).unwrap();
+        }
+        wheel.pause_wheel(WheelType::Minute);
+
+        let mut fired_at = vec![];
+        let mut tick_and_collect = |ticks: u64| {
+            for _ in 0..ticks {
+                wheel.tick();
+                for task in wheel.take_arrived_tasks() {
+                    fired_at.push((task.task_id, wheel.current_timestamp()));
+                }
+            }
+        };
+        let start = wheel.current_timestamp();
+        tick_and_collect(120);
+        assert_eq!(
+            wheel.task_location(TaskId::new(321)).unwrap().wheel_type,
+            WheelType::Minute
+        );
+
+        // The minute hand passed the slot while paused, so the task waits for it to come around
+        wheel.resume_wheel(WheelType::Minute);
+        tick_and_collect(3600);
+        assert_eq!(
+            fired_at,
+            vec![
+                (TaskId::new(320), start + 5),
+                (TaskId::new(321), start + 3690)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_delay_beyond_horizon_is_rejected() {
+        let wheel = MulitWheel::new();
+        let max = wheel.max_delay();
+        assert_eq!(max, 366 * 24 * 3600);
+
+        let task = |task_id, seconds| {
+            TaskBuilder::new(task_id)
+                .with_frequency_once_by_seconds(seconds)
+                .spwan_async(TestTaskRunner::new())
+                .unwrap()
+        };
+        // The alarm of a new task may already be a second behind the wheel
+        wheel.add_task(task(330, max - 1)).unwrap();
+        let result = wheel.add_task(task(331, max + 10));
+        assert!(matches!(
+            result,
+            Err(TaskError::DelayTooLong { requested, max: m }) if requested >= max + 10 && m == max
+        ));
+        assert!(!wheel.contains(TaskId::new(331)));
+    }
+
+    #[test]
+    fn test_due_tasks_preview_does_not_drain() {
+        let wheel = MulitWheel::new();
+        let task = |task_id, seconds| {
+            TaskBuilder::new(task_id)
+                .with_frequency_once_by_seconds(seconds)
+                .spwan_async(TestTaskRunner::new())
+                .unwrap()
+        };
+        wheel.add_task(task(360, 1)).unwrap();
+        wheel.add_task(task(361, 1)).unwrap();
+        wheel.add_task(task(362, 2)).unwrap();
+        wheel.disable_task(TaskId::new(361));
+        assert_eq!(wheel.due_tasks_preview(), vec![TaskId::new(360)]);
+        assert_eq!(wheel.due_tasks_preview(), vec![TaskId::new(360)]);
+        assert!(wheel.contains(TaskId::new(360)));
+
+        wheel.tick();
+        let arrived: Vec<TaskId> = wheel
+            .take_arrived_tasks()
+            .iter()
+            .map(|task| task.task_id)
+            .collect();
+        assert_eq!(arrived, vec![TaskId::new(360)]);
+        assert_eq!(wheel.due_tasks_preview(), vec![TaskId::new(362)]);
+    }
+
+    #[test]
+    fn test_due_tasks_preview_includes_cascading_tasks() {
+        let wheel = MulitWheel::new();
+        let (sec, _, _) = wheel.get_wheel_positions();
+        // Lands on the minute wheel and cascades on the tick that wraps the second hand
+        let seconds = 60 - sec + 60;
+        wheel
+            .add_task(
+                TaskBuilder::new(363)
+                    .with_frequency_once_by_seconds(seconds)
+                    .spwan_async(TestTaskRunner::new())
+                    .unwrap(),
+            )
+            .unwrap();
+        assert_eq!(
+            wheel.task_location(TaskId::new(363)).unwrap().wheel_type,
+            WheelType::Minute
+        );
+
+        for _ in 1..seconds {
+            assert!(wheel.due_tasks_preview().is_empty());
+            wheel.tick();
+            assert!(wheel.take_arrived_tasks().is_empty());
+        }
+        assert_eq!(wheel.due_tasks_preview(), vec![TaskId::new(363)]);
+        wheel.tick();
+        assert_eq!(wheel.take_arrived_tasks().len(), 1);
+    }
+
+    #[test]
+    fn test_drained_slot_shrinks_to_floor() {
+        let capacity_after_burst = |capacity_floor| {
+            let wheel = MulitWheel::with_slot_capacity_floor(capacity_floor);
+            let tasks = (0..2000)
+                .map(|task_id| {
+                    TaskBuilder::new(task_id)
+                        .with_frequency_once_by_seconds(1)
+                        .spwan_async(TestTaskRunner::new())
+                        .unwrap()
+                })
+                .collect();
+            wheel.add_tasks(tasks);
+            let capacity = || wheel.sec_wheel.slots.get(&1).unwrap().task_map.capacity();
+            assert!(capacity() >= 2000);
+
+            wheel.tick();
+            assert_eq!(wheel.take_arrived_tasks().len(), 2000);
+            capacity()
+        };
+        let capacity = capacity_after_burst(16);
+        assert!((16..500).contains(&capacity));
+        assert!(capacity_after_burst(1024) >= 1024);
+    }
+
+    #[test]
+    fn test_dependency_cycle_is_rejected() {
+        let wheel = MulitWheel::new();
+        let task = |task_id, depends_on: &[u64]| {
+            depends_on
+                .iter()
+                .fold(TaskBuilder::new(task_id), |builder, &task_id| {
+                    builder.with_depends_on(task_id)
+                })
+                .with_frequency_repeated_by_seconds(5)
+                .spwan_async(TestTaskRunner::new())
+                .unwrap()
+        };
+        wheel.add_task(task(370, &[371])).unwrap();
+        wheel.add_task(task(371, &[372])).unwrap();
+        assert!(matches!(
+            wheel.add_task(task(372, &[380, 370])),
+            Err(TaskError::DependencyCycle(task_id)) if task_id == TaskId::new(372)
+        ));
+        assert!(matches!(
+            wheel.add_task(task(373, &[373])),
+            Err(TaskError::DependencyCycle(_))
+        ));
+        assert!(!wheel.contains(TaskId::new(372)));
+
+        // Shared dependencies are not cycles
+        wheel.add_task(task(372, &[380])).unwrap();
+        wheel.add_task(task(374, &[370, 371])).unwrap();
+    }
+
+    #[test]
+    fn test_waiting_for_a_task_that_is_gone() {
+        let wheel = MulitWheel::new();
+        let task = |task_id, seconds, depends_on: Option<u64>| {
+            let mut builder = TaskBuilder::new(task_id).with_frequency_repeated_by_seconds(seconds);
+            if let Some(task_id) = depends_on {
+                builder = builder.with_depends_on(task_id);
+            }
+            builder.spwan_async(TestTaskRunner::new()).unwrap()
+        };
+        wheel.add_task(task(1, 30, None)).unwrap();
+        wheel.add_task(task(2, 1, Some(1))).unwrap();
+        wheel.add_task(task(3, 1, Some(4))).unwrap();
+        wheel.add_task(task(4, 2, None)).unwrap();
+
+        // Waiting for a scheduled task, and for one whose run is pending
+        wheel.tick();
+        assert!(wheel.take_arrived_tasks().is_empty());
+        wheel.tick();
+        let arrived: Vec<TaskId> = wheel
+            .take_arrived_tasks()
+            .iter()
+            .map(|task| task.task_id)
+            .collect();
+        assert_eq!(arrived, vec![TaskId::new(4)]);
+        wheel.tick();
+        assert!(wheel.take_arrived_tasks().is_empty());
+        assert!(wheel.contains(TaskId::new(2)) && wheel.contains(TaskId::new(3)));
+
+        // The dependency is removed, or its run fails without it being scheduled again
+        wheel.remove_task(TaskId::new(1));
+        wheel.record_failure(TaskId::new(4));
+        wheel.tick();
+        assert!(wheel.take_arrived_tasks().is_empty());
+        assert!(!wheel.contains(TaskId::new(2)) && !wheel.contains(TaskId::new(3)));
+    }
+
+    #[test]
+    fn test_completions_are_pruned() {
+        let wheel = MulitWheel::new();
+        wheel
+            .add_task(
+                TaskBuilder::new(1)
+                    .with_frequency_repeated_by_seconds(5)
+                    .with_depends_on(7)
+                    .spwan_async(TestTaskRunner::new())
+                    .unwrap(),
+            )
+            .unwrap();
+        wheel.record_completion(TaskId::new(7));
+        for task_id in 100..10_000 {
+            wheel.record_completion(TaskId::new(task_id));
+        }
+        assert!(wheel.completions.len() < MIN_COMPLETIONS_LIMIT);
+        // What a scheduled task waits for is kept
+        assert!(wheel.has_completed(TaskId::new(7)));
+    }
+
+    #[test]
+    fn test_wheel_builder_levels_and_horizon() {
+        let wheel = MulitWheelBuilder::new()
+            .with_levels(&[30, 10, 12])
+            .with_horizon(Duration::from_secs(2 * 24 * 3600))
+            .build()
+            .unwrap();
+        assert_eq!(
+            [&wheel.sec_wheel, &wheel.min_wheel, &wheel.hour_wheel].map(|wheel| wheel.num_slots),
+            [30, 10, 12]
+        );
+        // A revolution takes an hour, so the two days take 48 rounds
+        assert_eq!(wheel.max_rounds, 48);
+        assert_eq!(wheel.max_delay(), 48 * 3600);
+
+        let task = TaskBuilder::new(380)
+            .with_frequency_once_by_seconds(4000)
+            .spwan_async(TestTaskRunner::new())
+            .unwrap();
+        wheel.add_task(task).unwrap();
+        let start = wheel.current_timestamp();
+        let alarm_timestamp = wheel.next_fire_time(TaskId::new(380)).unwrap();
+        let location = wheel.task_location(TaskId::new(380)).unwrap();
+        assert_eq!(location.wheel_type, WheelType::Hour);
+        let mut fired_at = None;
+        while fired_at.is_none() && wheel.current_timestamp() < start + 5000 {
+            wheel.tick();
+            if !wheel.take_arrived_tasks().is_empty() {
+                fired_at = Some(wheel.current_timestamp());
+            }
+        }
+        assert_eq!(fired_at, Some(alarm_timestamp));
+    }
+
+    #[test]
+    fn test_millisecond_wheel() {
+        // 2024-01-01 13:45:30.250 UTC
+        fn clock() -> Duration {
+            Duration::from_millis(1_704_116_730_250)
+        }
+
+        let wheel = MulitWheelBuilder::new()
+            .with_resolution(Duration::from_millis(1))
+            .with_levels(&[1000, 60, 60])
+            .with_horizon(Duration::from_secs(24 * 3600))
+            .with_clock(clock)
+            .with_wall_clock_alignment(true)
+            .build()
+            .unwrap();
+        // Milliseconds, seconds and minutes, a revolution takes an hour
+        assert_eq!(
+            [&wheel.sec_wheel, &wheel.min_wheel, &wheel.hour_wheel].map(|wheel| wheel.num_slots),
+            [1000, 60, 60]
+        );
+        assert_eq!(wheel.resolution(), Duration::from_millis(1));
+        assert_eq!(wheel.ticks_per_second(), 1000);
+        assert_eq!(wheel.max_rounds, 24);
+        assert_eq!(wheel.max_delay(), 24 * 3600);
+        assert_eq!(wheel.get_wheel_positions(), (250, 30, 45));
+        assert_eq!(wheel.current_timestamp(), 1_704_116_730);
+
+        // Due at 13:45:32.000, the start of its second rather than two seconds from 30.250
+        let task = TaskBuilder::new(1)
+            .with_frequency_once_by_seconds(2)
+            .spwan_async(TestTaskRunner::new())
+            .unwrap();
+        wheel.add_task(task).unwrap();
+        let location = wheel.task_location(TaskId::new(1)).unwrap();
+        assert_eq!(
+            (location.wheel_type, location.slot_num),
+            (WheelType::Minute, 32)
+        );
+        assert_eq!(location.ticks_until_fire, 1750);
+        for _ in 0..1749 {
+            wheel.tick();
+            assert!(wheel.take_arrived_tasks().is_empty());
+        }
+        wheel.tick();
+        assert_eq!(wheel.take_arrived_tasks().len(), 1);
+        assert_eq!(wheel.get_wheel_positions(), (0, 32, 45));
+        assert_eq!(wheel.current_timestamp(), 1_704_116_732);
+    }
+
+    #[test]
+    fn test_wheel_aligned_to_wall_clock() {
+        // 2024-01-01 13:45:30 UTC
+        fn clock() -> Duration {
+            Duration::from_secs(1_704_116_730)
+        }
+
+        let wheel = MulitWheelBuilder::new()
+            .with_clock(clock)
+            .with_wall_clock_alignment(true)
+            .build()
+            .unwrap();
+        assert_eq!(wheel.get_wheel_positions(), (30, 45, 13));
+        assert_eq!(wheel.current_timestamp(), clock().as_secs());
+
+        // Due at 13:46:00, the start of the next minute slot
+        let task = TaskBuilder::new(1)
+            .with_frequency_once_by_seconds(30)
+            .spwan_async(TestTaskRunner::new())
+            .unwrap();
+        wheel.add_task(task).unwrap();
+        let location = wheel.task_location(TaskId::new(1)).unwrap();
+        assert_eq!(
+            (location.wheel_type, location.slot_num),
+            (WheelType::Minute, 46)
+        );
+        assert_eq!(wheel.fast_forward(30).len(), 1);
+        assert_eq!(wheel.get_wheel_positions(), (0, 46, 13));
+
+        let unaligned = MulitWheelBuilder::new().with_clock(clock).build().unwrap();
+        assert_eq!(unaligned.get_wheel_positions(), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_wheel_builder_rejects_invalid_config() {
+        let invalid = |builder: MulitWheelBuilder| {
+            matches!(builder.validate(), Err(TaskError::InvalidWheelConfig(_)))
+        };
+        assert!(MulitWheelBuilder::new().validate().is_ok());
+        assert!(invalid(
+            MulitWheelBuilder::new().with_levels(&[60, 60, 24, 7])
+        ));
+        assert!(invalid(MulitWheelBuilder::new().with_levels(&[60, 1, 24])));
+        assert!(invalid(
+            MulitWheelBuilder::new().with_horizon(Duration::from_secs(3600))
+        ));
+        for resolution in [0, 300, 2000].map(Duration::from_millis) {
+            assert!(invalid(
+                MulitWheelBuilder::new().with_resolution(resolution)
+            ));
+        }
+        // A revolution of the millisecond wheel takes an hour
+        assert!(invalid(
+            MulitWheelBuilder::new()
+                .with_resolution(Duration::from_millis(1))
+                .with_levels(&[1000, 60, 60])
+                .with_horizon(Duration::from_secs(1800))
+        ));
+    }
+
+    #[test]
+    fn test_resync_after_large_jump() {
+        let wheel = MulitWheel::new();
+        let start = wheel.current_timestamp();
+        let task = |task_id, frequency| {
+            TaskBuilder::new(task_id)
+                .with_frequency(frequency)
+                .spwan_async(TestTaskRunner::new())
+                .unwrap()
+        };
+        wheel
+            .add_task(task(350, FrequencySeconds::Once(10)))
+            .unwrap();
+        wheel
+            .add_task(task(351, FrequencySeconds::Repeated(3600)))
+            .unwrap();
+        wheel
+            .add_task(task(352, FrequencySeconds::Once(10 * 3600)))
+            .unwrap();
+        wheel
+            .add_task(task(353, FrequencySeconds::Once(60)))
+            .unwrap();
+        wheel.disable_task(TaskId::new(353));
+        let alarm_352 = wheel
+            .get_task_tracking_info(TaskId::new(352))
+            .unwrap()
+            .alarm_timestamp;
+
+        // The machine sleeps for eight hours
+        let now = start + 8 * 3600;
+        assert!(wheel.lag_behind(now) > RESYNC_THRESHOLD);
+        let overdue = wheel.resync(now);
+        assert_eq!(wheel.current_timestamp(), now);
+        assert_eq!(wheel.get_wheel_positions(), (0, 0, 8));
+        let overdue_ids: Vec<TaskId> = overdue.iter().map(|task| task.task_id).collect();
+        assert_eq!(overdue_ids, [350, 351].map(TaskId::new));
+        assert!(!wheel.contains(TaskId::new(353)));
+
+        // The repeated task fires once and then keeps its phase
+        for task in overdue {
+            let _ = wheel.add_task(task);
+        }
+        let repeated = wheel.get_task_tracking_info(TaskId::new(351)).unwrap();
+        assert!(repeated.alarm_timestamp > now);
+        assert_eq!((repeated.alarm_timestamp - start) % 3600, 0);
+        assert!(wheel.resync(now).is_empty());
+
+        let location = wheel.task_location(TaskId::new(352)).unwrap();
+        assert_eq!(location.ticks_until_fire, alarm_352 - now);
+        let mut fired_at = None;
+        for _ in 0..3 * 3600 {
+            wheel.tick();
+            for task in wheel.take_arrived_tasks() {
+                if task.task_id == TaskId::new(352) {
+                    assert_eq!(fired_at.replace(wheel.current_timestamp()), None);
+                }
+            }
+        }
+        assert_eq!(fired_at, Some(alarm_352));
+    }
+
+    #[test]
+    fn test_update_frequency_moves_task_between_wheels() {
+        let wheel = MulitWheel::new();
+        let task = TaskBuilder::new(380)
+            .with_frequency_repeated_by_seconds(90)
+            .spwan_async(TestTaskRunner::new())
+            .unwrap();
+        wheel.add_task(task).unwrap();
+        let info = wheel.get_task_tracking_info(TaskId::new(380)).unwrap();
+        assert_eq!(info.wheel_type, WheelType::Minute);
+        let (old_slot, old_wheel) = (info.slot_num, info.wheel_type);
+
+        wheel
+            .update_frequency(TaskId::new(380), FrequencySeconds::Repeated(10))
+            .unwrap();
+        let info = wheel.get_task_tracking_info(TaskId::new(380)).unwrap();
+        assert_eq!(info.wheel_type, WheelType::Second);
+        assert_eq!(info.frequency, Some(FrequencySeconds::Repeated(10)));
+        assert_eq!(info.alarm_timestamp, wheel.current_timestamp() + 10);
+        assert!(wheel.wheel(old_wheel).task_ids(old_slot).is_empty());
+        assert_eq!(
+            wheel.wheel(WheelType::Second).task_ids(info.slot_num),
+            [TaskId::new(380)]
+        );
+
+        // And back up to the minute wheel
+        wheel
+            .update_frequency(TaskId::new(380), FrequencySeconds::Repeated(90))
+            .unwrap();
+        let location = wheel.task_location(TaskId::new(380)).unwrap();
+        assert_eq!(location.wheel_type, WheelType::Minute);
+        assert_eq!(location.ticks_until_fire, 90);
+    }
+
+    #[test]
+    fn test_hand_over_returns_task_with_runner() {
+        let wheel = MulitWheel::new();
+        let execution_count = Arc::new(AtomicU64::new(0));
+        let task = TaskBuilder::new(370)
+            .with_frequency_repeated_by_seconds(2)
+            .spwan_async(TestTaskRunner {
+                execution_count: execution_count.clone(),
+            })
+            .unwrap();
+        wheel.add_task(task).unwrap();
+
+        let ticks = wheel
+            .task_location(TaskId::new(370))
+            .unwrap()
+            .ticks_until_fire;
+        for _ in 1..ticks {
+            wheel.tick();
+            assert!(wheel.hand_over(wheel.take_arrived_tasks()).is_empty());
+        }
+        wheel.tick();
+        let due = wheel.hand_over(wheel.take_arrived_tasks());
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].task_id, TaskId::new(370));
+        assert_eq!(execution_count.load(Ordering::Relaxed), 0);
+
+        crate::utils::block_on(due[0].runner.run()).unwrap();
+        assert_eq!(execution_count.load(Ordering::Relaxed), 1);
+        // The schedule goes on without the caller putting the task back
+        let next = wheel.next_fire_time(TaskId::new(370)).unwrap();
+        assert_eq!(next, wheel.current_timestamp() + 2);
+    }
+
+    #[test]
+    fn test_missed_policy_after_gap() {
+        let wheel = MulitWheel::new();
+        let start = wheel.current_timestamp();
+        for (task_id, policy) in [(360, MissedPolicy::Skip), (361, MissedPolicy::CatchUp)] {
+            let mut task = TaskBuilder::new(task_id)
+                .with_frequency_repeated_by_seconds(5)
+                .with_missed_policy(policy)
+                .spwan_async(TestTaskRunner::new())
+                .unwrap();
+            task.frequency = FrequencyState::starting_at(FrequencySeconds::Repeated(5), start);
+            wheel.add_task(task).unwrap();
+        }
+
+        // A 30 second gap misses the alarms at +5, +10, ..., +30
+        let now = start + 30;
+        let mut fires = HashMap::<TaskId, u64>::new();
+        let fire = |tasks: Vec<Task>, fires: &mut HashMap<TaskId, u64>| {
+            for task in tasks {
+                *fires.entry(task.task_id).or_default() += 1;
+                wheel.requeue(task).unwrap();
+            }
+        };
+        fire(wheel.resync(now), &mut fires);
+        for _ in 0..4 {
+            wheel.tick();
+            fire(wheel.take_arrived_tasks(), &mut fires);
+        }
+        assert_eq!(fires[&TaskId::new(360)], 1);
+        assert_eq!(fires[&TaskId::new(361)], 5);
+
+        // The alarm at +35 is regular for the skipping task and the last of the backlog fires
+        wheel.tick();
+        fire(wheel.take_arrived_tasks(), &mut fires);
+        assert_eq!(fires[&TaskId::new(360)], 2);
+        assert_eq!(fires[&TaskId::new(361)], 6);
+
+        // Once caught up, the task is back in phase
+        wheel.tick();
+        fire(wheel.take_arrived_tasks(), &mut fires);
+        assert_eq!(fires[&TaskId::new(361)], 7);
+        for task_id in [360, 361] {
+            let info = wheel.get_task_tracking_info(TaskId::new(task_id)).unwrap();
+            assert_eq!(info.alarm_timestamp, start + 40);
+        }
+    }
+
+    #[test]
+    fn test_tasks_with_same_dedup_key_run_once() {
+        let wheel = MulitWheel::new();
+        for task_id in [340, 341] {
+            let task = TaskBuilder::new(task_id)
+                .with_frequency_repeated_by_seconds(2)
+                .with_dedup_key("nightly-report")
+                .spwan_async(TestTaskRunner::new())
+                .unwrap();
+            wheel.add_task(task).unwrap();
+        }
+        let task = TaskBuilder::new(342)
+            .with_frequency_repeated_by_seconds(2)
+            .spwan_async(TestTaskRunner::new())
+            .unwrap();
+        wheel.add_task(task).unwrap();
+
+        wheel.tick();
+        wheel.tick();
+        let arrived: Vec<TaskId> = wheel
+            .take_arrived_tasks()
+            .iter()
+            .map(|task| task.task_id)
+            .collect();
+        assert_eq!(arrived, [340, 342].map(TaskId::new));
+
+        // The coalesced task keeps its schedule
+        let location = wheel.task_location(TaskId::new(341)).unwrap();
+        assert_eq!(location.ticks_until_fire, 2);
+    }
+
+    #[test]
+    fn test_staggered_tasks_spread_across_slots() {
+        let wheel = MulitWheel::new();
+        let tasks: Vec<Task> = TaskBuilder::default()
+            .with_tags(HashMap::from([(
+                "job".to_string(),
+                "shard-poll".to_string(),
+            )]))
+            .staggered(10, 60, 1, |_| TestTaskRunner::new())
+            .unwrap()
+            .collect();
+        assert!(wheel.add_tasks(tasks).iter().all(Result::is_ok));
+
+        let task_ids = wheel.find_tasks_by_tag("job", "shard-poll");
+        assert_eq!(task_ids.len(), 10);
+        let slots: HashSet<u64> = task_ids
+            .iter()
+            .map(|&task_id| {
+                let location = wheel.task_location(task_id).unwrap();
+                assert_eq!(location.wheel_type, WheelType::Second);
+                location.slot_num
+            })
+            .collect();
+        assert_eq!(slots.len(), 10);
+    }
+
+    #[test]
+    fn test_arrived_tasks_in_priority_order() {
+        let wheel = MulitWheel::new();
+        for (task_id, priority) in [(400, 1), (401, 5), (402, 1), (403, 9)] {
+            let task = TaskBuilder::new(task_id)
+                .with_frequency_once_by_seconds(1)
+                .with_priority(priority)
+                .spwan_async(TestTaskRunner::new())
+                .unwrap();
+            wheel.add_task(task).unwrap();
+        }
+
+        wheel.tick();
+        let order: Vec<TaskId> = wheel
+            .take_arrived_tasks()
+            .iter()
+            .map(|task| task.task_id)
+            .collect();
+        // Equal priorities keep the order they were scheduled in
+        assert_eq!(order, [403, 401, 400, 402].map(TaskId::new));
+    }
+
+    #[test]
+    fn test_rescheduled_tasks_in_priority_order() {
+        let wheel = MulitWheel::new();
+        for (task_id, priority, seconds) in [
+            (600, 1, 3),
+            (601, 5, 7),
+            (602, 1, 90),
+            (603, 9, 5),
+            (604, 5, 2),
+            (605, 1, 30),
+        ] {
+            let task = TaskBuilder::new(task_id)
+                .with_frequency_repeated_by_seconds(seconds)
+                .with_priority(priority)
+                .spwan_async(TestTaskRunner::new())
+                .unwrap();
+            wheel.add_task(task).unwrap();
+        }
+        for task_id in [605, 600, 603, 601, 604, 602] {
+            wheel
+                .update_frequency(TaskId::new(task_id), FrequencySeconds::Repeated(4))
+                .unwrap();
+        }
+
+        for _ in 0..4 {
+            wheel.tick();
+        }
+        let order: Vec<TaskId> = wheel
+            .take_arrived_tasks()
+            .iter()
+            .map(|task| task.task_id)
+            .collect();
+        // Equal priorities follow the order the tasks were rescheduled in
+        assert_eq!(order, [603, 601, 604, 605, 600, 602].map(TaskId::new));
+    }
+
+    #[test]
+    fn test_add_tasks_reports_duplicates() {
+        let wheel = MulitWheel::new();
+        let task = |task_id, seconds| {
+            TaskBuilder::new(task_id)
+                .with_frequency_once_by_seconds(seconds)
+                .spwan_async(TestTaskRunner::new())
+                .unwrap()
+        };
+        wheel.add_task(task(500, 5)).unwrap();
+
+        let results = wheel.add_tasks(vec![task(501, 5), task(500, 90), task(502, 5)]);
+        assert!(results[0].is_ok());
+        assert!(
+            matches!(results[1], Err(TaskError::DuplicateTask(task_id)) if task_id == TaskId::new(500))
+        );
+        assert!(results[2].is_ok());
+
+        // The duplicate leaves the scheduled task untouched
+        let info = wheel.get_task_tracking_info(TaskId::new(500)).unwrap();
+        assert_eq!(info.wheel_type, WheelType::Second);
+        for _ in 0..5 {
+            wheel.tick();
+        }
+        let mut arrived: Vec<TaskId> = wheel
+            .take_arrived_tasks()
+            .iter()
+            .map(|task| task.task_id)
+            .collect();
+        arrived.sort_unstable();
+        assert_eq!(arrived, [500, 501, 502].map(TaskId::new));
+    }
+
+    #[test]
+    fn test_bulk_add_tasks_places_every_task() {
+        let wheel = MulitWheel::new();
+        let tasks: Vec<Task> = (0..20_000)
+            .map(|task_id| {
+                TaskBuilder::new(task_id)
+                    .with_frequency_repeated_by_seconds(1 + task_id % 7200)
+                    .spwan_async(TestTaskRunner::new())
+                    .unwrap()
+            })
+            .collect();
+        let results = wheel.add_tasks(tasks);
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(wheel.len(), 20_000);
+
+        for task_id in (0..20_000).map(TaskId::new) {
+            let info = wheel.get_task_tracking_info(task_id).unwrap();
+            let slot = wheel
+                .wheel(info.wheel_type)
+                .slots
+                .get(&info.slot_num)
+                .unwrap();
+            assert!(slot.task_map.contains_key(&task_id));
+        }
+        wheel.tick();
+        let arrived = wheel.take_arrived_tasks();
+        assert!(arrived.iter().all(|task| task.task_id.as_u64() % 7200 == 0));
+        assert!(!arrived.is_empty());
+    }
+
+    #[test]
+    fn test_disabled_task_skips_occurrences() {
+        let wheel = MulitWheel::new();
+        let task = TaskBuilder::new(600)
+            .with_frequency_repeated_by_seconds(2)
+            .spwan_async(TestTaskRunner::new())
+            .unwrap();
+        wheel.add_task(task).unwrap();
+        assert!(wheel.disable_task(TaskId::new(600)));
+        assert!(!wheel.disable_task(TaskId::new(601)));
+
+        for _ in 0..2 {
+            wheel.tick();
+            assert!(wheel.take_arrived_tasks().is_empty());
+        }
+        // The schedule advanced past the skipped occurrence and the task stays disabled
+        let info = wheel.get_task_tracking_info(TaskId::new(600)).unwrap();
+        assert!(!info.enabled);
+        assert_eq!(info.slot_num, 4);
+
+        assert!(wheel.enable_task(TaskId::new(600)));
+        wheel.tick();
+        assert!(wheel.take_arrived_tasks().is_empty());
+        wheel.tick();
+        let arrived = wheel.take_arrived_tasks();
+        assert_eq!(arrived.len(), 1);
+        assert_eq!(arrived[0].task_id, TaskId::new(600));
+    }
+
+    #[test]
+    fn test_task_location_counts_down() {
+        let wheel = MulitWheel::new();
+        let task = TaskBuilder::new(700)
+            .with_frequency_once_by_seconds(90)
+            .spwan_async(TestTaskRunner::new())
+            .unwrap();
+        wheel.add_task(task).unwrap();
+
+        let location = wheel.task_location(TaskId::new(700)).unwrap();
+        assert_eq!(location.wheel_type, WheelType::Minute);
+        assert_eq!(location.slot_num, 1);
+        assert_eq!(location.ticks_until_fire, 90);
+
+        for _ in 0..60 {
+            wheel.tick();
+        }
+        let location = wheel.task_location(TaskId::new(700)).unwrap();
+        assert_eq!(location.wheel_type, WheelType::Second);
+        assert_eq!(location.slot_num, 30);
+        assert_eq!(location.ticks_until_fire, 30);
+        assert!(wheel.task_location(TaskId::new(701)).is_none());
+    }
+
+    #[derive(Clone)]
+    struct ListSchedule(Vec<u64>);
+
+    impl crate::task::Schedule for ListSchedule {
+        fn next_alarm_timestamp(&mut self) -> Option<u64> {
+            (!self.0.is_empty()).then(|| self.0.remove(0))
+        }
+
+        fn peek_alarm_timestamp(&mut self) -> Option<u64> {
+            self.0.first().copied()
+        }
+    }
+
+    #[test]
+    fn test_custom_schedule_fires_at_listed_alarms() {
+        let wheel = MulitWheel::new();
+        let now = wheel.current_timestamp();
+        let task = TaskBuilder::new(800)
+            .with_schedule(ListSchedule(vec![now + 2, now + 5]))
+            .spwan_async(TestTaskRunner::new())
+            .unwrap();
+        wheel.add_task(task).unwrap();
+
+        let mut fired_at = vec![];
+        for tick in 1..=8 {
+            wheel.tick();
+            for task in wheel.take_arrived_tasks() {
+                fired_at.push(tick);
+                wheel.add_task(task).unwrap();
+            }
+        }
+        assert_eq!(fired_at, vec![2, 5]);
+        assert!(!wheel.contains(TaskId::new(800)));
+    }
+
+    #[test]
+    fn test_wheel_type_as_key_and_display() {
+        let wheel = MulitWheel::new();
+        for (task_id, seconds) in [(900, 5), (901, 10), (902, 90)] {
+            let task = TaskBuilder::new(task_id)
+                .with_frequency_once_by_seconds(seconds)
+                .spwan_async(TestTaskRunner::new())
+                .unwrap();
+            wheel.add_task(task).unwrap();
+        }
+
+        let mut per_wheel: HashMap<WheelType, usize> = HashMap::new();
+        for info in wheel.task_tracker_map.iter() {
+            *per_wheel.entry(info.wheel_type).or_default() += 1;
+        }
+        assert_eq!(per_wheel[&WheelType::Second], 2);
+        assert_eq!(per_wheel[&WheelType::Minute], 1);
+
+        assert_eq!(WheelType::Second.to_string(), "second");
+        assert_eq!(WheelType::Minute.to_string(), "minute");
+        assert_eq!(WheelType::Hour.to_string(), "hour");
+    }
+
+    #[test]
+    fn test_concurrent_add_remove_and_tick() {
+        let wheel = Arc::new(MulitWheel::new());
+        let (done_sender, done_receiver) = std::sync::mpsc::channel();
+
+        let mut handles = vec![];
+        for worker in 0..4u64 {
+            let wheel = wheel.clone();
+            let done_sender = done_sender.clone();
+            handles.push(std::thread::spawn(move || {
+                for i in 0..2000u64 {
+                    let task_id = TaskId::new(worker * 10_000 + i % 200);
+                    if i % 3 == 2 {
+                        wheel.remove_task(task_id);
+                    } else {
+                        let task = TaskBuilder::new(task_id)
+                            .with_frequency_repeated_by_seconds(1 + i % 150)
+                            .spwan_async(TestTaskRunner::new())
+                            .unwrap();
+                        let _ = wheel.add_task(task);
+                    }
+                }
+                done_sender.send(()).unwrap();
+            }));
+        }
+        handles.push(std::thread::spawn({
+            let wheel = wheel.clone();
+            move || {
+                for _ in 0..3000 {
+                    wheel.tick();
+                    for task in wheel.take_arrived_tasks() {
+                        let _ = wheel.add_task(task);
+                    }
+                }
+                done_sender.send(()).unwrap();
+            }
+        }));
+
+        for _ in 0..handles.len() {
+            done_receiver
+                .recv_timeout(std::time::Duration::from_secs(30))
+                .expect("wheel operations deadlocked");
+        }
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        // Every tracked task sits in the slot its tracking information points at
+        let mut scheduled = 0;
+        wheel.for_each_task(|_| scheduled += 1);
+        assert_eq!(scheduled, wheel.len());
+        for info in wheel.task_tracker_map.iter() {
+            assert!(
+                wheel
+                    .wheel(info.wheel_type)
+                    .task_ids(info.slot_num)
+                    .contains(&info.task_id)
+            );
+        }
+    }
 }
@@ -1,8 +1,136 @@
-use std::time::SystemTime;
+pub mod rand;
+
+use std::{
+    pin::pin,
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+    time::{Duration, SystemTime},
+};
+
+use crate::error::{InvalidFrequencyReason, TaskError};
 
 pub fn timestamp() -> u64 {
+    since_epoch().as_secs()
+}
+
+/// The time since the UNIX epoch, for a timestamp finer than `timestamp`.
+pub(crate) fn since_epoch() -> Duration {
     match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
-        Ok(n) => n.as_secs(),
+        Ok(n) => n,
         Err(_) => panic!("SystemTime before UNIX EPOCH!"),
     }
 }
+
+/// A unit of time a frequency may be given in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimeUnit {
+    Seconds,
+    Minutes,
+    Hours,
+    Days,
+}
+
+impl TimeUnit {
+    /// The number of seconds in one of this unit.
+    pub const fn seconds(self) -> u64 {
+        match self {
+            TimeUnit::Seconds => 1,
+            TimeUnit::Minutes => 60,
+            TimeUnit::Hours => 60 * 60,
+            TimeUnit::Days => 24 * 60 * 60,
+        }
+    }
+}
+
+impl std::fmt::Display for TimeUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            TimeUnit::Seconds => "seconds",
+            TimeUnit::Minutes => "minutes",
+            TimeUnit::Hours => "hours",
+            TimeUnit::Days => "days",
+        })
+    }
+}
+
+/// `count` of `unit` in seconds, or an `InvalidFrequency` with reason `Overflow` carrying
+/// `count` if that doesn't fit in a `u64`.
+pub fn checked_seconds_from(unit: TimeUnit, count: u64) -> Result<u64, TaskError> {
+    count.checked_mul(unit.seconds()).ok_or_else(|| {
+        TaskError::invalid_frequency(
+            InvalidFrequencyReason::Overflow,
+            Some(count),
+            format!("{count} {unit} overflows in seconds"),
+        )
+    })
+}
+
+/// Drive a future to completion on the current thread, parking it while the future is pending.
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNITS: [TimeUnit; 4] = [
+        TimeUnit::Seconds,
+        TimeUnit::Minutes,
+        TimeUnit::Hours,
+        TimeUnit::Days,
+    ];
+
+    #[test]
+    fn test_checked_seconds_from_converts() {
+        assert_eq!(checked_seconds_from(TimeUnit::Seconds, 7).unwrap(), 7);
+        assert_eq!(checked_seconds_from(TimeUnit::Minutes, 3).unwrap(), 180);
+        assert_eq!(checked_seconds_from(TimeUnit::Hours, 2).unwrap(), 7200);
+        assert_eq!(checked_seconds_from(TimeUnit::Days, 2).unwrap(), 172_800);
+        for unit in UNITS {
+            assert_eq!(checked_seconds_from(unit, 0).unwrap(), 0);
+            let max = u64::MAX / unit.seconds();
+            assert_eq!(
+                checked_seconds_from(unit, max).unwrap(),
+                max * unit.seconds()
+            );
+        }
+    }
+
+    #[test]
+    fn test_checked_seconds_from_overflow_is_clean_error() {
+        assert_eq!(
+            checked_seconds_from(TimeUnit::Seconds, u64::MAX).unwrap(),
+            u64::MAX
+        );
+        for unit in &UNITS[1..] {
+            for count in [u64::MAX, u64::MAX / unit.seconds() + 1] {
+                assert!(matches!(
+                    checked_seconds_from(*unit, count),
+                    Err(TaskError::InvalidFrequency {
+                        reason: InvalidFrequencyReason::Overflow,
+                        value: Some(value),
+                        ..
+                    }) if value == count
+                ));
+            }
+        }
+    }
+}
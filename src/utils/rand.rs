@@ -0,0 +1,68 @@
+use std::{
+    collections::hash_map::RandomState,
+    fmt,
+    hash::BuildHasher,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// The source of the random numbers of a timer, e.g. for `TaskBuilder::with_jitter`.
+///
+/// The default is `OsRandSource`; tests use a `SeededRandSource` to make randomized
+/// scheduling reproducible, see `TimerBuilder::with_rand_source`.
+pub trait RandSource: Send + Sync + fmt::Debug + 'static {
+    fn next_u64(&self) -> u64;
+
+    /// A number in `0..bound`, 0 for a bound of 0.
+    fn below(&self, bound: u64) -> u64 {
+        match bound {
+            0 => 0,
+            bound => self.next_u64() % bound,
+        }
+    }
+}
+
+/// Random numbers keyed by the OS randomness behind the std hasher.
+#[derive(Debug, Default)]
+pub struct OsRandSource {
+    state: RandomState,
+    counter: AtomicU64,
+}
+
+impl OsRandSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl RandSource for OsRandSource {
+    fn next_u64(&self) -> u64 {
+        self.state
+            .hash_one(self.counter.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// A deterministic sequence (SplitMix64) from a seed, the same seed gives the same numbers.
+#[derive(Debug)]
+pub struct SeededRandSource {
+    state: AtomicU64,
+}
+
+impl SeededRandSource {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: AtomicU64::new(seed),
+        }
+    }
+}
+
+impl RandSource for SeededRandSource {
+    fn next_u64(&self) -> u64 {
+        let mut z = self
+            .state
+            .fetch_add(0x9E37_79B9_7F4A_7C15, Ordering::Relaxed)
+            .wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}